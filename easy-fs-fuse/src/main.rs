@@ -56,28 +56,42 @@ fn easy_fs_pack() -> std::io::Result<()> {
             .write(true)
             .create(true)
             .open(format!("{}{}", target_path, "fs.img"))?;
-        f.set_len(8192 * 512).unwrap();
+        f.set_len(32768 * 512).unwrap();
         f
     })));
-    // 4MiB, at most 4095 files
+    // 16MiB, at most 4095 files, with room left for the swap file of the kernel
     let efs = EasyFileSystem::create(
         block_file.clone(),
-        8192,
+        32768,
         1,
     );
     let root_inode = Arc::new(EasyFileSystem::root_inode(&efs));
-    let apps: Vec<_> = read_dir(src_path)
+    let mut apps: Vec<(String, String)> = read_dir(src_path)
         .unwrap()
         .into_iter()
         .map(|dir_entry| {
             let mut name_with_ext = dir_entry.unwrap().file_name().into_string().unwrap();
             name_with_ext.drain(name_with_ext.find('.').unwrap()..name_with_ext.len());
-            name_with_ext
+            let host_path = format!("{}{}", target_path, name_with_ext);
+            (name_with_ext, host_path)
         })
         .collect();
-    for app in apps {
+    // the dynamic loader, shared libraries and programs linked with them
+    // are built apart, Linux programs come prebuilt, they go in under their
+    // own names
+    for extra_dir in ["dynamic", "linux_apps"].iter() {
+        if let Ok(dir) = read_dir(format!("{}{}", target_path, extra_dir)) {
+            for dir_entry in dir {
+                let dir_entry = dir_entry.unwrap();
+                let name = dir_entry.file_name().into_string().unwrap();
+                let host_path = dir_entry.path().into_os_string().into_string().unwrap();
+                apps.push((name, host_path));
+            }
+        }
+    }
+    for (app, host_path) in apps {
         // load app data from host file system
-        let mut host_file = File::open(format!("{}{}", target_path, app)).unwrap();
+        let mut host_file = File::open(host_path).unwrap();
         let mut all_data: Vec<u8> = Vec::new();
         host_file.read_to_end(&mut all_data).unwrap();
         // create a file in easy-fs
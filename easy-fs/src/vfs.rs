@@ -5,6 +5,7 @@ use super::{
     DirEntry,
     EasyFileSystem,
     DIRENT_SZ,
+    BLOCK_SZ,
     get_block_cache,
 };
 use alloc::sync::Arc;
@@ -176,6 +177,17 @@ impl Inode {
         })
     }
 
+    /// Size of the file in bytes.
+    pub fn size(&self) -> usize {
+        let _fs = self.fs.lock();
+        self.read_disk_inode(|disk_inode| disk_inode.size as usize)
+    }
+
+    /// Where the inode is on disk, which tells it apart from the others.
+    pub fn id(&self) -> usize {
+        self.block_id * BLOCK_SZ + self.block_offset
+    }
+
     pub fn read_at(&self, offset: usize, buf: &mut [u8]) -> usize {
         let _fs = self.fs.lock();
         self.read_disk_inode(|disk_inode| {
@@ -1,14 +1,53 @@
 #[allow(unused)]
 
+/// Initial size of a user stack, it grows downwards on page faults.
 pub const USER_STACK_SIZE: usize = 4096 * 2;
+/// Default limit on the size of a user stack, which a process may change up to
+/// `USER_STACK_LIMIT_MAX`, the room each thread has for its stack.
+pub const USER_STACK_LIMIT: usize = 0x1_0000;
+pub const USER_STACK_LIMIT_MAX: usize = 0x80_0000;
+/// A stack never grows closer than this to the area below it.
+pub const USER_STACK_GUARD_GAP: usize = 0x10_0000;
 pub const KERNEL_STACK_SIZE: usize = 4096 * 2;
 pub const KERNEL_HEAP_SIZE: usize = 0x20_0000;
 pub const MEMORY_END: usize = 0x80800000;
 pub const PAGE_SIZE: usize = 0x1000;
 pub const PAGE_SIZE_BITS: usize = 0xc;
+pub const MAX_FD_NUM: usize = 128;
+/// Harts with an id from 0 up to this are brought up, if the board has them.
+pub const MAX_HARTS: usize = 4;
+pub const BOOT_STACK_SIZE: usize = 4096 * 16;
+/// Priority of a thread unless it calls set_priority, at least 2.
+pub const DEFAULT_PRIORITY: usize = 16;
+/// Highest priority set_priority accepts, a thread of this priority still
+/// advances by a stride of more than 1 each time it is picked.
+pub const MAX_PRIORITY: usize = 1 << 16;
+/// User pages are swapped out to a file of this many pages on easy-fs.
+pub const SWAP_SLOTS: usize = 2048;
+/// Frames kept free for page tables while user pages are faulted in,
+/// other pages are swapped out to make room for them.
+pub const FRAMES_RESERVED: usize = 16;
 
 pub const TRAMPOLINE: usize = usize::MAX - PAGE_SIZE + 1;
+/// Trap context of the main thread, those of other threads are right below it.
 pub const TRAP_CONTEXT: usize = TRAMPOLINE - PAGE_SIZE;
+/// End of the lower half of Sv39, where all user areas lie. Addresses above it
+/// are not canonical, save for the trampoline and trap contexts at the top.
+pub const USER_SPACE_END: usize = 0x40_0000_0000;
+/// User stacks are laid out downwards from here.
+pub const USER_STACK_TOP: usize = USER_SPACE_END;
+/// mmap places mappings in `[MMAP_BASE, MMAP_TOP)` unless given an address,
+/// no mapping may reach above `MMAP_TOP`.
+pub const MMAP_BASE: usize = 0x10_0000_0000;
+pub const MMAP_TOP: usize = 0x20_0000_0000;
+/// Position-independent executables are loaded at `DYN_BASE`, the
+/// interpreter they ask for, i.e. the dynamic loader, at `INTERP_BASE`.
+/// The heap grows from the end of the executable up to `MMAP_BASE`, so the
+/// interpreter goes above the mmap range, out of the way of both. It has to
+/// fit below `INTERP_TOP`, which leaves 64 GiB to the user stacks.
+pub const DYN_BASE: usize = 0x1000_0000;
+pub const INTERP_BASE: usize = MMAP_TOP;
+pub const INTERP_TOP: usize = USER_STACK_TOP - 0x10_0000_0000;
 
 #[cfg(feature = "board_k210")]
 pub const CLOCK_FREQ: usize = 403000000 / 62;
@@ -16,6 +55,13 @@ pub const CLOCK_FREQ: usize = 403000000 / 62;
 #[cfg(feature = "board_qemu")]
 pub const CLOCK_FREQ: usize = 12500000;
 
+/// Number of harts of the board, for an SBI which cannot tell.
+#[cfg(feature = "board_k210")]
+pub const BOARD_HARTS: usize = 2;
+
+#[cfg(feature = "board_qemu")]
+pub const BOARD_HARTS: usize = MAX_HARTS;
+
 #[cfg(feature = "board_qemu")]
 pub const MMIO: &[(usize, usize)] = &[
     (0x10001000, 0x1000),
@@ -1,5 +1,6 @@
 use core::fmt::{self, Write};
 use crate::sbi::console_putchar;
+use spin::Mutex;
 
 struct Stdout;
 
@@ -12,8 +13,19 @@ impl Write for Stdout {
     }
 }
 
+/// Keeps the lines printed by different harts apart.
+static STDOUT: Mutex<Stdout> = Mutex::new(Stdout);
+
 pub fn print(args: fmt::Arguments) {
-    Stdout.write_fmt(args).unwrap();
+    STDOUT.lock().write_fmt(args).unwrap();
+}
+
+/// Write bytes as they are, they need not be valid UTF-8.
+pub fn print_bytes(bytes: &[u8]) {
+    let _stdout = STDOUT.lock();
+    for b in bytes {
+        console_putchar(*b as usize);
+    }
 }
 
 #[macro_export]
@@ -1,12 +1,20 @@
     .section .text.entry
     .globl _start
 _start:
-    la sp, boot_stack_top
+    # a0 = hart id, which is kept in tp while in the kernel
+    mv tp, a0
+    # harts beyond MAX_HARTS in config.rs are parked
+    la t0, boot_hart_count
+    ld t0, 0(t0)
+    bgeu a0, t0, park
+    # each hart gets a boot stack of its own, all of them in smp.rs
+    addi t0, a0, 1
+    la t1, boot_stack_size
+    ld t1, 0(t1)
+    mul t0, t0, t1
+    la sp, boot_stack
+    add sp, sp, t0
     call rust_main
-
-    .section .bss.stack
-    .globl boot_stack
-boot_stack:
-    .space 4096 * 16
-    .globl boot_stack_top
-boot_stack_top:
\ No newline at end of file
+park:
+    wfi
+    j park
@@ -0,0 +1,43 @@
+/// Error numbers returned to user space as negative values, following the
+/// numbering used by Linux so that user programs may decode them the same way.
+#[repr(isize)]
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum SysError {
+    EPERM = 1,
+    ENOENT = 2,
+    ESRCH = 3,
+    EINTR = 4,
+    EIO = 5,
+    E2BIG = 7,
+    ENOEXEC = 8,
+    EBADF = 9,
+    ECHILD = 10,
+    EAGAIN = 11,
+    ENOMEM = 12,
+    EACCES = 13,
+    EFAULT = 14,
+    EBUSY = 16,
+    EEXIST = 17,
+    ENODEV = 19,
+    ENOTDIR = 20,
+    EISDIR = 21,
+    EINVAL = 22,
+    EMFILE = 24,
+    ENOTTY = 25,
+    ENOSPC = 28,
+    ESPIPE = 29,
+    EPIPE = 32,
+    ERANGE = 34,
+    EDEADLK = 35,
+    ENOSYS = 38,
+    ELOOP = 40,
+}
+
+pub type SysResult<T = usize> = Result<T, SysError>;
+
+impl SysError {
+    /// The value written to a0 when a syscall fails.
+    pub fn as_ret(self) -> isize {
+        -(self as isize)
+    }
+}
@@ -8,7 +8,7 @@ use lazy_static::*;
 use bitflags::*;
 use alloc::vec::Vec;
 use spin::Mutex;
-use super::File;
+use super::{File, FileStat, FileKind};
 use crate::mm::UserBuffer;
 
 pub struct OSInode {
@@ -156,4 +156,8 @@ impl File for OSInode {
         }
         total_write_size
     }
+    fn stat(&self) -> FileStat {
+        let inner = self.inner.lock();
+        FileStat { kind: FileKind::Regular, size: inner.inode.size(), ino: inner.inode.id() }
+    }
 }
\ No newline at end of file
@@ -4,13 +4,33 @@ mod inode;
 
 use crate::mm::UserBuffer;
 
+/// What an open file is, as told by the mode bits fstat reports.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum FileKind {
+    Regular,
+    CharDevice,
+    Fifo,
+}
+
+/// What fstat tells about an open file.
+pub struct FileStat {
+    pub kind: FileKind,
+    /// In bytes, 0 unless a regular file.
+    pub size: usize,
+    /// Tells files of the same kind apart.
+    pub ino: usize,
+}
+
 pub trait File : Send + Sync {
     fn readable(&self) -> bool;
     fn writable(&self) -> bool;
     fn read(&self, buf: UserBuffer) -> usize;
     fn write(&self, buf: UserBuffer) -> usize;
+    /// Whether this is the console, the only file ioctl works on.
+    fn is_tty(&self) -> bool { false }
+    fn stat(&self) -> FileStat;
 }
 
 pub use pipe::{Pipe, make_pipe};
-pub use stdio::{Stdin, Stdout};
-pub use inode::{OSInode, open_file, OpenFlags, list_apps};
\ No newline at end of file
+pub use stdio::{Stdin, Stdout, poll_console, foreground_pgid, set_foreground_pgid};
+pub use inode::{OSInode, open_file, OpenFlags, list_apps, ROOT_INODE};
\ No newline at end of file
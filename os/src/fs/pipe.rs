@@ -1,4 +1,4 @@
-use super::File;
+use super::{File, FileStat, FileKind};
 use alloc::sync::{Arc, Weak};
 use spin::Mutex;
 use crate::mm::{
@@ -164,4 +164,8 @@ impl File for Pipe {
             }
         }
     }
+    fn stat(&self) -> FileStat {
+        // both ends share the buffer
+        FileStat { kind: FileKind::Fifo, size: 0, ino: Arc::as_ptr(&self.buffer) as usize }
+    }
 }
\ No newline at end of file
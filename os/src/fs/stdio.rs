@@ -1,7 +1,75 @@
-use super::File;
+use super::{File, FileStat, FileKind};
 use crate::mm::{UserBuffer};
 use crate::sbi::console_getchar;
-use crate::task::suspend_current_and_run_next;
+use crate::console::print_bytes;
+use alloc::collections::VecDeque;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use lazy_static::*;
+use spin::Mutex;
+use crate::task::{
+    current_task,
+    current_process,
+    block_current_and_run_next,
+    kill_process_group,
+    SignalFlags,
+};
+use crate::timer::{add_timer, remove_timer, next_tick_time, get_time};
+
+const CTRL_C: u8 = 0x03;
+const CTRL_Z: u8 = 0x1a;
+
+/// Process group allowed to read from the console, the group of initproc,
+/// which is the first process and has pid 0, until someone changes it.
+static FOREGROUND_PGID: AtomicUsize = AtomicUsize::new(0);
+/// The console is polled at most once a tick.
+static NEXT_POLL: AtomicUsize = AtomicUsize::new(0);
+
+lazy_static! {
+    /// Characters typed but not read yet.
+    static ref INPUT: Mutex<VecDeque<u8>> = Mutex::new(VecDeque::new());
+}
+
+pub fn foreground_pgid() -> usize {
+    FOREGROUND_PGID.load(Ordering::Acquire)
+}
+
+pub fn set_foreground_pgid(pgid: usize) {
+    FOREGROUND_PGID.store(pgid, Ordering::Release);
+}
+
+/// Move what has been typed into the input buffer. Ctrl-C and Ctrl-Z are not
+/// read by anyone but send SIGINT and SIGTSTP to the foreground process group.
+/// It is called on timer interrupts and by idle harts, so that the keys work
+/// even if nobody reads the console.
+pub fn poll_console() {
+    let now = get_time();
+    if now < NEXT_POLL.load(Ordering::Relaxed) {
+        return;
+    }
+    let mut signals = Vec::new();
+    {
+        let mut input = match INPUT.try_lock() {
+            Some(input) => input,
+            None => return,
+        };
+        NEXT_POLL.store(next_tick_time(), Ordering::Relaxed);
+        loop {
+            match console_getchar() {
+                c @ 1..=0xff => match c as u8 {
+                    CTRL_C => signals.push(SignalFlags::SIGINT),
+                    CTRL_Z => signals.push(SignalFlags::SIGTSTP),
+                    ch => input.push_back(ch),
+                },
+                _ => break,
+            }
+        }
+    }
+    for signal in signals {
+        kill_process_group(foreground_pgid(), signal);
+    }
+}
 
 pub struct Stdin;
 
@@ -10,26 +78,40 @@ pub struct Stdout;
 impl File for Stdin {
     fn readable(&self) -> bool { true }
     fn writable(&self) -> bool { false }
-    fn read(&self, mut user_buf: UserBuffer) -> usize {
-        assert_eq!(user_buf.len(), 1);
-        // busy loop
-        let mut c: usize;
+    /// Wait for at least one character, then read as many as have been typed.
+    fn read(&self, user_buf: UserBuffer) -> usize {
+        let len = user_buf.len();
+        if len == 0 {
+            return 0;
+        }
+        // wait for input once a tick, sleeping in between so that the
+        // scheduler sees the reader as an interactive thread; a process
+        // outside the foreground group waits until it is moved there
         loop {
-            c = console_getchar();
-            if c == 0 {
-                suspend_current_and_run_next();
-                continue;
-            } else {
-                break;
+            poll_console();
+            if current_process().acquire_inner_lock().pgid == foreground_pgid() {
+                let mut input = INPUT.lock();
+                if !input.is_empty() {
+                    let count = len.min(input.len());
+                    for (ptr, ch) in user_buf.into_iter().zip(input.drain(..count)) {
+                        unsafe { ptr.write_volatile(ch); }
+                    }
+                    return count;
+                }
             }
+            let task = current_task().unwrap();
+            add_timer(next_tick_time(), Arc::clone(&task));
+            block_current_and_run_next();
+            remove_timer(&task);
         }
-        let ch = c as u8;
-        unsafe { user_buf.buffers[0].as_mut_ptr().write_volatile(ch); }
-        1
     }
     fn write(&self, _user_buf: UserBuffer) -> usize {
         panic!("Cannot write to stdin!");
     }
+    fn is_tty(&self) -> bool { true }
+    fn stat(&self) -> FileStat {
+        FileStat { kind: FileKind::CharDevice, size: 0, ino: 0 }
+    }
 }
 
 impl File for Stdout {
@@ -40,8 +122,12 @@ impl File for Stdout {
     }
     fn write(&self, user_buf: UserBuffer) -> usize {
         for buffer in user_buf.buffers.iter() {
-            print!("{}", core::str::from_utf8(*buffer).unwrap());
+            print_bytes(*buffer);
         }
         user_buf.len()
     }
-}
\ No newline at end of file
+    fn is_tty(&self) -> bool { true }
+    fn stat(&self) -> FileStat {
+        FileStat { kind: FileKind::CharDevice, size: 0, ino: 0 }
+    }
+}
@@ -14,15 +14,18 @@ extern crate bitflags;
 #[macro_use]
 mod console;
 mod lang_items;
+mod error;
 mod sbi;
 mod syscall;
 mod trap;
 mod config;
 mod task;
+mod sync;
 mod timer;
 mod mm;
 mod fs;
 mod drivers;
+mod smp;
 
 global_asm!(include_str!("entry.asm"));
 
@@ -36,17 +39,30 @@ fn clear_bss() {
     });
 }
 
+/// Every hart comes here from entry.asm, the first one initializes the kernel.
 #[no_mangle]
-pub fn rust_main() -> ! {
+pub fn rust_main(hart_id: usize) -> ! {
+    if !smp::claim_boot_hart(hart_id) {
+        smp::wait_for_boot();
+        mm::init_hart();
+        hart_main(hart_id);
+    }
     clear_bss();
     println!("[kernel] Hello, world!");
     mm::init();
     mm::remap_test();
+    fs::list_apps();
+    task::add_initproc();
+    smp::boot_other_harts();
+    hart_main(hart_id);
+}
+
+fn hart_main(hart_id: usize) -> ! {
     trap::init();
     trap::enable_timer_interrupt();
+    trap::enable_software_interrupt();
     timer::set_next_trigger();
-    fs::list_apps();
-    task::add_initproc();
+    println!("[kernel] hart {} is running", hart_id);
     task::run_tasks();
-    panic!("Unreachable in rust_main!");
+    panic!("Unreachable in hart_main!");
 }
\ No newline at end of file
@@ -0,0 +1,244 @@
+use xmas_elf::ElfFile;
+use xmas_elf::header::{Class, Data};
+use xmas_elf::program::Type;
+use alloc::vec::Vec;
+use alloc::string::String;
+use alloc::sync::Arc;
+use core::ops::Range;
+use super::MapPermission;
+use crate::config::PAGE_SIZE;
+use crate::error::SysError;
+
+/// Sizes of the elf header and of a program header in a 64-bit file.
+const EHDR_SIZE: usize = 64;
+const PHDR_SIZE: usize = 56;
+/// Fields of the elf header after the identification bytes.
+const E_TYPE_OFFSET: usize = 16;
+const E_MACHINE_OFFSET: usize = 18;
+const ET_EXEC: u16 = 2;
+const ET_DYN: u16 = 3;
+const EM_RISCV: u16 = 243;
+
+/// Why an elf file cannot be loaded, each is reported as `ENOEXEC` to user space.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum LoadError {
+    /// Too short or without the elf magic.
+    NotElf,
+    /// Not a 64-bit little-endian file.
+    BadClass,
+    /// Built for another architecture than RISC-V.
+    BadMachine,
+    /// Neither an executable nor a position-independent one.
+    BadType,
+    /// The program header table is not within the file.
+    BadProgramHeaders,
+    /// A segment refers to bytes beyond the end of the file.
+    SegmentOutOfFile,
+    /// A segment is larger in the file than in memory, or wraps around.
+    BadSegment,
+    /// A segment reaches the mmap areas, the stacks, the trap contexts or the trampoline.
+    BadAddress,
+    /// Two segments share a page.
+    OverlappingSegments,
+    /// A segment is both writable and executable.
+    WritableAndExecutable,
+    /// The alignment of a segment is not a power of two, or its address
+    /// and offset do not agree with it.
+    BadAlignment,
+    /// The entry point is not in an executable segment.
+    BadEntry,
+    /// The path in `PT_INTERP` is malformed, or the interpreter is not
+    /// position-independent or asks for an interpreter itself.
+    BadInterpreter,
+}
+
+impl From<LoadError> for SysError {
+    fn from(_: LoadError) -> Self {
+        SysError::ENOEXEC
+    }
+}
+
+/// Where a loaded elf starts and where its program headers are in user space.
+pub struct ElfInfo {
+    pub entry_point: usize,
+    /// 0 if the program headers are not loaded.
+    pub phdr: usize,
+    pub phent: usize,
+    pub phnum: usize,
+    /// Where the interpreter is loaded, 0 if there is none.
+    pub base: usize,
+    /// Where the main thread starts, the entry point of the interpreter if any.
+    pub start: usize,
+}
+
+/// A program along with the interpreter named by its `PT_INTERP`, if any.
+pub struct Executable {
+    pub elf_data: Arc<Vec<u8>>,
+    pub interp_data: Option<Arc<Vec<u8>>>,
+}
+
+impl Executable {
+    /// A statically linked program.
+    pub fn new(elf_data: Arc<Vec<u8>>) -> Self {
+        Self { elf_data, interp_data: None }
+    }
+}
+
+/// A loadable segment, `file_size` bytes at `offset` of the file go to
+/// `start_va` and the rest up to `end_va` is zeroed.
+pub struct Segment {
+    pub start_va: usize,
+    pub end_va: usize,
+    pub offset: usize,
+    pub file_size: usize,
+    pub perm: MapPermission,
+}
+
+fn read_u16(data: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([data[offset], data[offset + 1]])
+}
+
+/// Check the elf header and that the program header table is within the file.
+fn check_header(data: &[u8]) -> Result<ElfFile, LoadError> {
+    if data.len() < EHDR_SIZE {
+        return Err(LoadError::NotElf);
+    }
+    let elf = ElfFile::new(data).map_err(|_| LoadError::NotElf)?;
+    let header = elf.header;
+    if header.pt1.class() != Class::SixtyFour || header.pt1.data() != Data::LittleEndian {
+        return Err(LoadError::BadClass);
+    }
+    if read_u16(data, E_MACHINE_OFFSET) != EM_RISCV {
+        return Err(LoadError::BadMachine);
+    }
+    let e_type = read_u16(data, E_TYPE_OFFSET);
+    if e_type != ET_EXEC && e_type != ET_DYN {
+        return Err(LoadError::BadType);
+    }
+    let ph_count = header.pt2.ph_count() as usize;
+    let ph_offset = header.pt2.ph_offset() as usize;
+    let ph_entry_size = header.pt2.ph_entry_size() as usize;
+    if ph_count == 0
+        || ph_entry_size != PHDR_SIZE
+        || ph_offset % core::mem::align_of::<u64>() != 0
+        || ph_offset.checked_add(ph_count * PHDR_SIZE).map_or(true, |end| end > data.len()) {
+        return Err(LoadError::BadProgramHeaders);
+    }
+    Ok(elf)
+}
+
+/// The path in the `PT_INTERP` segment, if the file has one.
+pub fn interpreter_path(data: &[u8]) -> Result<Option<String>, LoadError> {
+    let elf = check_header(data)?;
+    for i in 0..elf.header.pt2.ph_count() {
+        let ph = elf.program_header(i).map_err(|_| LoadError::BadProgramHeaders)?;
+        if ph.get_type() != Ok(Type::Interp) {
+            continue;
+        }
+        let offset = ph.offset() as usize;
+        let end = offset
+            .checked_add(ph.file_size() as usize)
+            .filter(|end| *end <= data.len())
+            .ok_or(LoadError::SegmentOutOfFile)?;
+        // a null-terminated string
+        let path = &data[offset..end];
+        let len = path.iter().position(|c| *c == 0).ok_or(LoadError::BadInterpreter)?;
+        let path = core::str::from_utf8(&path[..len]).map_err(|_| LoadError::BadInterpreter)?;
+        if path.is_empty() {
+            return Err(LoadError::BadInterpreter);
+        }
+        return Ok(Some(String::from(path)));
+    }
+    Ok(None)
+}
+
+/// Check the whole file before anything is mapped, xmas-elf indexes the
+/// data without bounds checks of its own. A position-independent file is
+/// placed at `base`, which an `interpreter` has to be. Every segment has to
+/// lie within `va_range`, the rest of user space is laid out by the kernel.
+pub fn parse_elf(
+    data: &[u8],
+    base: usize,
+    interpreter: bool,
+    va_range: Range<usize>,
+) -> Result<(Vec<Segment>, ElfInfo), LoadError> {
+    let elf = check_header(data)?;
+    let header = elf.header;
+    let bias = match read_u16(data, E_TYPE_OFFSET) {
+        ET_DYN => base,
+        _ if interpreter => return Err(LoadError::BadInterpreter),
+        _ => 0,
+    };
+    let ph_count = header.pt2.ph_count() as usize;
+    let ph_offset = header.pt2.ph_offset() as usize;
+    let ph_entry_size = header.pt2.ph_entry_size() as usize;
+    let mut segments: Vec<Segment> = Vec::new();
+    let mut phdr = 0;
+    for i in 0..ph_count {
+        let ph = elf.program_header(i as u16).map_err(|_| LoadError::BadProgramHeaders)?;
+        match ph.get_type() {
+            Ok(Type::Load) if ph.mem_size() > 0 => {}
+            Ok(Type::Phdr) => {
+                phdr = (ph.virtual_addr() as usize).wrapping_add(bias);
+                continue;
+            }
+            Ok(Type::Interp) if interpreter => return Err(LoadError::BadInterpreter),
+            _ => continue,
+        }
+        let offset = ph.offset() as usize;
+        let file_size = ph.file_size() as usize;
+        let mem_size = ph.mem_size() as usize;
+        if offset.checked_add(file_size).map_or(true, |end| end > data.len()) {
+            return Err(LoadError::SegmentOutOfFile);
+        }
+        if file_size > mem_size {
+            return Err(LoadError::BadSegment);
+        }
+        let start_va = (ph.virtual_addr() as usize).checked_add(bias).ok_or(LoadError::BadSegment)?;
+        let end_va = start_va.checked_add(mem_size).ok_or(LoadError::BadSegment)?;
+        if start_va < va_range.start || end_va > va_range.end {
+            return Err(LoadError::BadAddress);
+        }
+        let align = ph.align() as usize;
+        if align > 1 && (!align.is_power_of_two() || start_va % align != offset % align) {
+            return Err(LoadError::BadAlignment);
+        }
+        let flags = ph.flags();
+        if flags.is_write() && flags.is_execute() {
+            return Err(LoadError::WritableAndExecutable);
+        }
+        let mut perm = MapPermission::U;
+        if flags.is_read() { perm |= MapPermission::R; }
+        if flags.is_write() { perm |= MapPermission::W; }
+        if flags.is_execute() { perm |= MapPermission::X; }
+        // the program headers are usually loaded along with the first segment
+        if phdr == 0 && (offset..offset + file_size).contains(&ph_offset) {
+            phdr = start_va + ph_offset - offset;
+        }
+        segments.push(Segment { start_va, end_va, offset, file_size, perm });
+    }
+    // each page belongs to a single area
+    segments.sort_by_key(|segment| segment.start_va);
+    for pair in segments.windows(2) {
+        let end_page = (pair[0].end_va + PAGE_SIZE - 1) / PAGE_SIZE;
+        if end_page > pair[1].start_va / PAGE_SIZE {
+            return Err(LoadError::OverlappingSegments);
+        }
+    }
+    let entry_point = (header.pt2.entry_point() as usize).wrapping_add(bias);
+    if !segments.iter().any(|segment| {
+        segment.perm.contains(MapPermission::X)
+            && (segment.start_va..segment.end_va).contains(&entry_point)
+    }) {
+        return Err(LoadError::BadEntry);
+    }
+    let elf_info = ElfInfo {
+        entry_point,
+        phdr,
+        phent: ph_entry_size,
+        phnum: ph_count,
+        base: 0,
+        start: entry_point,
+    };
+    Ok((segments, elf_info))
+}
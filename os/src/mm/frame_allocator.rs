@@ -36,6 +36,7 @@ trait FrameAllocator {
     fn new() -> Self;
     fn alloc(&mut self) -> Option<PhysPageNum>;
     fn dealloc(&mut self, ppn: PhysPageNum);
+    fn remaining(&self) -> usize;
 }
 
 pub struct StackFrameAllocator {
@@ -83,6 +84,9 @@ impl FrameAllocator for StackFrameAllocator {
         // recycle
         self.recycled.push(ppn);
     }
+    fn remaining(&self) -> usize {
+        self.end - self.current + self.recycled.len()
+    }
 }
 
 type FrameAllocatorImpl = StackFrameAllocator;
@@ -114,6 +118,13 @@ pub fn frame_dealloc(ppn: PhysPageNum) {
         .dealloc(ppn);
 }
 
+/// Number of frames which can still be allocated.
+pub fn frame_remaining() -> usize {
+    FRAME_ALLOCATOR
+        .lock()
+        .remaining()
+}
+
 #[allow(unused)]
 pub fn frame_allocator_test() {
     let mut v: Vec<FrameTracker> = Vec::new();
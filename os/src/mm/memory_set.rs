@@ -1,7 +1,10 @@
 use super::{PageTable, PageTableEntry, PTEFlags};
 use super::{VirtPageNum, VirtAddr, PhysPageNum, PhysAddr};
-use super::{FrameTracker, frame_alloc};
+use super::{FrameTracker, frame_alloc, frame_remaining};
 use super::{VPNRange, StepByOne};
+use super::swap::SwapSlot;
+use super::elf::{parse_elf, ElfInfo, LoadError, Executable, Segment};
+use crate::error::{SysError, SysResult};
 use alloc::collections::BTreeMap;
 use alloc::vec::Vec;
 use riscv::register::satp;
@@ -12,10 +15,18 @@ use crate::config::{
     MEMORY_END,
     PAGE_SIZE,
     TRAMPOLINE,
-    TRAP_CONTEXT,
-    USER_STACK_SIZE,
     MMIO,
+    USER_STACK_LIMIT,
+    USER_STACK_GUARD_GAP,
+    FRAMES_RESERVED,
+    DYN_BASE,
+    INTERP_BASE,
+    INTERP_TOP,
+    MMAP_BASE,
+    USER_SPACE_END,
 };
+use crate::task::swap_out_page;
+use crate::smp::tlb_shootdown;
 
 extern "C" {
     fn stext();
@@ -40,9 +51,20 @@ pub fn kernel_token() -> usize {
     KERNEL_SPACE.lock().token()
 }
 
+/// Swap out pages of user processes until `FRAMES_RESERVED` frames are free,
+/// before the kernel maps frames of its own, i.e. kernel stacks. Swapping
+/// out may need the kernel space, which must not be locked by the caller.
+pub fn reserve_kernel_frames() {
+    while frame_remaining() < FRAMES_RESERVED && swap_out_page() {}
+}
+
 pub struct MemorySet {
     page_table: PageTable,
     areas: Vec<MapArea>,
+    /// Stack areas may grow downwards up to this many bytes.
+    stack_limit: usize,
+    /// Where the clock looking for a page to swap out goes on from.
+    clock_hand: VirtPageNum,
 }
 
 impl MemorySet {
@@ -50,33 +72,138 @@ impl MemorySet {
         Self {
             page_table: PageTable::new(),
             areas: Vec::new(),
+            stack_limit: USER_STACK_LIMIT,
+            clock_hand: VirtPageNum(0),
         }
     }
+    pub fn stack_limit(&self) -> usize {
+        self.stack_limit
+    }
+    pub fn set_stack_limit(&mut self, stack_limit: usize) {
+        self.stack_limit = stack_limit;
+    }
     pub fn token(&self) -> usize {
         self.page_table.token()
     }
-    /// Assume that no conflicts.
-    pub fn insert_framed_area(&mut self, start_va: VirtAddr, end_va: VirtAddr, permission: MapPermission) {
+    /// Assume that no conflicts. All pages get their frames at once, nothing is
+    /// mapped if there are not enough of them.
+    pub fn insert_framed_area(
+        &mut self,
+        start_va: VirtAddr,
+        end_va: VirtAddr,
+        permission: MapPermission,
+    ) -> SysResult<()> {
         self.push(MapArea::new(
             start_va,
             end_va,
             MapType::Framed,
             permission,
-        ), None);
+        ), None)
     }
     pub fn remove_area_with_start_vpn(&mut self, start_vpn: VirtPageNum) {
         if let Some((idx, area)) = self.areas.iter_mut().enumerate()
             .find(|(_, area)| area.vpn_range.get_start() == start_vpn) {
             area.unmap(&mut self.page_table);
             self.areas.remove(idx);
+            tlb_shootdown(self.token());
+        }
+    }
+    /// Whether no area has a page in `[start_vpn, end_vpn)`.
+    pub fn is_range_free(&self, start_vpn: VirtPageNum, end_vpn: VirtPageNum) -> bool {
+        self.areas.iter().all(|area| !area.overlaps(start_vpn, end_vpn))
+    }
+    /// The lowest start of `page_count` free pages in `[lower, upper)`.
+    pub fn find_free_range(
+        &self,
+        lower: VirtPageNum,
+        upper: VirtPageNum,
+        page_count: usize,
+    ) -> Option<VirtPageNum> {
+        let mut start = lower;
+        loop {
+            let end = VirtPageNum(start.0 + page_count);
+            if end > upper {
+                return None;
+            }
+            // skip over the area in the way, if any
+            match self.areas.iter().find(|area| area.overlaps(start, end)) {
+                Some(area) => start = area.vpn_range.get_end(),
+                None => return Some(start),
+            }
+        }
+    }
+    /// Unmap every page in `[start_vpn, end_vpn)`, areas partially inside
+    /// the range are split and keep their pages outside of it.
+    pub fn remove_range(&mut self, start_vpn: VirtPageNum, end_vpn: VirtPageNum) {
+        let mut kept = Vec::new();
+        for mut area in core::mem::take(&mut self.areas) {
+            if !area.overlaps(start_vpn, end_vpn) {
+                kept.push(area);
+                continue;
+            }
+            if area.vpn_range.get_start() < start_vpn {
+                let middle = area.split_off(start_vpn);
+                kept.push(area);
+                area = middle;
+            }
+            if area.vpn_range.get_end() > end_vpn {
+                kept.push(area.split_off(end_vpn));
+            }
+            area.unmap(&mut self.page_table);
+        }
+        self.areas = kept;
+        tlb_shootdown(self.token());
+    }
+    /// Grow or shrink the area starting at `start_vpn` so that it ends at
+    /// `new_end_vpn`. Fails if there is no such area or the new pages are taken.
+    pub fn resize_area(&mut self, start_vpn: VirtPageNum, new_end_vpn: VirtPageNum) -> bool {
+        assert!(start_vpn <= new_end_vpn);
+        let idx = match self.areas.iter().position(|area| area.vpn_range.get_start() == start_vpn) {
+            Some(idx) => idx,
+            None => return false,
+        };
+        let end_vpn = self.areas[idx].vpn_range.get_end();
+        if new_end_vpn > end_vpn {
+            if !self.is_range_free(end_vpn, new_end_vpn) {
+                return false;
+            }
+            if self.areas[idx].append_to(&mut self.page_table, new_end_vpn).is_err() {
+                return false;
+            }
+        } else {
+            self.areas[idx].shrink_to(&mut self.page_table, new_end_vpn);
+            tlb_shootdown(self.token());
         }
+        true
     }
-    fn push(&mut self, mut map_area: MapArea, data: Option<&[u8]>) {
-        map_area.map(&mut self.page_table);
+    /// Zero-filled pages which get their frames when first touched.
+    pub fn insert_lazy_area(&mut self, start_va: VirtAddr, end_va: VirtAddr, permission: MapPermission) {
+        self.push_lazy(MapArea::new_lazy(
+            start_va,
+            end_va,
+            permission,
+            MapBacking::Zero,
+        ));
+    }
+    /// A lazy area like `insert_lazy_area` which also grows downwards on page faults
+    /// right below it, see `grow_stack`.
+    pub fn insert_stack_area(&mut self, start_va: VirtAddr, end_va: VirtAddr, permission: MapPermission) {
+        let mut area = MapArea::new_lazy(start_va, end_va, permission, MapBacking::Zero);
+        area.grows_down = true;
+        self.push_lazy(area);
+    }
+    fn push(&mut self, mut map_area: MapArea, data: Option<&[u8]>) -> SysResult<()> {
+        map_area.map(&mut self.page_table)?;
         if let Some(data) = data {
             map_area.copy_data(&mut self.page_table, data);
         }
         self.areas.push(map_area);
+        Ok(())
+    }
+    /// Lazy areas take no frames until their pages are touched.
+    fn push_lazy(&mut self, map_area: MapArea) {
+        assert!(map_area.backing.is_some());
+        self.areas.push(map_area);
     }
     /// Mention that trampoline is not collected by areas.
     fn map_trampoline(&mut self) {
@@ -102,35 +229,35 @@ impl MemorySet {
             (etext as usize).into(),
             MapType::Identical,
             MapPermission::R | MapPermission::X,
-        ), None);
+        ), None).unwrap();
         println!("mapping .rodata section");
         memory_set.push(MapArea::new(
             (srodata as usize).into(),
             (erodata as usize).into(),
             MapType::Identical,
             MapPermission::R,
-        ), None);
+        ), None).unwrap();
         println!("mapping .data section");
         memory_set.push(MapArea::new(
             (sdata as usize).into(),
             (edata as usize).into(),
             MapType::Identical,
             MapPermission::R | MapPermission::W,
-        ), None);
+        ), None).unwrap();
         println!("mapping .bss section");
         memory_set.push(MapArea::new(
             (sbss_with_stack as usize).into(),
             (ebss as usize).into(),
             MapType::Identical,
             MapPermission::R | MapPermission::W,
-        ), None);
+        ), None).unwrap();
         println!("mapping physical memory");
         memory_set.push(MapArea::new(
             (ekernel as usize).into(),
             MEMORY_END.into(),
             MapType::Identical,
             MapPermission::R | MapPermission::W,
-        ), None);
+        ), None).unwrap();
         println!("mapping memory-mapped registers");
         for pair in MMIO {
             memory_set.push(MapArea::new(
@@ -138,75 +265,92 @@ impl MemorySet {
                 ((*pair).0 + (*pair).1).into(),
                 MapType::Identical,
                 MapPermission::R | MapPermission::W,
-            ), None);
+            ), None).unwrap();
         }
         memory_set
     }
-    /// Include sections in elf and trampoline and TrapContext and user stack,
-    /// also returns user_sp and entry point.
-    pub fn from_elf(elf_data: &[u8]) -> (Self, usize, usize) {
+    /// Map the segments of `elf_data` lazily, their pages are read from the file on first touch.
+    fn map_elf(&mut self, segments: &[Segment], elf_data: &Arc<Vec<u8>>) {
+        for segment in segments.iter() {
+            let start_va: VirtAddr = segment.start_va.into();
+            let end_va: VirtAddr = segment.end_va.into();
+            let map_area = MapArea::new_lazy(
+                start_va,
+                end_va,
+                segment.perm,
+                MapBacking::Elf {
+                    data: Arc::clone(elf_data),
+                    offset: segment.offset,
+                    len: segment.file_size,
+                    va: segment.start_va,
+                },
+            );
+            self.push_lazy(map_area);
+        }
+    }
+    /// Include sections in elf and trampoline and an empty heap right after them,
+    /// also returns the heap bottom, the entry point and the program headers.
+    /// The sections are loaded from the files page by page when they are first touched.
+    /// User stacks and trap contexts are mapped per thread by `TaskUserRes`.
+    /// The program goes at `DYN_BASE` if it is position-independent and its
+    /// interpreter, if any, at `INTERP_BASE`, where the main thread starts.
+    pub fn from_elf(executable: &Executable) -> Result<(Self, usize, ElfInfo), LoadError> {
+        // nothing is mapped before the whole files have been checked
+        let (segments, mut elf_info) = parse_elf(executable.elf_data.as_slice(), DYN_BASE, false, 0..MMAP_BASE)?;
+        let interp = match &executable.interp_data {
+            Some(interp_data) => {
+                let (interp_segments, interp_info) =
+                    parse_elf(interp_data.as_slice(), INTERP_BASE, true, INTERP_BASE..INTERP_TOP)?;
+                let overlaps = |a: &Segment, b: &Segment| {
+                    a.start_va / PAGE_SIZE < (b.end_va + PAGE_SIZE - 1) / PAGE_SIZE
+                        && b.start_va / PAGE_SIZE < (a.end_va + PAGE_SIZE - 1) / PAGE_SIZE
+                };
+                if interp_segments.iter().any(|a| segments.iter().any(|b| overlaps(a, b))) {
+                    return Err(LoadError::OverlappingSegments);
+                }
+                elf_info.base = INTERP_BASE;
+                elf_info.start = interp_info.entry_point;
+                Some((interp_segments, interp_data))
+            }
+            None => None,
+        };
         let mut memory_set = Self::new_bare();
         // map trampoline
         memory_set.map_trampoline();
         // map program headers of elf, with U flag
-        let elf = xmas_elf::ElfFile::new(elf_data).unwrap();
-        let elf_header = elf.header;
-        let magic = elf_header.pt1.magic;
-        assert_eq!(magic, [0x7f, 0x45, 0x4c, 0x46], "invalid elf!");
-        let ph_count = elf_header.pt2.ph_count();
-        let mut max_end_vpn = VirtPageNum(0);
-        for i in 0..ph_count {
-            let ph = elf.program_header(i).unwrap();
-            if ph.get_type().unwrap() == xmas_elf::program::Type::Load {
-                let start_va: VirtAddr = (ph.virtual_addr() as usize).into();
-                let end_va: VirtAddr = ((ph.virtual_addr() + ph.mem_size()) as usize).into();
-                let mut map_perm = MapPermission::U;
-                let ph_flags = ph.flags();
-                if ph_flags.is_read() { map_perm |= MapPermission::R; }
-                if ph_flags.is_write() { map_perm |= MapPermission::W; }
-                if ph_flags.is_execute() { map_perm |= MapPermission::X; }
-                let map_area = MapArea::new(
-                    start_va,
-                    end_va,
-                    MapType::Framed,
-                    map_perm,
-                );
-                max_end_vpn = map_area.vpn_range.get_end();
-                memory_set.push(
-                    map_area,
-                    Some(&elf.input[ph.offset() as usize..(ph.offset() + ph.file_size()) as usize])
-                );
-            }
+        memory_set.map_elf(&segments, &executable.elf_data);
+        if let Some((interp_segments, interp_data)) = interp {
+            memory_set.map_elf(&interp_segments, interp_data);
         }
-        // map user stack with U flags
-        let max_end_va: VirtAddr = max_end_vpn.into();
-        let mut user_stack_bottom: usize = max_end_va.into();
-        // guard page
-        user_stack_bottom += PAGE_SIZE;
-        let user_stack_top = user_stack_bottom + USER_STACK_SIZE;
-        memory_set.push(MapArea::new(
-            user_stack_bottom.into(),
-            user_stack_top.into(),
-            MapType::Framed,
+        // the heap grows from here by brk/sbrk
+        let heap_bottom = segments
+            .iter()
+            .map(|segment| (segment.end_va + PAGE_SIZE - 1) / PAGE_SIZE * PAGE_SIZE)
+            .max()
+            .unwrap();
+        memory_set.insert_lazy_area(
+            heap_bottom.into(),
+            heap_bottom.into(),
             MapPermission::R | MapPermission::W | MapPermission::U,
-        ), None);
-        // map TrapContext
-        memory_set.push(MapArea::new(
-            TRAP_CONTEXT.into(),
-            TRAMPOLINE.into(),
-            MapType::Framed,
-            MapPermission::R | MapPermission::W,
-        ), None);
-        (memory_set, user_stack_top, elf.header.pt2.entry_point() as usize)
+        );
+        Ok((memory_set, heap_bottom, elf_info))
     }
-    pub fn from_existed_user(user_space: &MemorySet) -> MemorySet {
+    /// Pages accessible from user mode are shared copy-on-write with
+    /// `user_space`, the others (i.e. trap contexts) are copied at once,
+    /// which fails if there are not enough frames for them.
+    pub fn from_existed_user(user_space: &mut MemorySet) -> SysResult<MemorySet> {
         let mut memory_set = Self::new_bare();
+        memory_set.stack_limit = user_space.stack_limit;
         // map trampoline
         memory_set.map_trampoline();
-        // copy data sections/trap_context/user_stack
-        for area in user_space.areas.iter() {
+        for area in user_space.areas.iter_mut() {
+            if area.map_perm.contains(MapPermission::U) {
+                let new_area = area.share(&mut user_space.page_table, &mut memory_set.page_table);
+                memory_set.areas.push(new_area);
+                continue;
+            }
             let new_area = MapArea::from_another(area);
-            memory_set.push(new_area, None);
+            memory_set.push(new_area, None)?;
             // copy data from another space
             for vpn in area.vpn_range {
                 let src_ppn = user_space.translate(vpn).unwrap().ppn();
@@ -214,7 +358,7 @@ impl MemorySet {
                 dst_ppn.get_bytes_array().copy_from_slice(src_ppn.get_bytes_array());
             }
         }
-        memory_set
+        Ok(memory_set)
     }
     pub fn activate(&self) {
         let satp = self.page_table.token();
@@ -226,6 +370,190 @@ impl MemorySet {
     pub fn translate(&self, vpn: VirtPageNum) -> Option<PageTableEntry> {
         self.page_table.translate(vpn)
     }
+    /// Handle a page fault at `vpn` caused by an `access` which is one of R, W or X:
+    /// load the page of a lazy area on first touch, read it back if it has been
+    /// swapped out, or give a page shared copy-on-write a frame of its own on the
+    /// first store. Other pages of this address space may be swapped out for it.
+    /// Fails with EFAULT if the access is a real violation, or with ENOMEM if
+    /// there is no frame for the page, nor anything left to swap out.
+    pub fn handle_page_fault(&mut self, vpn: VirtPageNum, access: MapPermission) -> SysResult<()> {
+        let contains = |area: &MapArea| area.overlaps(vpn, VirtPageNum(vpn.0 + 1));
+        if !self.areas.iter().any(contains) && !self.grow_stack(vpn) {
+            return Err(SysError::EFAULT);
+        }
+        let idx = self.areas.iter().position(contains).unwrap();
+        let area = &self.areas[idx];
+        if !area.map_perm.contains(access) {
+            return Err(SysError::EFAULT);
+        }
+        let mapped = self.page_table.translate(vpn).filter(|pte| pte.is_valid());
+        match mapped {
+            // another hart has just mapped the page, this one still had the old
+            // entry in its TLB, which the trap has flushed
+            Some(pte) if access != MapPermission::W || pte.writable() => return Ok(()),
+            // otherwise only a store to a page shared copy-on-write may fault on a mapped page
+            Some(_) if !area.is_shared(vpn) => {
+                self.areas[idx].set_writable(&mut self.page_table, vpn);
+                return Ok(());
+            }
+            None if !area.is_swapped(vpn) && area.backing.is_none() => return Err(SysError::EFAULT),
+            _ => {}
+        }
+        // swapping out may take a page of any area, so the area is looked at again
+        let frame = self.alloc_frame().ok_or(SysError::ENOMEM)?;
+        let area = &mut self.areas[idx];
+        if mapped.is_some() {
+            area.copy_on_write(&mut self.page_table, vpn, frame);
+            // other threads may still read the shared frame
+            tlb_shootdown(self.token());
+        } else if area.is_swapped(vpn) {
+            area.swap_in(&mut self.page_table, vpn, frame)?;
+        } else {
+            area.load_page(&mut self.page_table, vpn, frame);
+        }
+        Ok(())
+    }
+    /// Swap out pages of this address space, or else of other processes,
+    /// until `FRAMES_RESERVED` frames are free. Gives up if nothing is left
+    /// to swap out.
+    pub fn reserve_frames(&mut self) {
+        while frame_remaining() < FRAMES_RESERVED {
+            if !self.swap_out_one() && !swap_out_page() {
+                break;
+            }
+        }
+    }
+    /// A frame for a user page, leaving the reserved ones for page tables.
+    fn alloc_frame(&mut self) -> Option<FrameTracker> {
+        self.reserve_frames();
+        frame_alloc()
+    }
+    /// Pick a page by the clock algorithm and write it to the swap file. The hand
+    /// sweeps the user pages in address order and gives a page accessed since its
+    /// last visit a second chance. Pages shared with another address space or
+    /// pinned by the kernel are skipped.
+    /// Returns false if there is no such page or the swap file is full.
+    pub fn swap_out_one(&mut self) -> bool {
+        let mut candidates: Vec<VirtPageNum> = self.areas
+            .iter()
+            .filter(|area| area.map_type == MapType::Framed && area.map_perm.contains(MapPermission::U))
+            .flat_map(|area| area.data_frames.iter())
+            .filter(|(_, frame)| Arc::strong_count(*frame) == 1)
+            .map(|(vpn, _)| *vpn)
+            .collect();
+        if candidates.is_empty() {
+            return false;
+        }
+        candidates.sort();
+        let count = candidates.len();
+        let start = candidates.iter().position(|vpn| *vpn >= self.clock_hand).unwrap_or(0);
+        // every accessed bit has been cleared after a whole round
+        let page_table = &mut self.page_table;
+        let victim = (0..2 * count)
+            .map(|i| candidates[(start + i) % count])
+            .find(|vpn| !page_table.take_accessed(*vpn))
+            .unwrap();
+        // harts caching an entry with the bit still set would not set it again
+        tlb_shootdown(self.token());
+        self.clock_hand = VirtPageNum(victim.0 + 1);
+        let area = self.areas
+            .iter_mut()
+            .find(|area| area.overlaps(victim, VirtPageNum(victim.0 + 1)))
+            .unwrap();
+        area.swap_out(&mut self.page_table, victim)
+    }
+    /// Grow the stack area right above `vpn` down to it, as long as the stack stays
+    /// within the limit and keeps a guard gap to the area below.
+    fn grow_stack(&mut self, vpn: VirtPageNum) -> bool {
+        let idx = match self.areas
+            .iter()
+            .enumerate()
+            .filter(|(_, area)| area.vpn_range.get_start() > vpn)
+            .min_by_key(|(_, area)| area.vpn_range.get_start()) {
+            Some((idx, area)) if area.grows_down => idx,
+            _ => return false,
+        };
+        let start_vpn = self.areas[idx].vpn_range.get_start();
+        let end_vpn = self.areas[idx].vpn_range.get_end();
+        if end_vpn.0 - vpn.0 > self.stack_limit / PAGE_SIZE {
+            return false;
+        }
+        let guard_vpn = VirtPageNum(vpn.0.saturating_sub(USER_STACK_GUARD_GAP / PAGE_SIZE));
+        if !self.is_range_free(guard_vpn, start_vpn) {
+            return false;
+        }
+        self.areas[idx].extend_down_to(vpn);
+        true
+    }
+    /// The kernel accesses user memory through the frames, bypassing the page table,
+    /// so the faults user code would take on `[start, start + len)` are handled first.
+    pub fn prepare_user_access(&mut self, start: usize, len: usize, write: bool) -> SysResult<()> {
+        self.pin_user_access(start, len, write).map(|_| ())
+    }
+    /// `prepare_user_access` which also keeps the pages from being swapped out
+    /// or freed until the returned frames are dropped, so that they can still
+    /// be accessed without holding the lock of the address space.
+    /// Only pages of user areas in the lower half may be accessed, never the
+    /// trampoline or the trap contexts.
+    pub fn pin_user_access(
+        &mut self,
+        start: usize,
+        len: usize,
+        write: bool,
+    ) -> SysResult<Vec<Arc<FrameTracker>>> {
+        let mut pins = Vec::new();
+        if len == 0 {
+            return Ok(pins);
+        }
+        let end = start.checked_add(len).ok_or(SysError::EFAULT)?;
+        if end > USER_SPACE_END {
+            return Err(SysError::EFAULT);
+        }
+        let access = if write { MapPermission::W } else { MapPermission::R };
+        for vpn in VPNRange::new(VirtAddr::from(start).floor(), VirtAddr::from(end).ceil()) {
+            match self.page_table.translate(vpn) {
+                Some(pte) if pte.is_valid() && (!write || pte.writable()) => {}
+                _ => self.handle_page_fault(vpn, access)?,
+            }
+            // faulting in the next pages must not swap out this one
+            let area = self.areas
+                .iter()
+                .find(|area| area.overlaps(vpn, VirtPageNum(vpn.0 + 1)))
+                .filter(|area| area.map_perm.contains(MapPermission::U))
+                .ok_or(SysError::EFAULT)?;
+            if let Some(frame) = area.data_frames.get(&vpn) {
+                pins.push(Arc::clone(frame));
+            }
+        }
+        Ok(pins)
+    }
+    /// `prepare_user_access` for reading a string ending with `\0` at `start`.
+    pub fn prepare_user_str(&mut self, start: usize) -> SysResult<()> {
+        let mut pins = Vec::new();
+        let mut va = start;
+        loop {
+            let mut vpn = VirtAddr::from(va).floor();
+            vpn.step();
+            let page_end: usize = VirtAddr::from(vpn).into();
+            pins.append(&mut self.pin_user_access(va, page_end - va, false)?);
+            let pa: usize = self.page_table.translate_va(va.into()).ok_or(SysError::EFAULT)?.into();
+            let bytes = unsafe { core::slice::from_raw_parts(pa as *const u8, page_end - va) };
+            if bytes.contains(&0) {
+                return Ok(());
+            }
+            va = page_end;
+        }
+    }
+    /// Bytes of the areas mapped in user space, and of those backed by frames.
+    pub fn virtual_size(&self) -> usize {
+        self.areas
+            .iter()
+            .map(|area| area.vpn_range.get_end().0 - area.vpn_range.get_start().0)
+            .sum::<usize>() * PAGE_SIZE
+    }
+    pub fn resident_size(&self) -> usize {
+        self.areas.iter().map(|area| area.data_frames.len()).sum::<usize>() * PAGE_SIZE
+    }
     pub fn recycle_data_pages(&mut self) {
         //*self = Self::new_bare();
         self.areas.clear();
@@ -234,9 +562,17 @@ impl MemorySet {
 
 pub struct MapArea {
     vpn_range: VPNRange,
-    data_frames: BTreeMap<VirtPageNum, FrameTracker>,
+    /// A frame may be shared by the areas of several address spaces after fork.
+    data_frames: BTreeMap<VirtPageNum, Arc<FrameTracker>>,
+    /// Pages which have been swapped out, a slot may be shared after fork as well.
+    swapped: BTreeMap<VirtPageNum, Arc<SwapSlot>>,
     map_type: MapType,
     map_perm: MapPermission,
+    /// Pages of a lazy area are only mapped when first touched, the others
+    /// are all mapped along with the area.
+    backing: Option<MapBacking>,
+    /// Whether this is a stack which grows downwards.
+    grows_down: bool,
 }
 
 impl MapArea {
@@ -251,46 +587,211 @@ impl MapArea {
         Self {
             vpn_range: VPNRange::new(start_vpn, end_vpn),
             data_frames: BTreeMap::new(),
+            swapped: BTreeMap::new(),
             map_type,
             map_perm,
+            backing: None,
+            grows_down: false,
         }
     }
+    pub fn new_lazy(
+        start_va: VirtAddr,
+        end_va: VirtAddr,
+        map_perm: MapPermission,
+        backing: MapBacking,
+    ) -> Self {
+        let mut area = Self::new(start_va, end_va, MapType::Framed, map_perm);
+        area.backing = Some(backing);
+        area
+    }
     pub fn from_another(another: &MapArea) -> Self {
         Self {
             vpn_range: VPNRange::new(another.vpn_range.get_start(), another.vpn_range.get_end()),
             data_frames: BTreeMap::new(),
+            swapped: BTreeMap::new(),
             map_type: another.map_type,
             map_perm: another.map_perm,
+            backing: another.backing.clone(),
+            grows_down: another.grows_down,
+        }
+    }
+    pub fn overlaps(&self, start_vpn: VirtPageNum, end_vpn: VirtPageNum) -> bool {
+        self.vpn_range.get_start() < end_vpn && start_vpn < self.vpn_range.get_end()
+    }
+    /// Move the pages from `at` on into a new area, `at` must be inside this one.
+    pub fn split_off(&mut self, at: VirtPageNum) -> MapArea {
+        let start = self.vpn_range.get_start();
+        let end = self.vpn_range.get_end();
+        assert!(start < at && at < end);
+        self.vpn_range = VPNRange::new(start, at);
+        Self {
+            vpn_range: VPNRange::new(at, end),
+            data_frames: self.data_frames.split_off(&at),
+            swapped: self.swapped.split_off(&at),
+            map_type: self.map_type,
+            map_perm: self.map_perm,
+            backing: self.backing.clone(),
+            grows_down: self.grows_down,
         }
     }
-    pub fn map_one(&mut self, page_table: &mut PageTable, vpn: VirtPageNum) {
+    pub fn map_one(&mut self, page_table: &mut PageTable, vpn: VirtPageNum) -> SysResult<()> {
         let ppn: PhysPageNum;
         match self.map_type {
             MapType::Identical => {
                 ppn = PhysPageNum(vpn.0);
             }
             MapType::Framed => {
-                let frame = frame_alloc().unwrap();
+                let frame = frame_alloc().ok_or(SysError::ENOMEM)?;
                 ppn = frame.ppn;
-                self.data_frames.insert(vpn, frame);
+                self.data_frames.insert(vpn, Arc::new(frame));
             }
         }
         let pte_flags = PTEFlags::from_bits(self.map_perm.bits).unwrap();
         page_table.map(vpn, ppn, pte_flags);
+        Ok(())
     }
     pub fn unmap_one(&mut self, page_table: &mut PageTable, vpn: VirtPageNum) {
         match self.map_type {
             MapType::Framed => {
-                self.data_frames.remove(&vpn);
+                // pages of a lazy area which were never touched are not mapped
+                if self.data_frames.remove(&vpn).is_none() {
+                    if self.swapped.remove(&vpn).is_some() {
+                        page_table.clear_swapped(vpn);
+                    }
+                    return;
+                }
             }
             _ => {}
         }
         page_table.unmap(vpn);
     }
-    pub fn map(&mut self, page_table: &mut PageTable) {
-        for vpn in self.vpn_range {
-            self.map_one(page_table, vpn);
+    /// Map the page at `vpn` of a lazy area to `frame`, filled from its backing.
+    pub fn load_page(&mut self, page_table: &mut PageTable, vpn: VirtPageNum, frame: FrameTracker) {
+        // frames are zeroed, which is all a zero-filled page needs
+        if let Some(MapBacking::Elf { data, offset, len, va }) = &self.backing {
+            let page_start: usize = VirtAddr::from(vpn).into();
+            let start = page_start.max(*va);
+            let end = (page_start + PAGE_SIZE).min(*va + *len);
+            if start < end {
+                frame.ppn.get_bytes_array()[start - page_start..end - page_start]
+                    .copy_from_slice(&data[*offset + (start - *va)..*offset + (end - *va)]);
+            }
         }
+        let pte_flags = PTEFlags::from_bits(self.map_perm.bits).unwrap();
+        page_table.map(vpn, frame.ppn, pte_flags);
+        self.data_frames.insert(vpn, Arc::new(frame));
+    }
+    /// A copy of this area sharing all its frames, which are write-protected
+    /// in both `page_table` and `new_page_table` until the first store.
+    /// Pages swapped out share their slots, each side reads its own copy back.
+    pub fn share(&mut self, page_table: &mut PageTable, new_page_table: &mut PageTable) -> MapArea {
+        assert_eq!(self.map_type, MapType::Framed);
+        let mut new_area = MapArea::from_another(self);
+        let mut pte_flags = PTEFlags::from_bits(self.map_perm.bits).unwrap();
+        pte_flags.remove(PTEFlags::W);
+        for (vpn, frame) in self.data_frames.iter() {
+            page_table.remap(*vpn, frame.ppn, pte_flags);
+            new_page_table.map(*vpn, frame.ppn, pte_flags);
+            new_area.data_frames.insert(*vpn, Arc::clone(frame));
+        }
+        for (vpn, slot) in self.swapped.iter() {
+            new_page_table.set_swapped(*vpn, slot.id());
+            new_area.swapped.insert(*vpn, Arc::clone(slot));
+        }
+        new_area
+    }
+    /// Whether the frame at `vpn` is also used by another address space or the kernel.
+    pub fn is_shared(&self, vpn: VirtPageNum) -> bool {
+        Arc::strong_count(&self.data_frames[&vpn]) > 1
+    }
+    pub fn is_swapped(&self, vpn: VirtPageNum) -> bool {
+        self.swapped.contains_key(&vpn)
+    }
+    /// Give the write-protected page at `vpn` a copy of its shared frame in `frame`.
+    pub fn copy_on_write(&mut self, page_table: &mut PageTable, vpn: VirtPageNum, frame: FrameTracker) {
+        frame.ppn.get_bytes_array().copy_from_slice(self.data_frames[&vpn].ppn.get_bytes_array());
+        self.data_frames.insert(vpn, Arc::new(frame));
+        self.set_writable(page_table, vpn);
+    }
+    /// Make the write-protected page at `vpn` writable, its frame is no longer shared.
+    pub fn set_writable(&mut self, page_table: &mut PageTable, vpn: VirtPageNum) {
+        let ppn = self.data_frames[&vpn].ppn;
+        let pte_flags = PTEFlags::from_bits(self.map_perm.bits).unwrap();
+        page_table.remap(vpn, ppn, pte_flags);
+    }
+    /// Write the page at `vpn` to the swap file and free its frame.
+    /// Returns false if the swap file is full.
+    pub fn swap_out(&mut self, page_table: &mut PageTable, vpn: VirtPageNum) -> bool {
+        // threads on other harts must not write to the page while it is copied out
+        let ppn = self.data_frames[&vpn].ppn;
+        let pte_flags = PTEFlags::from_bits(self.map_perm.bits).unwrap();
+        page_table.remap(vpn, ppn, pte_flags - PTEFlags::W);
+        tlb_shootdown(page_table.token());
+        let slot = match SwapSlot::write(ppn) {
+            Some(slot) => slot,
+            None => {
+                self.set_writable(page_table, vpn);
+                return false;
+            }
+        };
+        page_table.set_swapped(vpn, slot.id());
+        // nor read it once the frame is freed
+        tlb_shootdown(page_table.token());
+        self.data_frames.remove(&vpn);
+        self.swapped.insert(vpn, Arc::new(slot));
+        true
+    }
+    /// Read the page at `vpn` back from the swap file into `frame`. It is mapped
+    /// with the permissions of the area, since the frame is not shared.
+    /// Fails with ENOMEM if the swap file cannot be read, the page stays swapped out.
+    pub fn swap_in(&mut self, page_table: &mut PageTable, vpn: VirtPageNum, frame: FrameTracker) -> SysResult<()> {
+        if !self.swapped[&vpn].read(frame.ppn) {
+            return Err(SysError::ENOMEM);
+        }
+        self.swapped.remove(&vpn);
+        let pte_flags = PTEFlags::from_bits(self.map_perm.bits).unwrap();
+        page_table.map(vpn, frame.ppn, pte_flags);
+        self.data_frames.insert(vpn, Arc::new(frame));
+        Ok(())
+    }
+    /// Move the start of a lazy area down to `new_start`.
+    pub fn extend_down_to(&mut self, new_start: VirtPageNum) {
+        assert!(self.backing.is_some() && new_start <= self.vpn_range.get_start());
+        self.vpn_range = VPNRange::new(new_start, self.vpn_range.get_end());
+    }
+    /// Fails with ENOMEM if the new pages of an area which is not lazy cannot
+    /// all get frames, the area is left as it was.
+    pub fn append_to(&mut self, page_table: &mut PageTable, new_end: VirtPageNum) -> SysResult<()> {
+        if self.backing.is_none() {
+            self.map_range(page_table, VPNRange::new(self.vpn_range.get_end(), new_end))?;
+        }
+        self.vpn_range = VPNRange::new(self.vpn_range.get_start(), new_end);
+        Ok(())
+    }
+    pub fn shrink_to(&mut self, page_table: &mut PageTable, new_end: VirtPageNum) {
+        for vpn in VPNRange::new(new_end, self.vpn_range.get_end()) {
+            self.unmap_one(page_table, vpn);
+        }
+        self.vpn_range = VPNRange::new(self.vpn_range.get_start(), new_end);
+    }
+    /// Map all pages unless the area is lazy, fails with ENOMEM if they cannot
+    /// all get frames, in which case none is mapped.
+    pub fn map(&mut self, page_table: &mut PageTable) -> SysResult<()> {
+        if self.backing.is_some() {
+            return Ok(());
+        }
+        self.map_range(page_table, self.vpn_range)
+    }
+    fn map_range(&mut self, page_table: &mut PageTable, vpn_range: VPNRange) -> SysResult<()> {
+        for vpn in vpn_range {
+            if let Err(err) = self.map_one(page_table, vpn) {
+                for mapped in VPNRange::new(vpn_range.get_start(), vpn) {
+                    self.unmap_one(page_table, mapped);
+                }
+                return Err(err);
+            }
+        }
+        Ok(())
     }
     pub fn unmap(&mut self, page_table: &mut PageTable) {
         for vpn in self.vpn_range {
@@ -321,6 +822,21 @@ impl MapArea {
     }
 }
 
+/// Where the pages of a lazy area come from.
+#[derive(Clone)]
+pub enum MapBacking {
+    /// Zero-filled, e.g. stacks and the heap.
+    Zero,
+    /// The `len` bytes at `offset` of an elf file are placed at `va`,
+    /// the rest of the area is zero-filled like `.bss`.
+    Elf {
+        data: Arc<Vec<u8>>,
+        offset: usize,
+        len: usize,
+        va: usize,
+    },
+}
+
 #[derive(Copy, Clone, PartialEq, Debug)]
 pub enum MapType {
     Identical,
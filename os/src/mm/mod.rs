@@ -3,11 +3,13 @@ mod address;
 mod frame_allocator;
 mod page_table;
 mod memory_set;
+mod swap;
+mod elf;
 
 use page_table::PTEFlags;
 use address::VPNRange;
 pub use address::{PhysAddr, VirtAddr, PhysPageNum, VirtPageNum, StepByOne};
-pub use frame_allocator::{FrameTracker, frame_alloc, frame_dealloc,};
+pub use frame_allocator::{FrameTracker, frame_alloc, frame_dealloc, frame_remaining};
 pub use page_table::{
     PageTable,
     PageTableEntry,
@@ -15,14 +17,22 @@ pub use page_table::{
     translated_str,
     translated_ref,
     translated_refmut,
+    copy_to_user,
+    copy_from_user,
     UserBuffer,
     UserBufferIterator,
 };
-pub use memory_set::{MemorySet, KERNEL_SPACE, MapPermission, kernel_token};
+pub use memory_set::{MemorySet, KERNEL_SPACE, MapPermission, kernel_token, reserve_kernel_frames};
+pub use elf::{ElfInfo, LoadError, Executable, interpreter_path};
 pub use memory_set::remap_test;
 
 pub fn init() {
     heap_allocator::init_heap();
     frame_allocator::init_frame_allocator();
+    init_hart();
+}
+
+/// Switch the current hart to kernel space, the boot hart does it in `init`.
+pub fn init_hart() {
     KERNEL_SPACE.lock().activate();
 }
\ No newline at end of file
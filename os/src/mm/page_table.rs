@@ -11,6 +11,7 @@ use alloc::vec::Vec;
 use alloc::vec;
 use alloc::string::String;
 use bitflags::*;
+use crate::error::{SysError, SysResult};
 
 bitflags! {
     pub struct PTEFlags: u8 {
@@ -25,6 +26,10 @@ bitflags! {
     }
 }
 
+/// The first bit reserved for software marks an invalid entry of a page which
+/// has been swapped out, the swap slot takes the place of the ppn.
+const PTE_SWAPPED: usize = 1 << 8;
+
 #[derive(Copy, Clone)]
 #[repr(C)]
 pub struct PageTableEntry {
@@ -42,6 +47,11 @@ impl PageTableEntry {
             bits: 0,
         }
     }
+    pub fn swapped(slot: usize) -> Self {
+        PageTableEntry {
+            bits: slot << 10 | PTE_SWAPPED,
+        }
+    }
     pub fn ppn(&self) -> PhysPageNum {
         (self.bits >> 10 & ((1usize << 44) - 1)).into()
     }
@@ -60,6 +70,17 @@ impl PageTableEntry {
     pub fn executable(&self) -> bool {
         (self.flags() & PTEFlags::X) != PTEFlags::empty()
     }
+    pub fn accessed(&self) -> bool {
+        (self.flags() & PTEFlags::A) != PTEFlags::empty()
+    }
+    /// The swap slot holding the page, if it has been swapped out.
+    pub fn swap_slot(&self) -> Option<usize> {
+        if !self.is_valid() && self.bits & PTE_SWAPPED != 0 {
+            Some(self.bits >> 10 & ((1usize << 44) - 1))
+        } else {
+            None
+        }
+    }
 }
 
 pub struct PageTable {
@@ -131,12 +152,38 @@ impl PageTable {
         assert!(pte.is_valid(), "vpn {:?} is invalid before unmapping", vpn);
         *pte = PageTableEntry::empty();
     }
+    /// Change the frame and flags of a mapped page.
+    pub fn remap(&mut self, vpn: VirtPageNum, ppn: PhysPageNum, flags: PTEFlags) {
+        let pte = self.find_pte_create(vpn).unwrap();
+        assert!(pte.is_valid(), "vpn {:?} is invalid before remapping", vpn);
+        *pte = PageTableEntry::new(ppn, flags | PTEFlags::V);
+    }
+    /// Record that the page at `vpn` has been swapped out to `slot`.
+    pub fn set_swapped(&mut self, vpn: VirtPageNum, slot: usize) {
+        let pte = self.find_pte_create(vpn).unwrap();
+        *pte = PageTableEntry::swapped(slot);
+    }
+    /// Forget about a page which has been swapped out.
+    pub fn clear_swapped(&mut self, vpn: VirtPageNum) {
+        let pte = self.find_pte_create(vpn).unwrap();
+        assert!(pte.swap_slot().is_some(), "vpn {:?} is not swapped out", vpn);
+        *pte = PageTableEntry::empty();
+    }
+    /// Clear the accessed bit of a mapped page, returns whether it was set.
+    pub fn take_accessed(&mut self, vpn: VirtPageNum) -> bool {
+        let pte = self.find_pte_create(vpn).unwrap();
+        assert!(pte.is_valid(), "vpn {:?} is invalid", vpn);
+        let accessed = pte.accessed();
+        pte.bits &= !(PTEFlags::A.bits() as usize);
+        accessed
+    }
     pub fn translate(&self, vpn: VirtPageNum) -> Option<PageTableEntry> {
         self.find_pte(vpn)
             .map(|pte| {pte.clone()})
     }
     pub fn translate_va(&self, va: VirtAddr) -> Option<PhysAddr> {
         self.find_pte(va.clone().floor())
+            .filter(|pte| pte.is_valid())
             .map(|pte| {
                 let aligned_pa: PhysAddr = pte.ppn().into();
                 let offset = va.page_offset();
@@ -149,17 +196,18 @@ impl PageTable {
     }
 }
 
-pub fn translated_byte_buffer(token: usize, ptr: *const u8, len: usize) -> Vec<&'static mut [u8]> {
+pub fn translated_byte_buffer(token: usize, ptr: *const u8, len: usize) -> SysResult<Vec<&'static mut [u8]>> {
     let page_table = PageTable::from_token(token);
     let mut start = ptr as usize;
-    let end = start + len;
+    let end = start.checked_add(len).ok_or(SysError::EFAULT)?;
     let mut v = Vec::new();
     while start < end {
         let start_va = VirtAddr::from(start);
         let mut vpn = start_va.floor();
         let ppn = page_table
             .translate(vpn)
-            .unwrap()
+            .filter(|pte| pte.is_valid())
+            .ok_or(SysError::EFAULT)?
             .ppn();
         vpn.step();
         let mut end_va: VirtAddr = vpn.into();
@@ -171,34 +219,69 @@ pub fn translated_byte_buffer(token: usize, ptr: *const u8, len: usize) -> Vec<&
         }
         start = end_va.into();
     }
-    v
+    Ok(v)
 }
 
 /// Load a string from other address spaces into kernel space without an end `\0`.
-pub fn translated_str(token: usize, ptr: *const u8) -> String {
+pub fn translated_str(token: usize, ptr: *const u8) -> SysResult<String> {
     let page_table = PageTable::from_token(token);
     let mut string = String::new();
     let mut va = ptr as usize;
     loop {
-        let ch: u8 = *(page_table.translate_va(VirtAddr::from(va)).unwrap().get_mut());
+        let ch: u8 = *(page_table
+            .translate_va(VirtAddr::from(va))
+            .ok_or(SysError::EFAULT)?
+            .get_mut());
         if ch == 0 {
             break;
         }
         string.push(ch as char);
         va += 1;
     }
-    string
+    Ok(string)
 }
 
-pub fn translated_ref<T>(token: usize, ptr: *const T) -> &'static T {
+pub fn translated_ref<T>(token: usize, ptr: *const T) -> SysResult<&'static T> {
     let page_table = PageTable::from_token(token);
-    page_table.translate_va(VirtAddr::from(ptr as usize)).unwrap().get_ref()
+    page_table
+        .translate_va(VirtAddr::from(ptr as usize))
+        .map(|pa| pa.get_ref())
+        .ok_or(SysError::EFAULT)
 }
 
-pub fn translated_refmut<T>(token: usize, ptr: *mut T) -> &'static mut T {
+pub fn translated_refmut<T>(token: usize, ptr: *mut T) -> SysResult<&'static mut T> {
     let page_table = PageTable::from_token(token);
     let va = ptr as usize;
-    page_table.translate_va(VirtAddr::from(va)).unwrap().get_mut()
+    page_table
+        .translate_va(VirtAddr::from(va))
+        .map(|pa| pa.get_mut())
+        .ok_or(SysError::EFAULT)
+}
+
+/// Copy a value into another address space, it may cross a page boundary.
+pub fn copy_to_user<T>(token: usize, dst: *mut T, value: &T) -> SysResult<()> {
+    let src = unsafe {
+        core::slice::from_raw_parts(value as *const T as *const u8, core::mem::size_of::<T>())
+    };
+    let mut start = 0usize;
+    for buffer in translated_byte_buffer(token, dst as *const u8, src.len())? {
+        buffer.copy_from_slice(&src[start..start + buffer.len()]);
+        start += buffer.len();
+    }
+    Ok(())
+}
+
+/// Copy a value out of another address space, it may cross a page boundary.
+pub fn copy_from_user<T>(token: usize, src: *const T, value: &mut T) -> SysResult<()> {
+    let dst = unsafe {
+        core::slice::from_raw_parts_mut(value as *mut T as *mut u8, core::mem::size_of::<T>())
+    };
+    let mut start = 0usize;
+    for buffer in translated_byte_buffer(token, src as *const u8, dst.len())? {
+        dst[start..start + buffer.len()].copy_from_slice(buffer);
+        start += buffer.len();
+    }
+    Ok(())
 }
 
 pub struct UserBuffer {
@@ -0,0 +1,84 @@
+use super::PhysPageNum;
+use crate::config::{PAGE_SIZE, SWAP_SLOTS};
+use crate::fs::ROOT_INODE;
+use crate::task::RecycleAllocator;
+use easy_fs::Inode;
+use alloc::sync::Arc;
+use lazy_static::*;
+use spin::Mutex;
+
+const SWAP_FILE: &str = "swap";
+
+/// Swapped out pages are kept in a file on easy-fs, one page per slot.
+struct SwapManager {
+    inode: Arc<Inode>,
+    slot_allocator: RecycleAllocator,
+    slot_count: usize,
+}
+
+impl SwapManager {
+    /// Nothing survives a reboot, so a swap file left over is emptied.
+    fn new() -> Self {
+        let inode = match ROOT_INODE.find(SWAP_FILE) {
+            Some(inode) => {
+                inode.clear();
+                inode
+            }
+            None => ROOT_INODE.create(SWAP_FILE).unwrap(),
+        };
+        Self {
+            inode,
+            slot_allocator: RecycleAllocator::new(),
+            slot_count: 0,
+        }
+    }
+    fn alloc(&mut self) -> Option<usize> {
+        if self.slot_count == SWAP_SLOTS {
+            return None;
+        }
+        self.slot_count += 1;
+        Some(self.slot_allocator.alloc())
+    }
+    fn dealloc(&mut self, slot: usize) {
+        self.slot_count -= 1;
+        self.slot_allocator.dealloc(slot);
+    }
+}
+
+lazy_static! {
+    static ref SWAP_MANAGER: Mutex<SwapManager> = Mutex::new(SwapManager::new());
+}
+
+/// A page in the swap file, which is freed when dropped.
+/// It is shared like a frame by the address spaces of a parent and its child.
+pub struct SwapSlot(usize);
+
+impl SwapSlot {
+    /// Write the page in frame `ppn` to a new slot, None if the swap file is
+    /// full or easy-fs is out of blocks for it.
+    pub fn write(ppn: PhysPageNum) -> Option<Self> {
+        let mut swap_manager = SWAP_MANAGER.lock();
+        let slot = swap_manager.alloc()?;
+        let written = swap_manager.inode.write_at(slot * PAGE_SIZE, ppn.get_bytes_array());
+        if written != PAGE_SIZE {
+            swap_manager.dealloc(slot);
+            return None;
+        }
+        Some(Self(slot))
+    }
+    /// Read the page back into frame `ppn`, the slot keeps its copy.
+    /// Returns false if the slot could not be read whole.
+    pub fn read(&self, ppn: PhysPageNum) -> bool {
+        let read = SWAP_MANAGER.lock().inode.read_at(self.0 * PAGE_SIZE, ppn.get_bytes_array());
+        read == PAGE_SIZE
+    }
+    pub fn id(&self) -> usize {
+        self.0
+    }
+}
+
+impl Drop for SwapSlot {
+    fn drop(&mut self) {
+        SWAP_MANAGER.lock().dealloc(self.0);
+    }
+}
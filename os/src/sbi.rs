@@ -10,6 +10,15 @@ const SBI_REMOTE_SFENCE_VMA: usize = 6;
 const SBI_REMOTE_SFENCE_VMA_ASID: usize = 7;
 const SBI_SHUTDOWN: usize = 8;
 
+/// Hart state management extension of SBI v0.2.
+const SBI_EXT_HSM: usize = 0x48534D;
+const SBI_HSM_HART_START: usize = 0;
+const SBI_HSM_HART_GET_STATUS: usize = 2;
+
+/// Error codes of SBI v0.2 calls.
+pub const SBI_ERR_NOT_SUPPORTED: isize = -2;
+pub const SBI_ERR_INVALID_PARAM: isize = -3;
+
 #[inline(always)]
 fn sbi_call(which: usize, arg0: usize, arg1: usize, arg2: usize) -> usize {
     let mut ret;
@@ -24,6 +33,24 @@ fn sbi_call(which: usize, arg0: usize, arg1: usize, arg2: usize) -> usize {
     ret
 }
 
+/// A call to an SBI v0.2 extension, which returns an error code in a0 and a value in a1.
+#[inline(always)]
+fn sbi_call_ext(eid: usize, fid: usize, arg0: usize, arg1: usize, arg2: usize) -> Result<usize, isize> {
+    let (error, value): (isize, usize);
+    unsafe {
+        llvm_asm!("ecall"
+            : "={x10}" (error), "={x11}" (value)
+            : "{x10}" (arg0), "{x11}" (arg1), "{x12}" (arg2), "{x16}" (fid), "{x17}" (eid)
+            : "memory"
+            : "volatile"
+        );
+    }
+    match error {
+        0 => Ok(value),
+        error => Err(error),
+    }
+}
+
 pub fn set_timer(timer: usize) {
     sbi_call(SBI_SET_TIMER, timer, 0, 0);
 }
@@ -36,6 +63,22 @@ pub fn console_getchar() -> usize {
     sbi_call(SBI_CONSOLE_GETCHAR, 0, 0, 0)
 }
 
+/// Raise a supervisor software interrupt on the harts in `hart_mask`.
+pub fn send_ipi(hart_mask: usize) {
+    sbi_call(SBI_SEND_IPI, &hart_mask as *const _ as usize, 0, 0);
+}
+
+/// Start a stopped hart at `start_addr` with a0 = `hartid` and a1 = `opaque`,
+/// returns false if the SBI cannot, e.g. because the hart is running already.
+pub fn hart_start(hartid: usize, start_addr: usize, opaque: usize) -> bool {
+    sbi_call_ext(SBI_EXT_HSM, SBI_HSM_HART_START, hartid, start_addr, opaque).is_ok()
+}
+
+/// State of hart `hartid`, SBI_ERR_INVALID_PARAM if there is no such hart.
+pub fn hart_get_status(hartid: usize) -> Result<usize, isize> {
+    sbi_call_ext(SBI_EXT_HSM, SBI_HSM_HART_GET_STATUS, hartid, 0, 0)
+}
+
 pub fn shutdown() -> ! {
     sbi_call(SBI_SHUTDOWN, 0, 0, 0);
     panic!("It should shutdown!");
@@ -0,0 +1,118 @@
+use crate::config::{MAX_HARTS, BOARD_HARTS, BOOT_STACK_SIZE};
+use crate::sbi::{send_ipi, hart_start, hart_get_status, SBI_ERR_NOT_SUPPORTED};
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering, spin_loop_hint};
+
+/// Read by entry.asm, which parks harts with an id of `MAX_HARTS` or above.
+#[export_name = "boot_hart_count"]
+static ENTRY_HART_COUNT: usize = MAX_HARTS;
+#[export_name = "boot_stack_size"]
+static ENTRY_STACK_SIZE: usize = BOOT_STACK_SIZE;
+
+#[repr(align(4096))]
+struct BootStacks([u8; BOOT_STACK_SIZE * MAX_HARTS]);
+
+/// The boot stack of hart `i` ends at `(i + 1) * BOOT_STACK_SIZE`. They are
+/// below `sbss`, so clearing .bss does not wipe those already in use.
+#[export_name = "boot_stack"]
+#[link_section = ".bss.stack"]
+static mut BOOT_STACKS: BootStacks = BootStacks([0; BOOT_STACK_SIZE * MAX_HARTS]);
+
+/// Hart which won the race to boot, the others wait until it is done.
+/// It is not zero-initialized, so clearing .bss leaves it alone.
+static BOOT_HART: AtomicUsize = AtomicUsize::new(usize::MAX);
+static BOOTED: AtomicBool = AtomicBool::new(false);
+
+const NO_USER_SPACE: AtomicUsize = AtomicUsize::new(0);
+const NO_TRAPS: AtomicUsize = AtomicUsize::new(0);
+/// Token of the user space each hart is running in, 0 while in the kernel.
+static USER_TOKENS: [AtomicUsize; MAX_HARTS] = [NO_USER_SPACE; MAX_HARTS];
+/// Number of traps from user space taken by each hart, each of which flushes the TLB.
+static USER_TRAPS: [AtomicUsize; MAX_HARTS] = [NO_TRAPS; MAX_HARTS];
+
+/// The hart id is kept in `tp` while in the kernel.
+pub fn hart_id() -> usize {
+    let hart_id;
+    unsafe { llvm_asm!("mv $0, tp" : "=r"(hart_id) ::: "volatile"); }
+    hart_id
+}
+
+/// Return true on the hart which should initialize the kernel.
+pub fn claim_boot_hart(hart_id: usize) -> bool {
+    BOOT_HART.compare_exchange(usize::MAX, hart_id, Ordering::AcqRel, Ordering::Acquire).is_ok()
+}
+
+/// Whether the platform has hart `hart`, asking the SBI if it can tell.
+fn hart_exists(hart: usize) -> bool {
+    match hart_get_status(hart) {
+        Ok(_) => true,
+        Err(SBI_ERR_NOT_SUPPORTED) => hart < BOARD_HARTS,
+        Err(_) => false,
+    }
+}
+
+/// Called by the boot hart once the kernel is initialized. The other harts
+/// are either waiting for it already or stopped by the SBI, in which case
+/// they are started at `_start` as well.
+pub fn boot_other_harts() {
+    extern "C" {
+        fn _start();
+    }
+    BOOTED.store(true, Ordering::Release);
+    let boot_hart = hart_id();
+    let mut others = 0;
+    for hart in (0..MAX_HARTS).filter(|hart| *hart != boot_hart && hart_exists(*hart)) {
+        hart_start(hart, _start as usize, 0);
+        others |= 1 << hart;
+    }
+    // some SBI implementations hold the other harts until they get an IPI
+    send_ipi(others);
+}
+
+pub fn wait_for_boot() {
+    while !BOOTED.load(Ordering::Acquire) {
+        spin_loop_hint();
+    }
+}
+
+/// Called right before returning to the user space of `token`.
+pub fn enter_user(token: usize) {
+    USER_TOKENS[hart_id()].store(token, Ordering::SeqCst);
+}
+
+/// Called first on a trap from user space, by which the TLB has been flushed.
+pub fn leave_user() {
+    let hart_id = hart_id();
+    USER_TRAPS[hart_id].fetch_add(1, Ordering::SeqCst);
+    USER_TOKENS[hart_id].store(0, Ordering::SeqCst);
+}
+
+/// Make the other harts drop the translations they may have cached from the
+/// page table of `token`, after entries of it have been changed or removed.
+/// A hart in the kernel flushes its TLB on the way back to user space anyway;
+/// one running in that user space gets an IPI, and we wait until it traps.
+pub fn tlb_shootdown(token: usize) {
+    let hart_id = hart_id();
+    let mut targets = 0;
+    let mut traps = [0; MAX_HARTS];
+    for hart in (0..MAX_HARTS).filter(|hart| *hart != hart_id) {
+        if USER_TOKENS[hart].load(Ordering::SeqCst) == token {
+            targets |= 1 << hart;
+            traps[hart] = USER_TRAPS[hart].load(Ordering::SeqCst);
+        }
+    }
+    if targets == 0 {
+        return;
+    }
+    send_ipi(targets);
+    for hart in (0..MAX_HARTS).filter(|hart| targets & (1 << hart) != 0) {
+        while USER_TOKENS[hart].load(Ordering::SeqCst) == token
+            && USER_TRAPS[hart].load(Ordering::SeqCst) == traps[hart] {
+            spin_loop_hint();
+        }
+    }
+}
+
+/// Acknowledge an IPI, the trap which delivered it has flushed the TLB already.
+pub fn handle_ipi() {
+    unsafe { llvm_asm!("csrci sip, 2" :::: "volatile"); }
+}
@@ -0,0 +1,32 @@
+use crate::task::{WaitQueue, current_has_pending_signal};
+use crate::error::SysResult;
+use alloc::sync::Arc;
+use super::Mutex;
+
+/// A condition variable shared by the threads of a process.
+pub struct Condvar {
+    wait_queue: WaitQueue,
+}
+
+impl Condvar {
+    pub fn new() -> Self {
+        Self { wait_queue: WaitQueue::new() }
+    }
+    pub fn signal(&self) {
+        self.wait_queue.wake_one();
+    }
+    /// Release `mutex`, sleep until signaled and lock `mutex` again.
+    /// Wakeups may be spurious, e.g. because of a signal, so the caller
+    /// should check its condition in a loop. If relocking is interrupted
+    /// by a signal, EINTR is returned with `mutex` unlocked.
+    pub fn wait(&self, mutex: Arc<dyn Mutex>) -> SysResult<()> {
+        // a signal between unlocking and sleeping is not missed
+        let ticket = self.wait_queue.ticket();
+        mutex.unlock()?;
+        // a pending signal would make us sleep through it
+        if !current_has_pending_signal() {
+            self.wait_queue.wait(ticket);
+        }
+        mutex.lock()
+    }
+}
@@ -0,0 +1,7 @@
+mod mutex;
+mod semaphore;
+mod condvar;
+
+pub use mutex::{Mutex, MutexSpin, MutexBlocking};
+pub use semaphore::Semaphore;
+pub use condvar::Condvar;
@@ -0,0 +1,100 @@
+use crate::task::{WaitQueue, current_task, suspend_current_and_run_next, current_has_pending_signal};
+use crate::error::{SysError, SysResult};
+
+/// A lock shared by the threads of a process.
+/// Locking fails with EINTR if a signal arrives meanwhile, the caller may retry.
+/// Only the thread holding the lock may unlock it, others get EPERM.
+pub trait Mutex: Sync + Send {
+    fn lock(&self) -> SysResult<()>;
+    fn unlock(&self) -> SysResult<()>;
+}
+
+fn current_tid() -> usize {
+    current_task().unwrap().gettid()
+}
+
+/// Yields the processor until the lock is released.
+pub struct MutexSpin {
+    /// The tid of the thread holding the lock.
+    owner: spin::Mutex<Option<usize>>,
+}
+
+impl MutexSpin {
+    pub fn new() -> Self {
+        Self { owner: spin::Mutex::new(None) }
+    }
+}
+
+impl Mutex for MutexSpin {
+    fn lock(&self) -> SysResult<()> {
+        let tid = current_tid();
+        loop {
+            let mut owner = self.owner.lock();
+            if owner.is_none() {
+                *owner = Some(tid);
+                return Ok(());
+            }
+            drop(owner);
+            if current_has_pending_signal() {
+                return Err(SysError::EINTR);
+            }
+            suspend_current_and_run_next();
+        }
+    }
+    fn unlock(&self) -> SysResult<()> {
+        let tid = current_tid();
+        let mut owner = self.owner.lock();
+        if *owner != Some(tid) {
+            return Err(SysError::EPERM);
+        }
+        *owner = None;
+        Ok(())
+    }
+}
+
+/// Blocks on a wait queue until the lock is released.
+pub struct MutexBlocking {
+    /// The tid of the thread holding the lock.
+    owner: spin::Mutex<Option<usize>>,
+    wait_queue: WaitQueue,
+}
+
+impl MutexBlocking {
+    pub fn new() -> Self {
+        Self {
+            owner: spin::Mutex::new(None),
+            wait_queue: WaitQueue::new(),
+        }
+    }
+}
+
+impl Mutex for MutexBlocking {
+    fn lock(&self) -> SysResult<()> {
+        let tid = current_tid();
+        loop {
+            let ticket = self.wait_queue.ticket();
+            let mut owner = self.owner.lock();
+            if owner.is_none() {
+                *owner = Some(tid);
+                return Ok(());
+            }
+            drop(owner);
+            if current_has_pending_signal() {
+                return Err(SysError::EINTR);
+            }
+            self.wait_queue.wait(ticket);
+        }
+    }
+    fn unlock(&self) -> SysResult<()> {
+        let tid = current_tid();
+        let mut owner = self.owner.lock();
+        if *owner != Some(tid) {
+            return Err(SysError::EPERM);
+        }
+        *owner = None;
+        drop(owner);
+        // the woken thread competes for the lock again
+        self.wait_queue.wake_one();
+        Ok(())
+    }
+}
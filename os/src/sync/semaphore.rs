@@ -0,0 +1,38 @@
+use crate::task::{WaitQueue, current_has_pending_signal};
+use crate::error::{SysError, SysResult};
+use spin::Mutex;
+
+/// A counting semaphore shared by the threads of a process.
+pub struct Semaphore {
+    count: Mutex<usize>,
+    wait_queue: WaitQueue,
+}
+
+impl Semaphore {
+    pub fn new(res_count: usize) -> Self {
+        Self {
+            count: Mutex::new(res_count),
+            wait_queue: WaitQueue::new(),
+        }
+    }
+    pub fn up(&self) {
+        *self.count.lock() += 1;
+        self.wait_queue.wake_one();
+    }
+    /// Fails with EINTR if a signal arrives while blocking, the caller may retry.
+    pub fn down(&self) -> SysResult<()> {
+        loop {
+            let ticket = self.wait_queue.ticket();
+            let mut count = self.count.lock();
+            if *count > 0 {
+                *count -= 1;
+                return Ok(());
+            }
+            drop(count);
+            if current_has_pending_signal() {
+                return Err(SysError::EINTR);
+            }
+            self.wait_queue.wait(ticket);
+        }
+    }
+}
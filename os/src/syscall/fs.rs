@@ -3,109 +3,184 @@ use crate::mm::{
     translated_byte_buffer,
     translated_refmut,
     translated_str,
+    copy_to_user,
 };
-use crate::task::{current_user_token, current_task};
-use crate::fs::{make_pipe, OpenFlags, open_file};
+use crate::task::{current_user_token, current_process, pgid2processes};
+use crate::fs::{make_pipe, OpenFlags, open_file, foreground_pgid, set_foreground_pgid};
+use crate::error::{SysError, SysResult};
 use alloc::sync::Arc;
 
-pub fn sys_write(fd: usize, buf: *const u8, len: usize) -> isize {
+pub fn sys_write(fd: usize, buf: *const u8, len: usize) -> SysResult {
     let token = current_user_token();
-    let task = current_task().unwrap();
-    let inner = task.acquire_inner_lock();
+    let process = current_process();
+    let mut inner = process.acquire_inner_lock();
     if fd >= inner.fd_table.len() {
-        return -1;
+        return Err(SysError::EBADF);
     }
     if let Some(file) = &inner.fd_table[fd] {
         if !file.writable() {
-            return -1;
+            return Err(SysError::EBADF);
         }
         let file = file.clone();
-        // release Task lock manually to avoid deadlock
+        // the pages stay in memory while the file works on them
+        let _pins = inner.memory_set.pin_user_access(buf as usize, len, false)?;
+        // release PCB lock manually to avoid deadlock
         drop(inner);
-        file.write(
-            UserBuffer::new(translated_byte_buffer(token, buf, len))
-        ) as isize
+        Ok(file.write(
+            UserBuffer::new(translated_byte_buffer(token, buf, len)?)
+        ))
     } else {
-        -1
+        Err(SysError::EBADF)
     }
 }
 
-pub fn sys_read(fd: usize, buf: *const u8, len: usize) -> isize {
+pub fn sys_read(fd: usize, buf: *const u8, len: usize) -> SysResult {
     let token = current_user_token();
-    let task = current_task().unwrap();
-    let inner = task.acquire_inner_lock();
+    let process = current_process();
+    let mut inner = process.acquire_inner_lock();
     if fd >= inner.fd_table.len() {
-        return -1;
+        return Err(SysError::EBADF);
     }
     if let Some(file) = &inner.fd_table[fd] {
         let file = file.clone();
         if !file.readable() {
-            return -1;
+            return Err(SysError::EBADF);
         }
-        // release Task lock manually to avoid deadlock
+        // the pages stay in memory while the file works on them
+        let _pins = inner.memory_set.pin_user_access(buf as usize, len, true)?;
+        // release PCB lock manually to avoid deadlock
         drop(inner);
-        file.read(
-            UserBuffer::new(translated_byte_buffer(token, buf, len))
-        ) as isize
+        Ok(file.read(
+            UserBuffer::new(translated_byte_buffer(token, buf, len)?)
+        ))
     } else {
-        -1
+        Err(SysError::EBADF)
     }
 }
 
-pub fn sys_open(path: *const u8, flags: u32) -> isize {
-    let task = current_task().unwrap();
+pub fn sys_open(path: *const u8, flags: u32) -> SysResult {
+    let process = current_process();
     let token = current_user_token();
-    let path = translated_str(token, path);
-    if let Some(inode) = open_file(
-        path.as_str(),
-        OpenFlags::from_bits(flags).unwrap()
-    ) {
-        let mut inner = task.acquire_inner_lock();
-        let fd = inner.alloc_fd();
-        inner.fd_table[fd] = Some(inode);
-        fd as isize
-    } else {
-        -1
-    }
+    let mut inner = process.acquire_inner_lock();
+    inner.memory_set.prepare_user_str(path as usize)?;
+    let path = translated_str(token, path)?;
+    drop(inner);
+    let flags = OpenFlags::from_bits(flags).ok_or(SysError::EINVAL)?;
+    let inode = open_file(path.as_str(), flags).ok_or(SysError::ENOENT)?;
+    let mut inner = process.acquire_inner_lock();
+    let fd = inner.alloc_fd()?;
+    inner.fd_table[fd] = Some(inode);
+    Ok(fd)
 }
 
-pub fn sys_close(fd: usize) -> isize {
-    let task = current_task().unwrap();
-    let mut inner = task.acquire_inner_lock();
+pub fn sys_close(fd: usize) -> SysResult {
+    let process = current_process();
+    let mut inner = process.acquire_inner_lock();
     if fd >= inner.fd_table.len() {
-        return -1;
+        return Err(SysError::EBADF);
     }
     if inner.fd_table[fd].is_none() {
-        return -1;
+        return Err(SysError::EBADF);
     }
     inner.fd_table[fd].take();
-    0
+    Ok(0)
 }
 
-pub fn sys_pipe(pipe: *mut usize) -> isize {
-    let task = current_task().unwrap();
+pub fn sys_pipe(pipe: *mut usize) -> SysResult {
+    let process = current_process();
     let token = current_user_token();
-    let mut inner = task.acquire_inner_lock();
+    // the lock keeps the page from being swapped out until it is written
+    let mut inner = process.acquire_inner_lock();
+    inner.memory_set.prepare_user_access(pipe as usize, 2 * core::mem::size_of::<usize>(), true)?;
+    // check the user buffer before any fd is allocated
+    let read_fd_ref = translated_refmut(token, pipe)?;
+    let write_fd_ref = translated_refmut(token, unsafe { pipe.add(1) })?;
     let (pipe_read, pipe_write) = make_pipe();
-    let read_fd = inner.alloc_fd();
+    let read_fd = inner.alloc_fd()?;
     inner.fd_table[read_fd] = Some(pipe_read);
-    let write_fd = inner.alloc_fd();
+    let write_fd = match inner.alloc_fd() {
+        Ok(fd) => fd,
+        Err(err) => {
+            inner.fd_table[read_fd].take();
+            return Err(err);
+        }
+    };
     inner.fd_table[write_fd] = Some(pipe_write);
-    *translated_refmut(token, pipe) = read_fd;
-    *translated_refmut(token, unsafe { pipe.add(1) }) = write_fd;
-    0
+    *read_fd_ref = read_fd;
+    *write_fd_ref = write_fd;
+    Ok(0)
 }
 
-pub fn sys_dup(fd: usize) -> isize {
-    let task = current_task().unwrap();
-    let mut inner = task.acquire_inner_lock();
+pub fn sys_dup(fd: usize) -> SysResult {
+    let process = current_process();
+    let mut inner = process.acquire_inner_lock();
     if fd >= inner.fd_table.len() {
-        return -1;
+        return Err(SysError::EBADF);
     }
     if inner.fd_table[fd].is_none() {
-        return -1;
+        return Err(SysError::EBADF);
     }
-    let new_fd = inner.alloc_fd();
+    let new_fd = inner.alloc_fd()?;
     inner.fd_table[new_fd] = Some(Arc::clone(inner.fd_table[fd].as_ref().unwrap()));
-    new_fd as isize
-}
\ No newline at end of file
+    Ok(new_fd)
+}
+
+const TIOCGPGRP: usize = 0x540f;
+const TIOCSPGRP: usize = 0x5410;
+const TIOCGWINSZ: usize = 0x5413;
+
+/// Same layout as `struct winsize` in Linux.
+#[repr(C)]
+struct WinSize {
+    ws_row: u16,
+    ws_col: u16,
+    ws_xpixel: u16,
+    ws_ypixel: u16,
+}
+
+/// The serial console cannot tell its size, so the usual one is reported.
+const CONSOLE_SIZE: WinSize = WinSize { ws_row: 24, ws_col: 80, ws_xpixel: 0, ws_ypixel: 0 };
+
+/// Only getting and setting the foreground process group of the console are
+/// supported, `arg` points to the pgid as an i32 like in Linux. The new group
+/// has to be in the session of the caller. The size of the console can be
+/// read as well, which libc does to tell whether a file is a terminal.
+pub fn sys_ioctl(fd: usize, cmd: usize, arg: usize) -> SysResult {
+    let process = current_process();
+    let mut inner = process.acquire_inner_lock();
+    let file = inner.fd_table.get(fd).and_then(|file| file.clone()).ok_or(SysError::EBADF)?;
+    if !file.is_tty() {
+        return Err(SysError::ENOTTY);
+    }
+    let token = inner.get_user_token();
+    let pgid_ptr = arg as *mut i32;
+    match cmd {
+        TIOCGPGRP => {
+            inner.memory_set.prepare_user_access(arg, core::mem::size_of::<i32>(), true)?;
+            *translated_refmut(token, pgid_ptr)? = foreground_pgid() as i32;
+            Ok(0)
+        }
+        TIOCSPGRP => {
+            inner.memory_set.prepare_user_access(arg, core::mem::size_of::<i32>(), false)?;
+            let pgid = *translated_refmut(token, pgid_ptr)?;
+            let sid = inner.sid;
+            drop(inner);
+            if pgid < 0 {
+                return Err(SysError::EINVAL);
+            }
+            if !pgid2processes(pgid as usize)
+                .iter()
+                .any(|member| member.acquire_inner_lock().sid == sid) {
+                return Err(SysError::EPERM);
+            }
+            set_foreground_pgid(pgid as usize);
+            Ok(0)
+        }
+        TIOCGWINSZ => {
+            inner.memory_set.prepare_user_access(arg, core::mem::size_of::<WinSize>(), true)?;
+            copy_to_user(token, arg as *mut WinSize, &CONSOLE_SIZE)?;
+            Ok(0)
+        }
+        _ => Err(SysError::EINVAL),
+    }
+}
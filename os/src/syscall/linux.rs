@@ -0,0 +1,445 @@
+use super::fs::{sys_dup, sys_ioctl, sys_close, sys_read, sys_write};
+use super::process::{
+    sys_exit,
+    sys_nanosleep,
+    sys_clock_nanosleep,
+    sys_yield,
+    sys_kill,
+    sys_getpid,
+    sys_fork,
+    sys_execve,
+    sys_waitpid,
+    RUsage,
+};
+use super::memory::{sys_brk, sys_mmap, sys_munmap};
+use super::table::{is_sorted, SyscallEntry, SyscallTable, NO_CALLS};
+use crate::task::{current_process, exit_current_group_and_run_next};
+use crate::fs::{open_file, OpenFlags, File, FileKind, FileStat, ROOT_INODE};
+use crate::mm::{translated_str, copy_to_user, copy_from_user};
+use crate::timer::{get_time, TimeSpec};
+use crate::error::{SysError, SysResult};
+use alloc::string::String;
+use core::sync::atomic::AtomicUsize;
+
+// System calls of Linux on riscv64, from the asm-generic table.
+const SYSCALL_GETCWD: usize = 17;
+const SYSCALL_DUP: usize = 23;
+const SYSCALL_IOCTL: usize = 29;
+const SYSCALL_OPENAT: usize = 56;
+const SYSCALL_CLOSE: usize = 57;
+const SYSCALL_READ: usize = 63;
+const SYSCALL_WRITE: usize = 64;
+const SYSCALL_READV: usize = 65;
+const SYSCALL_WRITEV: usize = 66;
+const SYSCALL_NEWFSTATAT: usize = 79;
+const SYSCALL_FSTAT: usize = 80;
+const SYSCALL_PERSONALITY: usize = 92;
+const SYSCALL_EXIT: usize = 93;
+const SYSCALL_EXIT_GROUP: usize = 94;
+const SYSCALL_SET_TID_ADDRESS: usize = 96;
+const SYSCALL_NANOSLEEP: usize = 101;
+const SYSCALL_CLOCK_GETTIME: usize = 113;
+const SYSCALL_CLOCK_NANOSLEEP: usize = 115;
+const SYSCALL_SCHED_YIELD: usize = 124;
+const SYSCALL_KILL: usize = 129;
+const SYSCALL_GETPID: usize = 172;
+const SYSCALL_GETPPID: usize = 173;
+const SYSCALL_GETUID: usize = 174;
+const SYSCALL_GETEUID: usize = 175;
+const SYSCALL_GETGID: usize = 176;
+const SYSCALL_GETEGID: usize = 177;
+const SYSCALL_GETTID: usize = 178;
+const SYSCALL_BRK: usize = 214;
+const SYSCALL_MUNMAP: usize = 215;
+const SYSCALL_CLONE: usize = 220;
+const SYSCALL_EXECVE: usize = 221;
+const SYSCALL_MMAP: usize = 222;
+const SYSCALL_WAIT4: usize = 260;
+
+/// The system calls a statically linked musl program needs, mostly served
+/// by the native ones. Sorted by id, like the native table.
+const LINUX_SYSCALLS: &[SyscallEntry] = &[
+    SyscallEntry { id: SYSCALL_GETCWD, name: "getcwd", handler: |args| linux_getcwd(args[0] as *mut u8, args[1]) },
+    SyscallEntry { id: SYSCALL_DUP, name: "dup", handler: |args| sys_dup(args[0]) },
+    SyscallEntry { id: SYSCALL_IOCTL, name: "ioctl", handler: |args| sys_ioctl(args[0], args[1], args[2]) },
+    SyscallEntry {
+        id: SYSCALL_OPENAT,
+        name: "openat",
+        handler: |args| linux_openat(args[0], args[1] as *const u8, args[2]),
+    },
+    SyscallEntry { id: SYSCALL_CLOSE, name: "close", handler: |args| sys_close(args[0]) },
+    SyscallEntry {
+        id: SYSCALL_READ,
+        name: "read",
+        handler: |args| sys_read(args[0], args[1] as *const u8, args[2]),
+    },
+    SyscallEntry {
+        id: SYSCALL_WRITE,
+        name: "write",
+        handler: |args| sys_write(args[0], args[1] as *const u8, args[2]),
+    },
+    SyscallEntry {
+        id: SYSCALL_READV,
+        name: "readv",
+        handler: |args| linux_rw_vectored(args[0], args[1] as *const IoVec, args[2], false),
+    },
+    SyscallEntry {
+        id: SYSCALL_WRITEV,
+        name: "writev",
+        handler: |args| linux_rw_vectored(args[0], args[1] as *const IoVec, args[2], true),
+    },
+    SyscallEntry {
+        id: SYSCALL_NEWFSTATAT,
+        name: "newfstatat",
+        handler: |args| linux_newfstatat(args[0], args[1] as *const u8, args[2] as *mut Stat, args[3]),
+    },
+    SyscallEntry { id: SYSCALL_FSTAT, name: "fstat", handler: |args| linux_fstat(args[0], args[1] as *mut Stat) },
+    SyscallEntry { id: SYSCALL_PERSONALITY, name: "personality", handler: |args| linux_personality(args[0]) },
+    SyscallEntry { id: SYSCALL_EXIT, name: "exit", handler: |args| sys_exit(args[0] as i32) },
+    SyscallEntry {
+        id: SYSCALL_EXIT_GROUP,
+        name: "exit_group",
+        handler: |args| {
+            exit_current_group_and_run_next(args[0] as i32);
+            panic!("Unreachable in exit_group!");
+        },
+    },
+    // the tid is not cleared at exit, as there is only the main thread
+    SyscallEntry { id: SYSCALL_SET_TID_ADDRESS, name: "set_tid_address", handler: |_| sys_getpid() },
+    SyscallEntry {
+        id: SYSCALL_NANOSLEEP,
+        name: "nanosleep",
+        handler: |args| sys_nanosleep(args[0] as *const TimeSpec, args[1] as *mut TimeSpec),
+    },
+    SyscallEntry {
+        id: SYSCALL_CLOCK_GETTIME,
+        name: "clock_gettime",
+        handler: |args| linux_clock_gettime(args[0], args[1] as *mut TimeSpec),
+    },
+    SyscallEntry {
+        id: SYSCALL_CLOCK_NANOSLEEP,
+        name: "clock_nanosleep",
+        handler: |args| sys_clock_nanosleep(args[0], args[1], args[2] as *const TimeSpec, args[3] as *mut TimeSpec),
+    },
+    SyscallEntry { id: SYSCALL_SCHED_YIELD, name: "sched_yield", handler: |_| sys_yield() },
+    SyscallEntry { id: SYSCALL_KILL, name: "kill", handler: |args| sys_kill(args[0] as isize, args[1]) },
+    SyscallEntry { id: SYSCALL_GETPID, name: "getpid", handler: |_| sys_getpid() },
+    SyscallEntry { id: SYSCALL_GETPPID, name: "getppid", handler: |_| linux_getppid() },
+    // everyone is root
+    SyscallEntry { id: SYSCALL_GETUID, name: "getuid", handler: |_| Ok(0) },
+    SyscallEntry { id: SYSCALL_GETEUID, name: "geteuid", handler: |_| Ok(0) },
+    SyscallEntry { id: SYSCALL_GETGID, name: "getgid", handler: |_| Ok(0) },
+    SyscallEntry { id: SYSCALL_GETEGID, name: "getegid", handler: |_| Ok(0) },
+    SyscallEntry { id: SYSCALL_GETTID, name: "gettid", handler: |_| sys_getpid() },
+    SyscallEntry { id: SYSCALL_BRK, name: "brk", handler: |args| linux_brk(args[0]) },
+    SyscallEntry { id: SYSCALL_MUNMAP, name: "munmap", handler: |args| sys_munmap(args[0], args[1]) },
+    SyscallEntry { id: SYSCALL_CLONE, name: "clone", handler: |args| linux_clone(args[0], args[1]) },
+    SyscallEntry {
+        id: SYSCALL_EXECVE,
+        name: "execve",
+        handler: |args| sys_execve(args[0] as *const u8, args[1] as *const usize, args[2] as *const usize),
+    },
+    SyscallEntry {
+        id: SYSCALL_MMAP,
+        name: "mmap",
+        handler: |args| linux_mmap(args[0], args[1], args[2], args[3]),
+    },
+    SyscallEntry {
+        id: SYSCALL_WAIT4,
+        name: "wait4",
+        handler: |args| linux_wait4(args[0] as isize, args[1] as *mut i32, args[2], args[3] as *mut RUsage),
+    },
+];
+
+const _: [(); 0 - !is_sorted(LINUX_SYSCALLS) as usize] = [];
+
+static LINUX_COUNTS: [AtomicUsize; LINUX_SYSCALLS.len()] = [NO_CALLS; LINUX_SYSCALLS.len()];
+
+pub static LINUX_TABLE: SyscallTable = SyscallTable {
+    entries: LINUX_SYSCALLS,
+    counts: &LINUX_COUNTS,
+};
+
+/// Everything is in the root directory, which is where everyone works.
+fn linux_getcwd(buf: *mut u8, size: usize) -> SysResult {
+    const CWD: [u8; 2] = [b'/', 0];
+    if size < CWD.len() {
+        return Err(SysError::ERANGE);
+    }
+    let process = current_process();
+    let mut inner = process.acquire_inner_lock();
+    inner.memory_set.prepare_user_access(buf as usize, CWD.len(), true)?;
+    copy_to_user(inner.get_user_token(), buf as *mut [u8; 2], &CWD)?;
+    Ok(CWD.len())
+}
+
+const AT_FDCWD: isize = -100;
+const AT_EMPTY_PATH: usize = 0x1000;
+
+/// Name in the root directory of `path`, relative to `dirfd` unless it
+/// is absolute. The files of easy-fs are not directories, so only the
+/// current one, i.e. the root, can be given as `AT_FDCWD`. An empty path
+/// is left as it is.
+fn linux_path(dirfd: usize, path: *const u8) -> SysResult<String> {
+    let process = current_process();
+    let mut inner = process.acquire_inner_lock();
+    inner.memory_set.prepare_user_str(path as usize)?;
+    let path = translated_str(inner.get_user_token(), path)?;
+    if !path.is_empty() && !path.starts_with('/') && dirfd as isize != AT_FDCWD {
+        return match inner.fd_table.get(dirfd) {
+            Some(Some(_)) => Err(SysError::ENOTDIR),
+            _ => Err(SysError::EBADF),
+        };
+    }
+    let name = path.trim_start_matches('/');
+    Ok(String::from(name.strip_prefix("./").unwrap_or(name)))
+}
+
+const O_ACCMODE: usize = 0o3;
+const O_CREAT: usize = 0o100;
+const O_EXCL: usize = 0o200;
+const O_TRUNC: usize = 0o1000;
+const O_APPEND: usize = 0o2000;
+const O_DIRECTORY: usize = 0o200000;
+
+/// Flags other than the access mode, `O_CREAT`, `O_EXCL` and `O_TRUNC` are
+/// ignored, except for `O_APPEND` and `O_DIRECTORY` which cannot be honored.
+fn linux_openat(dirfd: usize, path: *const u8, flags: usize) -> SysResult {
+    let name = linux_path(dirfd, path)?;
+    if name.is_empty() {
+        return Err(SysError::ENOENT);
+    }
+    if flags & O_ACCMODE == O_ACCMODE || flags & O_APPEND != 0 {
+        return Err(SysError::EINVAL);
+    }
+    if flags & O_DIRECTORY != 0 {
+        return Err(SysError::ENOTDIR);
+    }
+    let mut open_flags = OpenFlags::from_bits_truncate((flags & O_ACCMODE) as u32);
+    if flags & O_TRUNC != 0 {
+        open_flags |= OpenFlags::TRUNC;
+    }
+    // OpenFlags::CREATE truncates the file, unlike O_CREAT
+    if flags & O_CREAT != 0 {
+        match ROOT_INODE.find(name.as_str()) {
+            Some(_) if flags & O_EXCL != 0 => return Err(SysError::EEXIST),
+            Some(_) => {}
+            None => open_flags |= OpenFlags::CREATE,
+        }
+    }
+    let inode = open_file(name.as_str(), open_flags).ok_or(SysError::ENOENT)?;
+    let process = current_process();
+    let mut inner = process.acquire_inner_lock();
+    let fd = inner.alloc_fd()?;
+    inner.fd_table[fd] = Some(inode);
+    Ok(fd)
+}
+
+/// Same layout as `struct iovec` in Linux.
+#[repr(C)]
+#[derive(Copy, Clone, Default)]
+struct IoVec {
+    base: usize,
+    len: usize,
+}
+
+const IOV_MAX: usize = 1024;
+
+/// Read or write the buffers in turn, stopping at the first short transfer.
+/// An error is only reported if nothing has been transferred.
+fn linux_rw_vectored(fd: usize, iov: *const IoVec, iovcnt: usize, write: bool) -> SysResult {
+    if iovcnt > IOV_MAX {
+        return Err(SysError::EINVAL);
+    }
+    let process = current_process();
+    let mut total = 0;
+    for i in 0..iovcnt {
+        let mut vec = IoVec::default();
+        {
+            let mut inner = process.acquire_inner_lock();
+            let vec_ptr = unsafe { iov.add(i) };
+            inner.memory_set.prepare_user_access(vec_ptr as usize, core::mem::size_of::<IoVec>(), false)?;
+            copy_from_user(inner.get_user_token(), vec_ptr, &mut vec)?;
+        }
+        if vec.len == 0 {
+            continue;
+        }
+        let ret = if write {
+            sys_write(fd, vec.base as *const u8, vec.len)
+        } else {
+            sys_read(fd, vec.base as *const u8, vec.len)
+        };
+        match ret {
+            Ok(len) => {
+                total += len;
+                if len < vec.len {
+                    break;
+                }
+            }
+            Err(err) if total == 0 => return Err(err),
+            Err(_) => break,
+        }
+    }
+    Ok(total)
+}
+
+/// Same layout as `struct stat` in Linux on riscv64.
+#[repr(C)]
+#[derive(Default)]
+struct Stat {
+    st_dev: u64,
+    st_ino: u64,
+    st_mode: u32,
+    st_nlink: u32,
+    st_uid: u32,
+    st_gid: u32,
+    st_rdev: u64,
+    __pad1: u64,
+    st_size: i64,
+    st_blksize: i32,
+    __pad2: i32,
+    st_blocks: i64,
+    /// Access, modification and status change times as timespecs, all 0.
+    st_times: [u64; 6],
+    __unused: [u32; 2],
+}
+
+const S_IFIFO: u32 = 0o010000;
+const S_IFCHR: u32 = 0o020000;
+const S_IFREG: u32 = 0o100000;
+/// The block size of easy-fs, which is also the unit of `st_blocks`.
+const BLOCK_SIZE: usize = 512;
+
+fn stat_to_user(stat: FileStat, buf: *mut Stat) -> SysResult {
+    let st_mode = match stat.kind {
+        FileKind::Regular => S_IFREG | 0o755,
+        FileKind::CharDevice => S_IFCHR | 0o620,
+        FileKind::Fifo => S_IFIFO | 0o600,
+    };
+    let st = Stat {
+        st_ino: stat.ino as u64,
+        st_mode,
+        st_nlink: 1,
+        st_size: stat.size as i64,
+        st_blksize: BLOCK_SIZE as i32,
+        st_blocks: ((stat.size + BLOCK_SIZE - 1) / BLOCK_SIZE) as i64,
+        ..Stat::default()
+    };
+    let process = current_process();
+    let mut inner = process.acquire_inner_lock();
+    inner.memory_set.prepare_user_access(buf as usize, core::mem::size_of::<Stat>(), true)?;
+    copy_to_user(inner.get_user_token(), buf, &st)?;
+    Ok(0)
+}
+
+fn linux_fstat(fd: usize, buf: *mut Stat) -> SysResult {
+    let file = current_process()
+        .acquire_inner_lock()
+        .fd_table
+        .get(fd)
+        .and_then(|file| file.clone())
+        .ok_or(SysError::EBADF)?;
+    stat_to_user(file.stat(), buf)
+}
+
+fn linux_newfstatat(dirfd: usize, path: *const u8, buf: *mut Stat, flags: usize) -> SysResult {
+    let name = linux_path(dirfd, path)?;
+    if name.is_empty() && flags & AT_EMPTY_PATH != 0 {
+        return linux_fstat(dirfd, buf);
+    }
+    let inode = open_file(name.as_str(), OpenFlags::RDONLY).ok_or(SysError::ENOENT)?;
+    stat_to_user(inode.stat(), buf)
+}
+
+const PER_LINUX: usize = 0;
+const PERSONALITY_QUERY: usize = 0xffff_ffff;
+
+/// Linux is the only personality which can be asked for from here.
+fn linux_personality(persona: usize) -> SysResult {
+    match persona {
+        PER_LINUX | PERSONALITY_QUERY => Ok(PER_LINUX),
+        _ => Err(SysError::EINVAL),
+    }
+}
+
+const CLOCK_REALTIME: usize = 0;
+const CLOCK_MONOTONIC: usize = 1;
+const CLOCK_MONOTONIC_RAW: usize = 4;
+const CLOCK_BOOTTIME: usize = 7;
+
+/// All clocks count from boot, as there is no real time clock.
+fn linux_clock_gettime(clock_id: usize, tp: *mut TimeSpec) -> SysResult {
+    match clock_id {
+        CLOCK_REALTIME | CLOCK_MONOTONIC | CLOCK_MONOTONIC_RAW | CLOCK_BOOTTIME => {}
+        _ => return Err(SysError::EINVAL),
+    }
+    let process = current_process();
+    let mut inner = process.acquire_inner_lock();
+    inner.memory_set.prepare_user_access(tp as usize, core::mem::size_of::<TimeSpec>(), true)?;
+    copy_to_user(inner.get_user_token(), tp, &TimeSpec::from_cycles(get_time()))?;
+    Ok(0)
+}
+
+/// Orphans have been handed to initproc, which has no parent itself.
+fn linux_getppid() -> SysResult {
+    let process = current_process();
+    let inner = process.acquire_inner_lock();
+    Ok(inner.parent
+        .as_ref()
+        .and_then(|parent| parent.upgrade())
+        .map_or(0, |parent| parent.getpid()))
+}
+
+/// Linux reports a failure by returning the end of the heap unchanged.
+fn linux_brk(addr: usize) -> SysResult {
+    sys_brk(addr).or_else(|_| sys_brk(0))
+}
+
+const SIGCHLD: usize = 17;
+const CLONE_VM: usize = 0x100;
+const CLONE_VFORK: usize = 0x4000;
+
+/// Only fork and vfork, the latter being done as a fork since the parent
+/// does not touch the memory of the child until it execs. Threads of Linux
+/// programs are not supported.
+fn linux_clone(flags: usize, stack: usize) -> SysResult {
+    let vfork = CLONE_VM | CLONE_VFORK;
+    if (flags != SIGCHLD && flags != vfork | SIGCHLD) || stack != 0 {
+        return Err(SysError::EINVAL);
+    }
+    sys_fork()
+}
+
+const MAP_SHARED: usize = 0x01;
+const MAP_FIXED: usize = 0x10;
+const MAP_ANONYMOUS: usize = 0x20;
+
+/// Private anonymous mappings only, the address is a hint unless `MAP_FIXED`
+/// is given, in which case whatever is mapped there is replaced.
+fn linux_mmap(addr: usize, len: usize, prot: usize, flags: usize) -> SysResult {
+    if flags & MAP_ANONYMOUS == 0 {
+        // files cannot be mapped
+        return Err(SysError::ENODEV);
+    }
+    if flags & MAP_SHARED != 0 {
+        // the pages would not be shared with children
+        return Err(SysError::EINVAL);
+    }
+    if flags & MAP_FIXED == 0 {
+        return sys_mmap(0, len, prot);
+    }
+    sys_munmap(addr, len)?;
+    sys_mmap(addr, len, prot)
+}
+
+/// The resource usage of the child is not kept apart, so it is reported as 0.
+fn linux_wait4(pid: isize, wstatus: *mut i32, options: usize, rusage: *mut RUsage) -> SysResult {
+    let pid = sys_waitpid(pid, wstatus, options)?;
+    if !rusage.is_null() {
+        let process = current_process();
+        let mut inner = process.acquire_inner_lock();
+        inner.memory_set.prepare_user_access(rusage as usize, core::mem::size_of::<RUsage>(), true)?;
+        copy_to_user(inner.get_user_token(), rusage, &RUsage::default())?;
+    }
+    Ok(pid)
+}
@@ -0,0 +1,130 @@
+use crate::task::{current_process, ProcessControlBlockInner};
+use crate::mm::{MapPermission, VirtAddr, VirtPageNum};
+use crate::config::{PAGE_SIZE, MMAP_BASE, MMAP_TOP, USER_STACK_TOP};
+use crate::error::{SysError, SysResult};
+
+bitflags! {
+    /// `prot` argument of mmap, as in Linux.
+    pub struct MmapProt: usize {
+        const READ = 1 << 0;
+        const WRITE = 1 << 1;
+        const EXEC = 1 << 2;
+    }
+}
+
+impl From<MmapProt> for MapPermission {
+    fn from(prot: MmapProt) -> Self {
+        let mut perm = MapPermission::U;
+        if prot.contains(MmapProt::READ) { perm |= MapPermission::R; }
+        // writable pages must be readable as well on RISC-V
+        if prot.contains(MmapProt::WRITE) { perm |= MapPermission::R | MapPermission::W; }
+        if prot.contains(MmapProt::EXEC) { perm |= MapPermission::X; }
+        perm
+    }
+}
+
+/// Whether `[start, end)` overlaps the range the heap may grow in, whose
+/// pages are only mapped and unmapped by brk/sbrk.
+fn overlaps_heap(inner: &ProcessControlBlockInner, start: usize, end: usize) -> bool {
+    start < MMAP_BASE && end > inner.heap_bottom
+}
+
+/// Map `len` bytes of zeroed anonymous memory at `addr`, or at an address
+/// chosen by the kernel if `addr` is 0. Returns the start of the mapping.
+pub fn sys_mmap(addr: usize, len: usize, prot: usize) -> SysResult {
+    let prot = MmapProt::from_bits(prot).ok_or(SysError::EINVAL)?;
+    if prot.is_empty() || len == 0 || addr % PAGE_SIZE != 0 {
+        return Err(SysError::EINVAL);
+    }
+    let page_count = (len - 1) / PAGE_SIZE + 1;
+    let process = current_process();
+    let mut inner = process.acquire_inner_lock();
+    let start_vpn = if addr == 0 {
+        inner.memory_set
+            .find_free_range(
+                VirtAddr::from(MMAP_BASE).into(),
+                VirtAddr::from(MMAP_TOP).into(),
+                page_count,
+            )
+            .ok_or(SysError::ENOMEM)?
+    } else {
+        let start_vpn: VirtPageNum = VirtAddr::from(addr).into();
+        if addr >= MMAP_TOP || page_count > (MMAP_TOP - addr) / PAGE_SIZE {
+            return Err(SysError::ENOMEM);
+        }
+        if overlaps_heap(&inner, addr, addr + page_count * PAGE_SIZE) {
+            return Err(SysError::ENOMEM);
+        }
+        if !inner.memory_set.is_range_free(start_vpn, VirtPageNum(start_vpn.0 + page_count)) {
+            return Err(SysError::EEXIST);
+        }
+        start_vpn
+    };
+    let start_va: VirtAddr = start_vpn.into();
+    let end_va: VirtAddr = VirtPageNum(start_vpn.0 + page_count).into();
+    // pages get their frames when first touched, and may be swapped out later
+    inner.memory_set.insert_lazy_area(start_va, end_va, prot.into());
+    Ok(start_va.into())
+}
+
+/// Unmap the pages in `[addr, addr + len)`, which need not be mapped.
+/// The heap is left to brk/sbrk.
+pub fn sys_munmap(addr: usize, len: usize) -> SysResult {
+    if len == 0 || addr % PAGE_SIZE != 0 {
+        return Err(SysError::EINVAL);
+    }
+    if addr >= USER_STACK_TOP || len > USER_STACK_TOP - addr {
+        return Err(SysError::EINVAL);
+    }
+    let process = current_process();
+    let mut inner = process.acquire_inner_lock();
+    if overlaps_heap(&inner, addr, addr + len) {
+        return Err(SysError::EINVAL);
+    }
+    inner.memory_set.remove_range(
+        VirtAddr::from(addr).into(),
+        VirtAddr::from(addr + len).ceil(),
+    );
+    Ok(0)
+}
+
+/// Move the end of the heap to `new_brk`, mapping or unmapping the pages in between.
+fn set_program_brk(inner: &mut ProcessControlBlockInner, new_brk: usize) -> SysResult<()> {
+    if new_brk < inner.heap_bottom || new_brk > MMAP_BASE {
+        return Err(SysError::ENOMEM);
+    }
+    let start_vpn: VirtPageNum = VirtAddr::from(inner.heap_bottom).into();
+    let new_end_vpn = VirtAddr::from(new_brk).ceil();
+    // fails if an mmap area is in the way
+    if !inner.memory_set.resize_area(start_vpn, new_end_vpn) {
+        return Err(SysError::ENOMEM);
+    }
+    inner.program_brk = new_brk;
+    Ok(())
+}
+
+/// Set the end of the heap to `addr`, or only query it if `addr` is 0.
+/// Returns the new end of the heap.
+pub fn sys_brk(addr: usize) -> SysResult {
+    let process = current_process();
+    let mut inner = process.acquire_inner_lock();
+    if addr != 0 {
+        set_program_brk(&mut inner, addr)?;
+    }
+    Ok(inner.program_brk)
+}
+
+/// Grow the heap by `increment` bytes, or shrink it if negative.
+/// Returns the old end of the heap, which is the start of the new memory.
+pub fn sys_sbrk(increment: isize) -> SysResult {
+    let process = current_process();
+    let mut inner = process.acquire_inner_lock();
+    let old_brk = inner.program_brk;
+    let new_brk = if increment >= 0 {
+        old_brk.checked_add(increment as usize)
+    } else {
+        old_brk.checked_sub(increment.wrapping_neg() as usize)
+    }.ok_or(SysError::ENOMEM)?;
+    set_program_brk(&mut inner, new_brk)?;
+    Ok(old_brk)
+}
@@ -1,39 +1,237 @@
 const SYSCALL_DUP: usize = 24;
+const SYSCALL_IOCTL: usize = 29;
 const SYSCALL_OPEN: usize = 56;
 const SYSCALL_CLOSE: usize = 57;
 const SYSCALL_PIPE: usize = 59;
 const SYSCALL_READ: usize = 63;
 const SYSCALL_WRITE: usize = 64;
 const SYSCALL_EXIT: usize = 93;
+const SYSCALL_NANOSLEEP: usize = 101;
+const SYSCALL_CLOCK_NANOSLEEP: usize = 115;
 const SYSCALL_YIELD: usize = 124;
+const SYSCALL_KILL: usize = 129;
+const SYSCALL_SIGACTION: usize = 134;
+const SYSCALL_SIGPROCMASK: usize = 135;
+const SYSCALL_SIGRETURN: usize = 139;
+const SYSCALL_SET_PRIORITY: usize = 140;
+const SYSCALL_TIMES: usize = 153;
+const SYSCALL_SETPGID: usize = 154;
+const SYSCALL_GETPGID: usize = 155;
+const SYSCALL_SETSID: usize = 157;
+const SYSCALL_GETRLIMIT: usize = 163;
+const SYSCALL_SETRLIMIT: usize = 164;
+const SYSCALL_GETRUSAGE: usize = 165;
 const SYSCALL_GET_TIME: usize = 169;
 const SYSCALL_GETPID: usize = 172;
+const SYSCALL_BRK: usize = 214;
+const SYSCALL_MUNMAP: usize = 215;
 const SYSCALL_FORK: usize = 220;
-const SYSCALL_EXEC: usize = 221;
+const SYSCALL_EXECVE: usize = 221;
+const SYSCALL_MMAP: usize = 222;
 const SYSCALL_WAITPID: usize = 260;
+const SYSCALL_SPAWN: usize = 400;
+const SYSCALL_THREAD_CREATE: usize = 1000;
+const SYSCALL_GETTID: usize = 1001;
+const SYSCALL_WAITTID: usize = 1002;
+const SYSCALL_MUTEX_CREATE: usize = 1010;
+const SYSCALL_MUTEX_LOCK: usize = 1011;
+const SYSCALL_MUTEX_UNLOCK: usize = 1012;
+const SYSCALL_SEMAPHORE_CREATE: usize = 1020;
+const SYSCALL_SEMAPHORE_UP: usize = 1021;
+const SYSCALL_SEMAPHORE_DOWN: usize = 1022;
+const SYSCALL_CONDVAR_CREATE: usize = 1030;
+const SYSCALL_CONDVAR_SIGNAL: usize = 1031;
+const SYSCALL_CONDVAR_WAIT: usize = 1032;
+const SYSCALL_SBRK: usize = 1040;
+const SYSCALL_TASK_LIST: usize = 1050;
+const SYSCALL_PERSONALITY: usize = 1060;
+const SYSCALL_SYSCALL_STATS: usize = 1070;
 
 mod fs;
 mod process;
+mod thread;
+mod sync;
+mod memory;
+mod linux;
+mod table;
 
 use fs::*;
 use process::*;
+use thread::*;
+use sync::*;
+use memory::*;
+use table::*;
+use linux::LINUX_TABLE;
+use crate::error::SysError;
+use crate::task::{SignalAction, Personality, current_process};
+use crate::timer::TimeSpec;
+use core::sync::atomic::AtomicUsize;
 
-pub fn syscall(syscall_id: usize, args: [usize; 3]) -> isize {
-    match syscall_id {
-        SYSCALL_DUP=> sys_dup(args[0]),
-        SYSCALL_OPEN => sys_open(args[0] as *const u8, args[1] as u32),
-        SYSCALL_CLOSE => sys_close(args[0]),
-        SYSCALL_PIPE => sys_pipe(args[0] as *mut usize),
-        SYSCALL_READ => sys_read(args[0], args[1] as *const u8, args[2]),
-        SYSCALL_WRITE => sys_write(args[0], args[1] as *const u8, args[2]),
-        SYSCALL_EXIT => sys_exit(args[0] as i32),
-        SYSCALL_YIELD => sys_yield(),
-        SYSCALL_GET_TIME => sys_get_time(),
-        SYSCALL_GETPID => sys_getpid(),
-        SYSCALL_FORK => sys_fork(),
-        SYSCALL_EXEC => sys_exec(args[0] as *const u8, args[1] as *const usize),
-        SYSCALL_WAITPID => sys_waitpid(args[0] as isize, args[1] as *mut i32),
-        _ => panic!("Unsupported syscall_id: {}", syscall_id),
+/// Serve the system call `syscall_id` from the table of the personality of
+/// the current process, with a0..a5 as arguments. Unsupported ones fail with
+/// ENOSYS quietly, libc probes for many of them.
+pub fn syscall(syscall_id: usize, args: [usize; 6]) -> isize {
+    let table = match current_process().personality() {
+        Personality::Native => &NATIVE_TABLE,
+        Personality::Linux => &LINUX_TABLE,
+    };
+    let ret = table.dispatch(syscall_id, args).unwrap_or(Err(SysError::ENOSYS));
+    match ret {
+        Ok(value) => value as isize,
+        Err(err) => err.as_ret(),
     }
 }
 
+/// Sorted by id, so that the entry of a system call can be searched for.
+const NATIVE_SYSCALLS: &[SyscallEntry] = &[
+    SyscallEntry { id: SYSCALL_DUP, name: "dup", handler: |args| sys_dup(args[0]) },
+    SyscallEntry { id: SYSCALL_IOCTL, name: "ioctl", handler: |args| sys_ioctl(args[0], args[1], args[2]) },
+    SyscallEntry {
+        id: SYSCALL_OPEN,
+        name: "open",
+        handler: |args| sys_open(args[0] as *const u8, args[1] as u32),
+    },
+    SyscallEntry { id: SYSCALL_CLOSE, name: "close", handler: |args| sys_close(args[0]) },
+    SyscallEntry { id: SYSCALL_PIPE, name: "pipe", handler: |args| sys_pipe(args[0] as *mut usize) },
+    SyscallEntry {
+        id: SYSCALL_READ,
+        name: "read",
+        handler: |args| sys_read(args[0], args[1] as *const u8, args[2]),
+    },
+    SyscallEntry {
+        id: SYSCALL_WRITE,
+        name: "write",
+        handler: |args| sys_write(args[0], args[1] as *const u8, args[2]),
+    },
+    SyscallEntry { id: SYSCALL_EXIT, name: "exit", handler: |args| sys_exit(args[0] as i32) },
+    SyscallEntry {
+        id: SYSCALL_NANOSLEEP,
+        name: "nanosleep",
+        handler: |args| sys_nanosleep(args[0] as *const TimeSpec, args[1] as *mut TimeSpec),
+    },
+    SyscallEntry {
+        id: SYSCALL_CLOCK_NANOSLEEP,
+        name: "clock_nanosleep",
+        handler: |args| sys_clock_nanosleep(args[0], args[1], args[2] as *const TimeSpec, args[3] as *mut TimeSpec),
+    },
+    SyscallEntry { id: SYSCALL_YIELD, name: "yield", handler: |_| sys_yield() },
+    SyscallEntry { id: SYSCALL_KILL, name: "kill", handler: |args| sys_kill(args[0] as isize, args[1]) },
+    SyscallEntry {
+        id: SYSCALL_SIGACTION,
+        name: "sigaction",
+        handler: |args| sys_sigaction(args[0], args[1] as *const SignalAction, args[2] as *mut SignalAction),
+    },
+    SyscallEntry {
+        id: SYSCALL_SIGPROCMASK,
+        name: "sigprocmask",
+        handler: |args| sys_sigprocmask(args[0], args[1] as *const u32, args[2] as *mut u32),
+    },
+    SyscallEntry { id: SYSCALL_SIGRETURN, name: "sigreturn", handler: |_| sys_sigreturn() },
+    SyscallEntry {
+        id: SYSCALL_SET_PRIORITY,
+        name: "set_priority",
+        handler: |args| sys_set_priority(args[0] as isize),
+    },
+    SyscallEntry { id: SYSCALL_TIMES, name: "times", handler: |args| sys_times(args[0] as *mut Tms) },
+    SyscallEntry { id: SYSCALL_SETPGID, name: "setpgid", handler: |args| sys_setpgid(args[0], args[1]) },
+    SyscallEntry { id: SYSCALL_GETPGID, name: "getpgid", handler: |args| sys_getpgid(args[0]) },
+    SyscallEntry { id: SYSCALL_SETSID, name: "setsid", handler: |_| sys_setsid() },
+    SyscallEntry {
+        id: SYSCALL_GETRLIMIT,
+        name: "getrlimit",
+        handler: |args| sys_getrlimit(args[0], args[1] as *mut RLimit),
+    },
+    SyscallEntry {
+        id: SYSCALL_SETRLIMIT,
+        name: "setrlimit",
+        handler: |args| sys_setrlimit(args[0], args[1] as *const RLimit),
+    },
+    SyscallEntry {
+        id: SYSCALL_GETRUSAGE,
+        name: "getrusage",
+        handler: |args| sys_getrusage(args[0] as isize, args[1] as *mut RUsage),
+    },
+    SyscallEntry { id: SYSCALL_GET_TIME, name: "get_time", handler: |_| sys_get_time() },
+    SyscallEntry { id: SYSCALL_GETPID, name: "getpid", handler: |_| sys_getpid() },
+    SyscallEntry { id: SYSCALL_BRK, name: "brk", handler: |args| sys_brk(args[0]) },
+    SyscallEntry { id: SYSCALL_MUNMAP, name: "munmap", handler: |args| sys_munmap(args[0], args[1]) },
+    SyscallEntry { id: SYSCALL_FORK, name: "fork", handler: |_| sys_fork() },
+    SyscallEntry {
+        id: SYSCALL_EXECVE,
+        name: "execve",
+        handler: |args| sys_execve(args[0] as *const u8, args[1] as *const usize, args[2] as *const usize),
+    },
+    SyscallEntry { id: SYSCALL_MMAP, name: "mmap", handler: |args| sys_mmap(args[0], args[1], args[2]) },
+    SyscallEntry {
+        id: SYSCALL_WAITPID,
+        name: "waitpid",
+        handler: |args| sys_waitpid(args[0] as isize, args[1] as *mut i32, args[2]),
+    },
+    SyscallEntry {
+        id: SYSCALL_SPAWN,
+        name: "spawn",
+        handler: |args| sys_spawn(
+            args[0] as *const u8,
+            args[1] as *const usize,
+            args[2] as *const SpawnAction,
+            args[3] as *const usize,
+        ),
+    },
+    SyscallEntry {
+        id: SYSCALL_THREAD_CREATE,
+        name: "thread_create",
+        handler: |args| sys_thread_create(args[0], args[1]),
+    },
+    SyscallEntry { id: SYSCALL_GETTID, name: "gettid", handler: |_| sys_gettid() },
+    SyscallEntry {
+        id: SYSCALL_WAITTID,
+        name: "waittid",
+        handler: |args| sys_waittid(args[0], args[1] as *mut i32),
+    },
+    SyscallEntry { id: SYSCALL_MUTEX_CREATE, name: "mutex_create", handler: |args| sys_mutex_create(args[0]) },
+    SyscallEntry { id: SYSCALL_MUTEX_LOCK, name: "mutex_lock", handler: |args| sys_mutex_lock(args[0]) },
+    SyscallEntry { id: SYSCALL_MUTEX_UNLOCK, name: "mutex_unlock", handler: |args| sys_mutex_unlock(args[0]) },
+    SyscallEntry {
+        id: SYSCALL_SEMAPHORE_CREATE,
+        name: "semaphore_create",
+        handler: |args| sys_semaphore_create(args[0]),
+    },
+    SyscallEntry { id: SYSCALL_SEMAPHORE_UP, name: "semaphore_up", handler: |args| sys_semaphore_up(args[0]) },
+    SyscallEntry {
+        id: SYSCALL_SEMAPHORE_DOWN,
+        name: "semaphore_down",
+        handler: |args| sys_semaphore_down(args[0]),
+    },
+    SyscallEntry { id: SYSCALL_CONDVAR_CREATE, name: "condvar_create", handler: |_| sys_condvar_create() },
+    SyscallEntry {
+        id: SYSCALL_CONDVAR_SIGNAL,
+        name: "condvar_signal",
+        handler: |args| sys_condvar_signal(args[0]),
+    },
+    SyscallEntry {
+        id: SYSCALL_CONDVAR_WAIT,
+        name: "condvar_wait",
+        handler: |args| sys_condvar_wait(args[0], args[1]),
+    },
+    SyscallEntry { id: SYSCALL_SBRK, name: "sbrk", handler: |args| sys_sbrk(args[0] as isize) },
+    SyscallEntry {
+        id: SYSCALL_TASK_LIST,
+        name: "task_list",
+        handler: |args| sys_task_list(args[0] as *mut TaskInfo, args[1]),
+    },
+    SyscallEntry { id: SYSCALL_PERSONALITY, name: "personality", handler: |args| sys_personality(args[0]) },
+    SyscallEntry {
+        id: SYSCALL_SYSCALL_STATS,
+        name: "syscall_stats",
+        handler: |args| sys_syscall_stats(args[0] as *mut SyscallStat, args[1]),
+    },
+];
+
+const _: [(); 0 - !is_sorted(NATIVE_SYSCALLS) as usize] = [];
+
+static NATIVE_COUNTS: [AtomicUsize; NATIVE_SYSCALLS.len()] = [NO_CALLS; NATIVE_SYSCALLS.len()];
+
+static NATIVE_TABLE: SyscallTable = SyscallTable {
+    entries: NATIVE_SYSCALLS,
+    counts: &NATIVE_COUNTS,
+};
@@ -2,114 +2,816 @@ use crate::task::{
     suspend_current_and_run_next,
     exit_current_and_run_next,
     current_task,
-    current_user_token,
-    add_task,
+    current_process,
+    current_trap_cx,
+    pid2process,
+    pgid2processes,
+    send_signal,
+    all_processes,
+    INITPROC,
+    SignalFlags,
+    SignalAction,
+    SignalFrame,
+    SIG_BLOCK,
+    SIG_UNBLOCK,
+    SIG_SETMASK,
+    block_current_and_run_next,
+    account_current_time,
+    registered_tasks,
+    TaskStatus,
+    Personality,
+    ProcessControlBlock,
+    ProcessControlBlockInner,
 };
-use crate::timer::get_time_ms;
+use crate::timer::{get_time, get_time_ms, cycles_to_ticks, TimeSpec, TimeVal, add_timer, remove_timer};
 use crate::mm::{
     translated_str,
     translated_refmut,
     translated_ref,
+    copy_to_user,
+    copy_from_user,
+    Executable,
+    interpreter_path,
 };
 use crate::fs::{
     open_file,
     OpenFlags,
+    File,
 };
+use crate::config::{USER_STACK_LIMIT_MAX, MAX_FD_NUM, MAX_PRIORITY};
+use crate::error::{SysError, SysResult};
 use alloc::sync::Arc;
 use alloc::vec::Vec;
 use alloc::string::String;
 
+bitflags! {
+    pub struct WaitOptions: u32 {
+        const WNOHANG = 1;
+        /// Also report children which have stopped.
+        const WUNTRACED = 2;
+        /// Also report stopped children which have been continued.
+        const WCONTINUED = 8;
+    }
+}
+
 pub fn sys_exit(exit_code: i32) -> ! {
     exit_current_and_run_next(exit_code);
     panic!("Unreachable in sys_exit!");
 }
 
-pub fn sys_yield() -> isize {
+pub fn sys_yield() -> SysResult {
     suspend_current_and_run_next();
-    0
+    Ok(0)
+}
+
+pub fn sys_get_time() -> SysResult {
+    Ok(get_time_ms())
+}
+
+const CLOCK_REALTIME: usize = 0;
+const CLOCK_MONOTONIC: usize = 1;
+const TIMER_ABSTIME: usize = 1;
+
+/// Sleep on the kernel timer queue until `get_time() >= expire`.
+/// On EINTR the time left is written to `rem` if it is not null.
+fn sleep_until(expire: usize, rem: *mut TimeSpec) -> SysResult {
+    let task = current_task().unwrap();
+    let process = current_process();
+    loop {
+        let current = get_time();
+        if current >= expire {
+            return Ok(0);
+        }
+        let mut inner = process.acquire_inner_lock();
+        if inner.has_pending_signal() {
+            if !rem.is_null() {
+                // the page must not be swapped out before it is written
+                inner.memory_set.prepare_user_access(rem as usize, core::mem::size_of::<TimeSpec>(), true)?;
+                copy_to_user(inner.get_user_token(), rem, &TimeSpec::from_cycles(expire - current))?;
+            }
+            return Err(SysError::EINTR);
+        }
+        drop(inner);
+        add_timer(expire, task.clone());
+        block_current_and_run_next();
+        // we may be woken up by a signal before the deadline
+        remove_timer(&task);
+    }
+}
+
+fn copy_timespec_from_user(src: *const TimeSpec, time: &mut TimeSpec) -> SysResult<()> {
+    let process = current_process();
+    let mut inner = process.acquire_inner_lock();
+    inner.memory_set.prepare_user_access(src as usize, core::mem::size_of::<TimeSpec>(), false)?;
+    copy_from_user(inner.get_user_token(), src, time)
+}
+
+pub fn sys_nanosleep(req: *const TimeSpec, rem: *mut TimeSpec) -> SysResult {
+    let mut duration = TimeSpec::default();
+    copy_timespec_from_user(req, &mut duration)?;
+    if !duration.is_valid() {
+        return Err(SysError::EINVAL);
+    }
+    sleep_until(get_time().saturating_add(duration.to_cycles()), rem)
+}
+
+/// Both clocks count from boot. On EINTR the time left is written to `rem`
+/// unless it is null or `req` is a deadline.
+pub fn sys_clock_nanosleep(clock_id: usize, flags: usize, req: *const TimeSpec, rem: *mut TimeSpec) -> SysResult {
+    if clock_id != CLOCK_REALTIME && clock_id != CLOCK_MONOTONIC {
+        return Err(SysError::EINVAL);
+    }
+    let mut time = TimeSpec::default();
+    copy_timespec_from_user(req, &mut time)?;
+    if !time.is_valid() {
+        return Err(SysError::EINVAL);
+    }
+    if flags & TIMER_ABSTIME != 0 {
+        sleep_until(time.to_cycles(), core::ptr::null_mut())
+    } else {
+        sleep_until(get_time().saturating_add(time.to_cycles()), rem)
+    }
+}
+
+/// Set the priority of the current thread, which must be at least 2 and
+/// at most `MAX_PRIORITY`. New threads and forked children inherit it.
+/// Returns the new priority.
+pub fn sys_set_priority(prio: isize) -> SysResult {
+    if prio < 2 || prio as usize > MAX_PRIORITY {
+        return Err(SysError::EINVAL);
+    }
+    current_task().unwrap().acquire_inner_lock().priority = prio as usize;
+    Ok(prio as usize)
+}
+
+pub fn sys_getpid() -> SysResult {
+    Ok(current_process().getpid())
+}
+
+pub const PERSONALITY_NATIVE: usize = 0;
+pub const PERSONALITY_LINUX: usize = 1;
+
+/// Switch the process to the system calls of this kernel or to those of
+/// Linux, starting with the next call. Children and new images keep it.
+/// Returns the previous personality.
+pub fn sys_personality(persona: usize) -> SysResult {
+    let personality = match persona {
+        PERSONALITY_NATIVE => Personality::Native,
+        PERSONALITY_LINUX => Personality::Linux,
+        _ => return Err(SysError::EINVAL),
+    };
+    let prev = match current_process().set_personality(personality) {
+        Personality::Native => PERSONALITY_NATIVE,
+        Personality::Linux => PERSONALITY_LINUX,
+    };
+    Ok(prev)
+}
+
+/// Only the stack size can be limited so far.
+const RLIMIT_STACK: usize = 3;
+
+/// Same layout as `struct rlimit` in Linux.
+#[repr(C)]
+#[derive(Copy, Clone, Default)]
+pub struct RLimit {
+    pub rlim_cur: usize,
+    pub rlim_max: usize,
+}
+
+pub fn sys_getrlimit(resource: usize, rlim: *mut RLimit) -> SysResult {
+    if resource != RLIMIT_STACK {
+        return Err(SysError::EINVAL);
+    }
+    let process = current_process();
+    let mut inner = process.acquire_inner_lock();
+    let limit = RLimit {
+        rlim_cur: inner.memory_set.stack_limit(),
+        rlim_max: USER_STACK_LIMIT_MAX,
+    };
+    inner.memory_set.prepare_user_access(rlim as usize, core::mem::size_of::<RLimit>(), true)?;
+    copy_to_user(inner.get_user_token(), rlim, &limit)?;
+    Ok(0)
+}
+
+/// The hard limit of the stack is the room each thread has for its stack,
+/// it cannot be changed.
+pub fn sys_setrlimit(resource: usize, rlim: *const RLimit) -> SysResult {
+    if resource != RLIMIT_STACK {
+        return Err(SysError::EINVAL);
+    }
+    let process = current_process();
+    let mut inner = process.acquire_inner_lock();
+    let mut limit = RLimit::default();
+    inner.memory_set.prepare_user_access(rlim as usize, core::mem::size_of::<RLimit>(), false)?;
+    copy_from_user(inner.get_user_token(), rlim, &mut limit)?;
+    if limit.rlim_cur > limit.rlim_max {
+        return Err(SysError::EINVAL);
+    }
+    if limit.rlim_max != USER_STACK_LIMIT_MAX {
+        return Err(SysError::EPERM);
+    }
+    inner.memory_set.set_stack_limit(limit.rlim_cur);
+    Ok(0)
 }
 
-pub fn sys_get_time() -> isize {
-    get_time_ms() as isize
+/// Same layout as `struct tms` in Linux, in clock ticks.
+#[repr(C)]
+#[derive(Copy, Clone, Default)]
+pub struct Tms {
+    pub tms_utime: usize,
+    pub tms_stime: usize,
+    pub tms_cutime: usize,
+    pub tms_cstime: usize,
 }
 
-pub fn sys_getpid() -> isize {
-    current_task().unwrap().pid.0 as isize
+/// Fill `tms` with the CPU time of the caller and of its children which have
+/// been waited for, returns the clock ticks since boot.
+pub fn sys_times(tms: *mut Tms) -> SysResult {
+    account_current_time(false);
+    let process = current_process();
+    let mut inner = process.acquire_inner_lock();
+    if !tms.is_null() {
+        let times = inner.cpu_times();
+        let children = inner.children_times;
+        let value = Tms {
+            tms_utime: cycles_to_ticks(times.user),
+            tms_stime: cycles_to_ticks(times.kernel),
+            tms_cutime: cycles_to_ticks(children.user),
+            tms_cstime: cycles_to_ticks(children.kernel),
+        };
+        inner.memory_set.prepare_user_access(tms as usize, core::mem::size_of::<Tms>(), true)?;
+        copy_to_user(inner.get_user_token(), tms, &value)?;
+    }
+    Ok(cycles_to_ticks(get_time()))
+}
+
+const RUSAGE_SELF: isize = 0;
+const RUSAGE_CHILDREN: isize = -1;
+const RUSAGE_THREAD: isize = 1;
+
+/// Same layout as `struct rusage` in Linux.
+#[repr(C)]
+#[derive(Copy, Clone, Default)]
+pub struct RUsage {
+    pub ru_utime: TimeVal,
+    pub ru_stime: TimeVal,
+    /// The other fields, which are not kept track of and left as 0.
+    pub ru_others: [isize; 14],
 }
 
-pub fn sys_fork() -> isize {
-    let current_task = current_task().unwrap();
-    let new_task = current_task.fork();
-    let new_pid = new_task.pid.0;
-    // modify trap context of new_task, because it returns immediately after switching
-    let trap_cx = new_task.acquire_inner_lock().get_trap_cx();
-    // we do not have to move to next instruction since we have done it before
-    // for child process, fork returns 0
-    trap_cx.x[10] = 0;
-    // add new task to scheduler
-    add_task(new_task);
-    new_pid as isize
+/// Resource usage of the caller, its current thread, or its children which
+/// have been waited for.
+pub fn sys_getrusage(who: isize, usage: *mut RUsage) -> SysResult {
+    account_current_time(false);
+    let process = current_process();
+    let mut inner = process.acquire_inner_lock();
+    let times = match who {
+        RUSAGE_SELF => inner.cpu_times(),
+        RUSAGE_CHILDREN => inner.children_times,
+        RUSAGE_THREAD => current_task().unwrap().acquire_inner_lock().times,
+        _ => return Err(SysError::EINVAL),
+    };
+    let value = RUsage {
+        ru_utime: TimeVal::from_cycles(times.user),
+        ru_stime: TimeVal::from_cycles(times.kernel),
+        ..RUsage::default()
+    };
+    inner.memory_set.prepare_user_access(usage as usize, core::mem::size_of::<RUsage>(), true)?;
+    copy_to_user(inner.get_user_token(), usage, &value)?;
+    Ok(0)
 }
 
-pub fn sys_exec(path: *const u8, mut args: *const usize) -> isize {
-    let token = current_user_token();
-    let path = translated_str(token, path);
-    let mut args_vec: Vec<String> = Vec::new();
+pub fn sys_fork() -> SysResult {
+    let current_process = current_process();
+    let new_process = current_process.fork()?;
+    Ok(new_process.getpid())
+}
+
+/// Read a null-terminated array of strings like argv, a null array is empty.
+fn translated_str_array(mut array: *const usize) -> SysResult<Vec<String>> {
+    let process = current_process();
+    let mut inner = process.acquire_inner_lock();
+    let token = inner.get_user_token();
+    let mut strings: Vec<String> = Vec::new();
+    if array.is_null() {
+        return Ok(strings);
+    }
     loop {
-        let arg_str_ptr = *translated_ref(token, args);
-        if arg_str_ptr == 0 {
+        inner.memory_set.prepare_user_access(array as usize, core::mem::size_of::<usize>(), false)?;
+        let str_ptr = *translated_ref(token, array)?;
+        if str_ptr == 0 {
             break;
         }
-        args_vec.push(translated_str(token, arg_str_ptr as *const u8));
-        unsafe { args = args.add(1); }
+        inner.memory_set.prepare_user_str(str_ptr)?;
+        strings.push(translated_str(token, str_ptr as *const u8)?);
+        unsafe { array = array.add(1); }
     }
-    if let Some(app_inode) = open_file(path.as_str(), OpenFlags::RDONLY) {
-        let all_data = app_inode.read_all();
-        let task = current_task().unwrap();
-        let argc = args_vec.len();
-        task.exec(all_data.as_slice(), args_vec);
-        // return argc because cx.x[10] will be covered with it later
-        argc as isize
-    } else {
-        -1
+    Ok(strings)
+}
+
+fn translated_path(path: *const u8) -> SysResult<String> {
+    let process = current_process();
+    let mut inner = process.acquire_inner_lock();
+    inner.memory_set.prepare_user_str(path as usize)?;
+    translated_str(inner.get_user_token(), path)
+}
+
+/// Scripts may name other scripts as their interpreter, up to this depth.
+const MAX_INTERPRETER_DEPTH: usize = 4;
+/// The `#!` line is cut short after this many bytes.
+const MAX_SHEBANG_LEN: usize = 128;
+
+/// Read the executable at `path`, along with the dynamic loader named by
+/// its `PT_INTERP`. A script starting with `#!interpreter [arg]` is run by
+/// the interpreter instead, its argv being the interpreter, the optional
+/// argument, the path of the script and the rest of `args`.
+fn load_executable(mut path: String, mut args: Vec<String>) -> SysResult<(Executable, Vec<String>)> {
+    for _ in 0..=MAX_INTERPRETER_DEPTH {
+        let inode = open_file(path.as_str(), OpenFlags::RDONLY).ok_or(SysError::ENOENT)?;
+        let data = inode.read_all();
+        if !data.starts_with(b"#!") {
+            let interp_data = match interpreter_path(&data)? {
+                Some(interp) => {
+                    let inode = open_file(interp.as_str(), OpenFlags::RDONLY).ok_or(SysError::ENOENT)?;
+                    Some(Arc::new(inode.read_all()))
+                }
+                None => None,
+            };
+            let executable = Executable { elf_data: Arc::new(data), interp_data };
+            return Ok((executable, args));
+        }
+        let line_end = data
+            .iter()
+            .position(|c| *c == b'\n')
+            .unwrap_or(data.len())
+            .min(MAX_SHEBANG_LEN);
+        let line = core::str::from_utf8(&data[2..line_end])
+            .map_err(|_| SysError::ENOEXEC)?
+            .trim();
+        // everything after the interpreter is a single argument
+        let (interpreter, arg) = match line.find(|c: char| c == ' ' || c == '\t') {
+            Some(idx) => (&line[..idx], Some(line[idx..].trim())),
+            None => (line, None),
+        };
+        if interpreter.is_empty() {
+            return Err(SysError::ENOEXEC);
+        }
+        let mut interpreter_args = Vec::new();
+        interpreter_args.push(String::from(interpreter));
+        if let Some(arg) = arg {
+            interpreter_args.push(String::from(arg));
+        }
+        interpreter_args.push(path);
+        interpreter_args.extend(args.into_iter().skip(1));
+        args = interpreter_args;
+        path = String::from(interpreter);
     }
+    Err(SysError::ELOOP)
 }
 
-/// If there is not a child process whose pid is same as given, return -1.
-/// Else if there is a child process but it is still running, return -2.
-pub fn sys_waitpid(pid: isize, exit_code_ptr: *mut i32) -> isize {
-    let task = current_task().unwrap();
-    // find a child process
+/// Run the executable at `path` with the given argv and environment, each a
+/// null-terminated array of strings. They are found on the new user stack
+/// along with the auxiliary vector, argc and argv are in a0 and a1 as well.
+/// A file which is not a valid executable fails with `ENOEXEC` and the
+/// caller goes on with its old image. Scripts are run by their interpreter,
+/// dynamically linked programs start in their dynamic loader.
+pub fn sys_execve(path: *const u8, args: *const usize, envs: *const usize) -> SysResult {
+    let path = translated_path(path)?;
+    let args_vec = translated_str_array(args)?;
+    let envs_vec = translated_str_array(envs)?;
+    let process = current_process();
+    let (executable, args_vec) = load_executable(path.clone(), args_vec)?;
+    let argc = args_vec.len();
+    process.exec(path.as_str(), executable, args_vec, envs_vec)?;
+    // return argc because cx.x[10] will be covered with it later
+    Ok(argc)
+}
 
-    // ---- hold current PCB lock
-    let mut inner = task.acquire_inner_lock();
-    if inner.children
-        .iter()
-        .find(|p| {pid == -1 || pid as usize == p.getpid()})
-        .is_none() {
-        return -1;
-        // ---- release current PCB lock
+const TASK_NAME_LEN: usize = 32;
+
+const TASK_READY: usize = 0;
+const TASK_RUNNING: usize = 1;
+const TASK_BLOCKED: usize = 2;
+const TASK_STOPPED: usize = 3;
+const TASK_ZOMBIE: usize = 4;
+
+/// What `sys_task_list` tells about a thread.
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct TaskInfo {
+    pub pid: usize,
+    pub tid: usize,
+    /// `usize::MAX` for initproc, which has no parent.
+    pub ppid: usize,
+    pub pgid: usize,
+    pub state: usize,
+    /// Bytes mapped in the address space of the process, and backed by frames.
+    pub vsz: usize,
+    pub rss: usize,
+    /// CPU time of the thread.
+    pub utime: TimeVal,
+    pub stime: TimeVal,
+    /// Path of the program, cut short and ending with '\0'.
+    pub name: [u8; TASK_NAME_LEN],
+}
+
+/// Fill `infos` with up to `len` live threads, sorted by pid and tid.
+/// Returns how many threads there are, which may be more than `len`.
+pub fn sys_task_list(infos: *mut TaskInfo, len: usize) -> SysResult {
+    account_current_time(false);
+    let mut list = Vec::new();
+    for task in registered_tasks() {
+        // the process may be gone while the thread is being released
+        let process = match task.process.upgrade() {
+            Some(process) => process,
+            None => continue,
+        };
+        let inner = process.acquire_inner_lock();
+        let task_inner = task.acquire_inner_lock();
+        let state = match task_inner.task_status {
+            TaskStatus::Ready => TASK_READY,
+            TaskStatus::Running => TASK_RUNNING,
+            TaskStatus::Blocked if inner.stopped => TASK_STOPPED,
+            TaskStatus::Blocked => TASK_BLOCKED,
+            TaskStatus::Zombie => TASK_ZOMBIE,
+        };
+        let mut name = [0u8; TASK_NAME_LEN];
+        let name_len = inner.name.len().min(TASK_NAME_LEN - 1);
+        name[..name_len].copy_from_slice(&inner.name.as_bytes()[..name_len]);
+        list.push(TaskInfo {
+            pid: process.getpid(),
+            tid: task_inner.res.tid,
+            ppid: inner.parent
+                .as_ref()
+                .and_then(|parent| parent.upgrade())
+                .map_or(usize::MAX, |parent| parent.getpid()),
+            pgid: inner.pgid,
+            state,
+            vsz: inner.memory_set.virtual_size(),
+            rss: inner.memory_set.resident_size(),
+            utime: TimeVal::from_cycles(task_inner.times.user),
+            stime: TimeVal::from_cycles(task_inner.times.kernel),
+            name,
+        });
     }
-    let pair = inner.children
-        .iter()
-        .enumerate()
-        .find(|(_, p)| {
+    list.sort_by_key(|info| (info.pid, info.tid));
+    let process = current_process();
+    let mut inner = process.acquire_inner_lock();
+    let count = len.min(list.len());
+    if count > 0 {
+        inner.memory_set.prepare_user_access(infos as usize, count * core::mem::size_of::<TaskInfo>(), true)?;
+        let token = inner.get_user_token();
+        for (i, info) in list[..count].iter().enumerate() {
+            copy_to_user(token, unsafe { infos.add(i) }, info)?;
+        }
+    }
+    Ok(list.len())
+}
+
+const SPAWN_END: usize = 0;
+const SPAWN_CLOSE: usize = 1;
+const SPAWN_DUP2: usize = 2;
+const SPAWN_OPEN: usize = 3;
+const SPAWN_SETPGID: usize = 4;
+
+/// One change to the fd table a spawned process inherits, the list ends with `SPAWN_END`.
+/// `CLOSE` closes `fd`, `DUP2` makes `newfd` refer to the file of `fd`,
+/// `OPEN` opens `path` with `flags` as `newfd`. `SETPGID` is not about files
+/// but puts the child in the process group `fd`, 0 for a new one, as setpgid would.
+#[repr(C)]
+#[derive(Copy, Clone, Default)]
+pub struct SpawnAction {
+    pub kind: usize,
+    pub fd: usize,
+    pub newfd: usize,
+    pub path: usize,
+    pub flags: usize,
+}
+
+/// Create a child process running the executable at `path`, without copying
+/// the address space of the caller. It inherits the fd table, with
+/// `file_actions` applied in order; they only change the table of the child.
+/// `args` and `envs` are null-terminated as for execve, a null `envs` meaning no environment.
+pub fn sys_spawn(
+    path: *const u8,
+    args: *const usize,
+    mut file_actions: *const SpawnAction,
+    envs: *const usize,
+) -> SysResult {
+    let path = translated_path(path)?;
+    let args_vec = translated_str_array(args)?;
+    let envs_vec = translated_str_array(envs)?;
+    let process = current_process();
+    let mut inner = process.acquire_inner_lock();
+    let token = inner.get_user_token();
+    let mut fd_table = inner.fd_table.clone();
+    let mut pgid = None;
+    loop {
+        let mut action = SpawnAction::default();
+        inner.memory_set
+            .prepare_user_access(file_actions as usize, core::mem::size_of::<SpawnAction>(), false)?;
+        copy_from_user(token, file_actions, &mut action)?;
+        let file: Option<Arc<dyn File + Send + Sync>> = match action.kind {
+            SPAWN_END => break,
+            SPAWN_SETPGID => {
+                pgid = Some(action.fd);
+                None
+            }
+            SPAWN_CLOSE => {
+                fd_table.get_mut(action.fd).and_then(|file| file.take()).ok_or(SysError::EBADF)?;
+                None
+            }
+            SPAWN_DUP2 => Some(
+                fd_table.get(action.fd).and_then(|file| file.clone()).ok_or(SysError::EBADF)?
+            ),
+            SPAWN_OPEN => {
+                inner.memory_set.prepare_user_str(action.path)?;
+                let path = translated_str(token, action.path as *const u8)?;
+                let flags = OpenFlags::from_bits(action.flags as u32).ok_or(SysError::EINVAL)?;
+                Some(open_file(path.as_str(), flags).ok_or(SysError::ENOENT)?)
+            }
+            _ => return Err(SysError::EINVAL),
+        };
+        if file.is_some() {
+            if action.newfd >= MAX_FD_NUM {
+                return Err(SysError::EBADF);
+            }
+            if action.newfd >= fd_table.len() {
+                fd_table.resize(action.newfd + 1, None);
+            }
+            fd_table[action.newfd] = file;
+        }
+        unsafe { file_actions = file_actions.add(1); }
+    }
+    let sid = inner.sid;
+    drop(inner);
+    if let Some(pgid) = pgid.filter(|pgid| *pgid != 0) {
+        if !pgid2processes(pgid)
+            .iter()
+            .any(|member| member.acquire_inner_lock().sid == sid) {
+            return Err(SysError::EPERM);
+        }
+    }
+    let (executable, args_vec) = load_executable(path.clone(), args_vec)?;
+    let new_process = process.spawn(path.as_str(), executable, args_vec, envs_vec, fd_table, pgid)?;
+    Ok(new_process.getpid())
+}
+
+/// Wait for the child `pid`, any child if it is -1, any child in the process
+/// group of the caller if it is 0, or in the process group -`pid` if lower.
+/// If there is not a child process which is asked for, return ECHILD.
+/// Else if there is a child process but it is still running, block until it
+/// exits, or return 0 at once with WNOHANG. A signal interrupts the wait with EINTR.
+/// With WUNTRACED or WCONTINUED, a child stopping or continuing ends the wait as well.
+pub fn sys_waitpid(pid: isize, exit_code_ptr: *mut i32, options: usize) -> SysResult {
+    let options = WaitOptions::from_bits(options as u32).ok_or(SysError::EINVAL)?;
+    let process = current_process();
+    loop {
+        let ticket = process.child_exit_queue.ticket();
+        // find a child process
+
+        // ---- hold current PCB lock
+        let mut inner = process.acquire_inner_lock();
+        let pgid = match pid {
+            0 => Some(inner.pgid),
+            pid if pid < -1 => Some((pid as usize).wrapping_neg()),
+            _ => None,
+        };
+        // the child PCB has to be locked for its process group
+        let wanted = |child: &ProcessControlBlock, child_inner: &ProcessControlBlockInner| match pgid {
+            Some(pgid) => child_inner.pgid == pgid,
+            None => pid == -1 || pid as usize == child.getpid(),
+        };
+        if inner.children
+            .iter()
+            .find(|p| {
+                // ++++ temporarily hold child PCB lock
+                wanted(p, &p.acquire_inner_lock())
+                // ++++ release child PCB lock
+            })
+            .is_none() {
+            return Err(SysError::ECHILD);
+            // ---- release current PCB lock
+        }
+        // check the user pointer before a zombie child is reaped
+        let exit_code_ref = if exit_code_ptr.is_null() {
+            None
+        } else {
+            inner.memory_set.prepare_user_access(exit_code_ptr as usize, core::mem::size_of::<i32>(), true)?;
+            Some(translated_refmut(inner.memory_set.token(), exit_code_ptr)?)
+        };
+        let pair = inner.children
+            .iter()
+            .enumerate()
+            .find(|(_, p)| {
+                // ++++ temporarily hold child PCB lock
+                let child_inner = p.acquire_inner_lock();
+                child_inner.is_zombie && wanted(p, &child_inner)
+                // ++++ release child PCB lock
+            });
+        if let Some((idx, _)) = pair {
+            // the child is deallocated once its last thread has switched away,
+            // which may not have happened yet on another hart
+            let child = inner.children.remove(idx);
+            let found_pid = child.getpid();
+            // ++++ temporarily hold child lock
+            let child_inner = child.acquire_inner_lock();
+            let exit_code = child_inner.exit_code;
+            inner.children_times += child_inner.exited_times;
+            inner.children_times += child_inner.children_times;
+            drop(child_inner);
+            // ++++ release child PCB lock
+            if let Some(exit_code_ref) = exit_code_ref {
+                *exit_code_ref = exit_code;
+            }
+            return Ok(found_pid);
+        }
+        // a stop or continue is reported once, the child stays
+        for child in inner.children.iter() {
             // ++++ temporarily hold child PCB lock
-            p.acquire_inner_lock().is_zombie() && (pid == -1 || pid as usize == p.getpid())
+            let mut child_inner = child.acquire_inner_lock();
+            if !wanted(child, &child_inner) {
+                continue;
+            }
+            let status = match child_inner.stop_status {
+                Some(status) if child_inner.stopped && options.contains(WaitOptions::WUNTRACED) => status,
+                Some(status) if !child_inner.stopped && options.contains(WaitOptions::WCONTINUED) => status,
+                _ => continue,
+            };
+            child_inner.stop_status = None;
             // ++++ release child PCB lock
-        });
-    if let Some((idx, _)) = pair {
-        let child = inner.children.remove(idx);
-        // confirm that child will be deallocated after being removed from children list
-        assert_eq!(Arc::strong_count(&child), 1);
-        let found_pid = child.getpid();
-        // ++++ temporarily hold child lock
-        let exit_code = child.acquire_inner_lock().exit_code;
-        // ++++ release child PCB lock
-        *translated_refmut(inner.memory_set.token(), exit_code_ptr) = exit_code;
-        found_pid as isize
+            if let Some(exit_code_ref) = exit_code_ref {
+                *exit_code_ref = status;
+            }
+            return Ok(child.getpid());
+        }
+        if options.contains(WaitOptions::WNOHANG) {
+            return Ok(0);
+        }
+        if inner.has_pending_signal() {
+            return Err(SysError::EINTR);
+        }
+        drop(inner);
+        // ---- release current PCB lock
+        process.child_exit_queue.wait(ticket);
+    }
+}
+
+/// A positive `pid` is a process, a negative one the process group `-pid`,
+/// 0 the group of the caller and -1 every process but initproc and the caller.
+/// Signal 0 only checks whether the target exists.
+pub fn sys_kill(pid: isize, signum: usize) -> SysResult {
+    let signal = if signum == 0 {
+        None
+    } else {
+        Some(SignalFlags::from_signum(signum).ok_or(SysError::EINVAL)?)
+    };
+    if pid > 0 {
+        let process = pid2process(pid as usize).ok_or(SysError::ESRCH)?;
+        if let Some(signal) = signal {
+            send_signal(&process, signal);
+        }
+        return Ok(0);
+    }
+    let targets = match pid {
+        0 => pgid2processes(current_process().acquire_inner_lock().pgid),
+        -1 => {
+            let caller = current_process();
+            all_processes()
+                .into_iter()
+                .filter(|process| !Arc::ptr_eq(process, &caller) && !Arc::ptr_eq(process, &INITPROC))
+                .collect()
+        }
+        _ => pgid2processes(pid.checked_neg().ok_or(SysError::ESRCH)? as usize),
+    };
+    if let Some(signal) = signal {
+        for process in targets.iter() {
+            send_signal(process, signal);
+        }
+    }
+    if targets.is_empty() { Err(SysError::ESRCH) } else { Ok(0) }
+}
+
+/// Move the process `pid`, the caller or one of its children, to the process
+/// group `pgid` in the same session. 0 stands for the caller and for a new
+/// group led by the process respectively.
+pub fn sys_setpgid(pid: usize, pgid: usize) -> SysResult {
+    let process = current_process();
+    let sid = process.acquire_inner_lock().sid;
+    let pid = if pid == 0 { process.getpid() } else { pid };
+    let pgid = if pgid == 0 { pid } else { pgid };
+    let target = if pid == process.getpid() {
+        Arc::clone(&process)
     } else {
-        -2
+        process.acquire_inner_lock()
+            .children
+            .iter()
+            .find(|child| child.getpid() == pid)
+            .cloned()
+            .ok_or(SysError::ESRCH)?
+    };
+    let target_sid = target.acquire_inner_lock().sid;
+    // a session leader stays in its group
+    if target_sid != sid || target_sid == pid {
+        return Err(SysError::EPERM);
+    }
+    if pgid != pid && !pgid2processes(pgid)
+        .iter()
+        .any(|member| member.acquire_inner_lock().sid == sid) {
+        return Err(SysError::EPERM);
     }
-    // ---- release current PCB lock automatically
-}
\ No newline at end of file
+    target.acquire_inner_lock().pgid = pgid;
+    Ok(0)
+}
+
+pub fn sys_getpgid(pid: usize) -> SysResult {
+    let process = if pid == 0 {
+        current_process()
+    } else {
+        pid2process(pid).ok_or(SysError::ESRCH)?
+    };
+    let pgid = process.acquire_inner_lock().pgid;
+    Ok(pgid)
+}
+
+/// Make the caller lead a new session and a new process group in it,
+/// unless it leads a process group already.
+pub fn sys_setsid() -> SysResult {
+    let process = current_process();
+    let pid = process.getpid();
+    if !pgid2processes(pid).is_empty() {
+        return Err(SysError::EPERM);
+    }
+    let mut inner = process.acquire_inner_lock();
+    inner.pgid = pid;
+    inner.sid = pid;
+    Ok(pid)
+}
+
+pub fn sys_sigaction(
+    signum: usize,
+    action: *const SignalAction,
+    old_action: *mut SignalAction,
+) -> SysResult {
+    let signal = SignalFlags::from_signum(signum).ok_or(SysError::EINVAL)?;
+    if SignalFlags::unmaskable().contains(signal) {
+        return Err(SysError::EINVAL);
+    }
+    let process = current_process();
+    let mut inner = process.acquire_inner_lock();
+    let token = inner.get_user_token();
+    let prev_action = inner.signal_actions.table[signum];
+    if !action.is_null() {
+        let mut new_action = SignalAction::default();
+        inner.memory_set.prepare_user_access(action as usize, core::mem::size_of::<SignalAction>(), false)?;
+        copy_from_user(token, action, &mut new_action)?;
+        new_action.mask = SignalFlags::from_bits_truncate(new_action.mask.bits())
+            - SignalFlags::unmaskable();
+        inner.signal_actions.table[signum] = new_action;
+    }
+    if !old_action.is_null() {
+        inner.memory_set.prepare_user_access(old_action as usize, core::mem::size_of::<SignalAction>(), true)?;
+        copy_to_user(token, old_action, &prev_action)?;
+    }
+    Ok(0)
+}
+
+pub fn sys_sigprocmask(how: usize, set: *const u32, old_set: *mut u32) -> SysResult {
+    let process = current_process();
+    let mut inner = process.acquire_inner_lock();
+    let token = inner.get_user_token();
+    let prev_mask = inner.signal_mask;
+    if !set.is_null() {
+        inner.memory_set.prepare_user_access(set as usize, core::mem::size_of::<u32>(), false)?;
+        let set = SignalFlags::from_bits_truncate(*translated_ref(token, set)?)
+            - SignalFlags::unmaskable();
+        inner.signal_mask = match how {
+            SIG_BLOCK => prev_mask | set,
+            SIG_UNBLOCK => prev_mask - set,
+            SIG_SETMASK => set,
+            _ => return Err(SysError::EINVAL),
+        };
+    }
+    if !old_set.is_null() {
+        inner.memory_set.prepare_user_access(old_set as usize, core::mem::size_of::<u32>(), true)?;
+        *translated_refmut(token, old_set)? = prev_mask.bits();
+    }
+    Ok(0)
+}
+
+/// Restore the context saved by `handle_signals` from the user stack.
+pub fn sys_sigreturn() -> SysResult {
+    let process = current_process();
+    let mut inner = process.acquire_inner_lock();
+    let token = inner.get_user_token();
+    let trap_cx = current_trap_cx();
+    let mut frame = SignalFrame::empty();
+    inner.memory_set.prepare_user_access(trap_cx.x[2], core::mem::size_of::<SignalFrame>(), false)?;
+    copy_from_user(token, trap_cx.x[2] as *const SignalFrame, &mut frame)?;
+    trap_cx.x = frame.x;
+    trap_cx.sepc = frame.sepc;
+    inner.signal_mask = SignalFlags::from_bits_truncate(frame.mask.bits())
+        - SignalFlags::unmaskable();
+    // a0 of the interrupted context, it is written back by trap_handler
+    Ok(trap_cx.x[10])
+}
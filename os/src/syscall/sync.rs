@@ -0,0 +1,102 @@
+use crate::task::current_process;
+use crate::sync::{Mutex, MutexSpin, MutexBlocking, Semaphore, Condvar};
+use crate::error::{SysError, SysResult};
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+
+/// Put `item` in the first free slot of `list` and return its index.
+fn insert_into_list<T: ?Sized>(list: &mut Vec<Option<Arc<T>>>, item: Arc<T>) -> usize {
+    if let Some(id) = list.iter().position(|slot| slot.is_none()) {
+        list[id] = Some(item);
+        id
+    } else {
+        list.push(Some(item));
+        list.len() - 1
+    }
+}
+
+fn get_from_list<T: ?Sized>(list: &[Option<Arc<T>>], id: usize) -> SysResult<Arc<T>> {
+    list.get(id)
+        .and_then(|slot| slot.clone())
+        .ok_or(SysError::EINVAL)
+}
+
+/// Create a mutex which blocks the waiters if `blocking` is not 0, or
+/// makes them yield otherwise. Returns the mutex id.
+pub fn sys_mutex_create(blocking: usize) -> SysResult {
+    let mutex: Arc<dyn Mutex> = if blocking != 0 {
+        Arc::new(MutexBlocking::new())
+    } else {
+        Arc::new(MutexSpin::new())
+    };
+    let process = current_process();
+    let mut inner = process.acquire_inner_lock();
+    Ok(insert_into_list(&mut inner.mutex_list, mutex))
+}
+
+pub fn sys_mutex_lock(mutex_id: usize) -> SysResult {
+    let process = current_process();
+    let mutex = get_from_list(&process.acquire_inner_lock().mutex_list, mutex_id)?;
+    drop(process);
+    mutex.lock()?;
+    Ok(0)
+}
+
+pub fn sys_mutex_unlock(mutex_id: usize) -> SysResult {
+    let process = current_process();
+    let mutex = get_from_list(&process.acquire_inner_lock().mutex_list, mutex_id)?;
+    drop(process);
+    mutex.unlock()?;
+    Ok(0)
+}
+
+/// Create a semaphore with `res_count` resources. Returns the semaphore id.
+pub fn sys_semaphore_create(res_count: usize) -> SysResult {
+    let process = current_process();
+    let mut inner = process.acquire_inner_lock();
+    Ok(insert_into_list(&mut inner.semaphore_list, Arc::new(Semaphore::new(res_count))))
+}
+
+pub fn sys_semaphore_up(sem_id: usize) -> SysResult {
+    let process = current_process();
+    let sem = get_from_list(&process.acquire_inner_lock().semaphore_list, sem_id)?;
+    drop(process);
+    sem.up();
+    Ok(0)
+}
+
+pub fn sys_semaphore_down(sem_id: usize) -> SysResult {
+    let process = current_process();
+    let sem = get_from_list(&process.acquire_inner_lock().semaphore_list, sem_id)?;
+    drop(process);
+    sem.down()?;
+    Ok(0)
+}
+
+/// Returns the condvar id.
+pub fn sys_condvar_create() -> SysResult {
+    let process = current_process();
+    let mut inner = process.acquire_inner_lock();
+    Ok(insert_into_list(&mut inner.condvar_list, Arc::new(Condvar::new())))
+}
+
+pub fn sys_condvar_signal(condvar_id: usize) -> SysResult {
+    let process = current_process();
+    let condvar = get_from_list(&process.acquire_inner_lock().condvar_list, condvar_id)?;
+    drop(process);
+    condvar.signal();
+    Ok(0)
+}
+
+/// `mutex_id` must be locked by the caller, it is locked again on return
+/// unless EINTR is returned.
+pub fn sys_condvar_wait(condvar_id: usize, mutex_id: usize) -> SysResult {
+    let process = current_process();
+    let inner = process.acquire_inner_lock();
+    let condvar = get_from_list(&inner.condvar_list, condvar_id)?;
+    let mutex = get_from_list(&inner.mutex_list, mutex_id)?;
+    drop(inner);
+    drop(process);
+    condvar.wait(mutex)?;
+    Ok(0)
+}
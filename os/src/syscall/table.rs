@@ -0,0 +1,93 @@
+use super::NATIVE_TABLE;
+use super::linux::LINUX_TABLE;
+use super::process::{PERSONALITY_NATIVE, PERSONALITY_LINUX};
+use crate::task::current_process;
+use crate::mm::copy_to_user;
+use crate::error::SysResult;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use alloc::vec::Vec;
+
+/// A system call and the function serving it, which gets a0..a5.
+pub struct SyscallEntry {
+    pub id: usize,
+    pub name: &'static str,
+    pub handler: fn([usize; 6]) -> SysResult,
+}
+
+/// The system calls of a personality, sorted by id, and how many times
+/// each of them has been made since boot.
+pub struct SyscallTable {
+    pub entries: &'static [SyscallEntry],
+    pub counts: &'static [AtomicUsize],
+}
+
+pub const NO_CALLS: AtomicUsize = AtomicUsize::new(0);
+
+/// Whether the ids of `entries` are strictly ascending, as `dispatch`
+/// searches for them. Checked when the kernel is built, e.g.
+/// `const _: [(); 0 - !is_sorted(SYSCALLS) as usize] = [];`
+/// fails to build unless `SYSCALLS` is sorted.
+pub const fn is_sorted(entries: &[SyscallEntry]) -> bool {
+    let mut i = 1;
+    while i < entries.len() {
+        if entries[i - 1].id >= entries[i].id {
+            return false;
+        }
+        i += 1;
+    }
+    true
+}
+
+impl SyscallTable {
+    /// Count the call and serve it, `None` if there is no such system call.
+    pub fn dispatch(&self, syscall_id: usize, args: [usize; 6]) -> Option<SysResult> {
+        let idx = self.entries.binary_search_by_key(&syscall_id, |entry| entry.id).ok()?;
+        self.counts[idx].fetch_add(1, Ordering::Relaxed);
+        Some((self.entries[idx].handler)(args))
+    }
+}
+
+const SYSCALL_NAME_LEN: usize = 24;
+
+/// How many times a system call has been made, as listed by `syscall_stats`.
+#[repr(C)]
+pub struct SyscallStat {
+    /// The table of the system call, as given to personality.
+    pub personality: usize,
+    pub id: usize,
+    pub count: usize,
+    /// Ends with '\0'.
+    pub name: [u8; SYSCALL_NAME_LEN],
+}
+
+/// Fill `stats` with up to `len` system calls, those of this kernel first
+/// and then those of Linux, each sorted by id. Returns how many system
+/// calls there are, which may be more than `len`.
+pub fn sys_syscall_stats(stats: *mut SyscallStat, len: usize) -> SysResult {
+    let tables = [(PERSONALITY_NATIVE, &NATIVE_TABLE), (PERSONALITY_LINUX, &LINUX_TABLE)];
+    let mut list = Vec::new();
+    for (personality, table) in tables.iter() {
+        for (entry, count) in table.entries.iter().zip(table.counts.iter()) {
+            let mut name = [0u8; SYSCALL_NAME_LEN];
+            let name_len = entry.name.len().min(SYSCALL_NAME_LEN - 1);
+            name[..name_len].copy_from_slice(&entry.name.as_bytes()[..name_len]);
+            list.push(SyscallStat {
+                personality: *personality,
+                id: entry.id,
+                count: count.load(Ordering::Relaxed),
+                name,
+            });
+        }
+    }
+    let process = current_process();
+    let mut inner = process.acquire_inner_lock();
+    let count = len.min(list.len());
+    if count > 0 {
+        inner.memory_set.prepare_user_access(stats as usize, count * core::mem::size_of::<SyscallStat>(), true)?;
+        let token = inner.get_user_token();
+        for (i, stat) in list[..count].iter().enumerate() {
+            copy_to_user(token, unsafe { stats.add(i) }, stat)?;
+        }
+    }
+    Ok(list.len())
+}
@@ -0,0 +1,96 @@
+use crate::task::{
+    TaskControlBlock,
+    current_task,
+    current_process,
+    add_task,
+};
+use crate::trap::{TrapContext, trap_handler};
+use crate::mm::{kernel_token, translated_refmut};
+use crate::error::{SysError, SysResult};
+use alloc::sync::Arc;
+
+/// Start a thread at `entry` with `arg` in a0, sharing the address space of
+/// the current process. Returns the tid of the new thread.
+pub fn sys_thread_create(entry: usize, arg: usize) -> SysResult {
+    let task = current_task().unwrap();
+    let process = task.process.upgrade().unwrap();
+    // create a new thread with its own user stack and trap context
+    let new_task = TaskControlBlock::new(Arc::clone(&process), true)?;
+    let mut new_task_inner = new_task.acquire_inner_lock();
+    let new_task_tid = new_task_inner.res.tid;
+    let new_task_trap_cx = new_task_inner.get_trap_cx();
+    *new_task_trap_cx = TrapContext::app_init_context(
+        entry,
+        new_task_inner.res.ustack_top(),
+        kernel_token(),
+        new_task.kernel_stack.get_top(),
+        trap_handler as usize,
+    );
+    new_task_trap_cx.x[10] = arg;
+    new_task_inner.priority = task.acquire_inner_lock().priority;
+    drop(new_task_inner);
+    // register the thread in its process
+    let mut process_inner = process.acquire_inner_lock();
+    let tasks = &mut process_inner.tasks;
+    while tasks.len() < new_task_tid + 1 {
+        tasks.push(None);
+    }
+    tasks[new_task_tid] = Some(Arc::clone(&new_task));
+    drop(process_inner);
+    // add new thread to scheduler
+    add_task(new_task);
+    Ok(new_task_tid)
+}
+
+pub fn sys_gettid() -> SysResult {
+    Ok(current_task().unwrap().gettid())
+}
+
+/// Block until thread `tid` of the current process exits, then release it
+/// and store its exit code. Returns `tid`.
+pub fn sys_waittid(tid: usize, exit_code_ptr: *mut i32) -> SysResult {
+    let task = current_task().unwrap();
+    if task.gettid() == tid {
+        return Err(SysError::EDEADLK);
+    }
+    let process = current_process();
+    loop {
+        let ticket = process.thread_exit_queue.ticket();
+        // ---- hold current PCB lock
+        let mut inner = process.acquire_inner_lock();
+        let waited_task = inner.get_task(tid).ok_or(SysError::ESRCH)?;
+        // check the user pointer before the thread is released
+        let exit_code_ref = if exit_code_ptr.is_null() {
+            None
+        } else {
+            inner.memory_set.prepare_user_access(exit_code_ptr as usize, core::mem::size_of::<i32>(), true)?;
+            Some(translated_refmut(inner.get_user_token(), exit_code_ptr)?)
+        };
+        // ++++ temporarily hold waited thread lock
+        let (exit_code, times) = {
+            let waited_inner = waited_task.acquire_inner_lock();
+            (waited_inner.exit_code, waited_inner.times)
+        };
+        // ++++ release waited thread lock
+        if let Some(exit_code) = exit_code {
+            inner.tasks[tid] = None;
+            inner.exited_times += times;
+            // the page may be swapped out once the lock is released
+            if let Some(exit_code_ref) = exit_code_ref {
+                *exit_code_ref = exit_code;
+            }
+            drop(inner);
+            // ---- release current PCB lock
+            // the tid and the kernel stack go away with the thread
+            drop(waited_task);
+            return Ok(tid);
+        }
+        if inner.has_pending_signal() {
+            return Err(SysError::EINTR);
+        }
+        drop(inner);
+        // ---- release current PCB lock
+        drop(waited_task);
+        process.thread_exit_queue.wait(ticket);
+    }
+}
@@ -0,0 +1,220 @@
+use alloc::vec::Vec;
+use alloc::sync::{Arc, Weak};
+use lazy_static::*;
+use spin::Mutex;
+use crate::mm::{KERNEL_SPACE, MapPermission, PhysPageNum, VirtAddr, MemorySet, reserve_kernel_frames};
+use crate::error::SysResult;
+use crate::config::{
+    PAGE_SIZE,
+    TRAMPOLINE,
+    TRAP_CONTEXT,
+    KERNEL_STACK_SIZE,
+    USER_STACK_SIZE,
+    USER_STACK_TOP,
+    USER_STACK_LIMIT_MAX,
+    USER_STACK_GUARD_GAP,
+};
+use super::ProcessControlBlock;
+
+/// Hands out the smallest ids first after they are given back.
+pub struct RecycleAllocator {
+    current: usize,
+    recycled: Vec<usize>,
+}
+
+impl RecycleAllocator {
+    pub fn new() -> Self {
+        RecycleAllocator {
+            current: 0,
+            recycled: Vec::new(),
+        }
+    }
+    pub fn alloc(&mut self) -> usize {
+        if let Some(id) = self.recycled.pop() {
+            id
+        } else {
+            self.current += 1;
+            self.current - 1
+        }
+    }
+    pub fn dealloc(&mut self, id: usize) {
+        assert!(id < self.current);
+        assert!(
+            self.recycled.iter().find(|i| **i == id).is_none(),
+            "id {} has been deallocated!", id
+        );
+        self.recycled.push(id);
+    }
+}
+
+lazy_static! {
+    static ref PID_ALLOCATOR: Mutex<RecycleAllocator> = Mutex::new(RecycleAllocator::new());
+    static ref KSTACK_ALLOCATOR: Mutex<RecycleAllocator> = Mutex::new(RecycleAllocator::new());
+}
+
+pub struct PidHandle(pub usize);
+
+impl Drop for PidHandle {
+    fn drop(&mut self) {
+        //println!("drop pid {}", self.0);
+        PID_ALLOCATOR.lock().dealloc(self.0);
+    }
+}
+
+pub fn pid_alloc() -> PidHandle {
+    PidHandle(PID_ALLOCATOR.lock().alloc())
+}
+
+/// Return (bottom, top) of a kernel stack in kernel space.
+pub fn kernel_stack_position(kstack_id: usize) -> (usize, usize) {
+    let top = TRAMPOLINE - kstack_id * (KERNEL_STACK_SIZE + PAGE_SIZE);
+    let bottom = top - KERNEL_STACK_SIZE;
+    (bottom, top)
+}
+
+/// Each thread has its own kernel stack, so they are numbered apart from pids.
+pub struct KernelStack {
+    kstack_id: usize,
+}
+
+/// Fails with ENOMEM if there are no frames left for the stack.
+pub fn kstack_alloc() -> SysResult<KernelStack> {
+    let kstack_id = KSTACK_ALLOCATOR.lock().alloc();
+    let (kernel_stack_bottom, kernel_stack_top) = kernel_stack_position(kstack_id);
+    reserve_kernel_frames();
+    let result = KERNEL_SPACE
+        .lock()
+        .insert_framed_area(
+            kernel_stack_bottom.into(),
+            kernel_stack_top.into(),
+            MapPermission::R | MapPermission::W,
+        );
+    if let Err(err) = result {
+        KSTACK_ALLOCATOR.lock().dealloc(kstack_id);
+        return Err(err);
+    }
+    // this hart may still cache a translation of an old stack at the same
+    // address, which it would use to push the initial task context
+    unsafe { llvm_asm!("sfence.vma" :::: "volatile"); }
+    Ok(KernelStack { kstack_id })
+}
+
+impl KernelStack {
+    pub fn push_on_top<T>(&self, value: T) -> *mut T where
+        T: Sized, {
+        let kernel_stack_top = self.get_top();
+        let ptr_mut = (kernel_stack_top - core::mem::size_of::<T>()) as *mut T;
+        unsafe { *ptr_mut = value; }
+        ptr_mut
+    }
+    pub fn get_top(&self) -> usize {
+        let (_, kernel_stack_top) = kernel_stack_position(self.kstack_id);
+        kernel_stack_top
+    }
+}
+
+impl Drop for KernelStack {
+    fn drop(&mut self) {
+        let (kernel_stack_bottom, _) = kernel_stack_position(self.kstack_id);
+        let kernel_stack_bottom_va: VirtAddr = kernel_stack_bottom.into();
+        KERNEL_SPACE
+            .lock()
+            .remove_area_with_start_vpn(kernel_stack_bottom_va.into());
+        KSTACK_ALLOCATOR.lock().dealloc(self.kstack_id);
+    }
+}
+
+/// Trap contexts of the threads go downwards from `TRAP_CONTEXT`, one page each.
+pub fn trap_cx_bottom_from_tid(tid: usize) -> usize {
+    TRAP_CONTEXT - tid * PAGE_SIZE
+}
+
+/// User stacks go downwards from `USER_STACK_TOP`, each one with room to grow
+/// up to `USER_STACK_LIMIT_MAX` and a guard gap below.
+pub fn ustack_top_from_tid(tid: usize) -> usize {
+    USER_STACK_TOP - tid * (USER_STACK_LIMIT_MAX + USER_STACK_GUARD_GAP)
+}
+
+/// The tid of a thread, together with its user stack and trap context
+/// in the address space of the process.
+pub struct TaskUserRes {
+    pub tid: usize,
+    pub process: Weak<ProcessControlBlock>,
+}
+
+impl TaskUserRes {
+    pub fn new(process: Arc<ProcessControlBlock>, alloc_user_res: bool) -> SysResult<Self> {
+        let tid = process.acquire_inner_lock().alloc_tid();
+        let task_user_res = Self {
+            tid,
+            process: Arc::downgrade(&process),
+        };
+        if alloc_user_res {
+            task_user_res.alloc_user_res()?;
+        }
+        Ok(task_user_res)
+    }
+    /// Map the user stack and the trap context of this thread, fails with
+    /// ENOMEM if there is no frame for the trap context.
+    pub fn alloc_user_res(&self) -> SysResult<()> {
+        let process = self.process.upgrade().unwrap();
+        let mut process_inner = process.acquire_inner_lock();
+        self.map_user_res(&mut process_inner.memory_set)
+    }
+    /// The same, in `memory_set`, which need not be that of the process yet.
+    pub fn map_user_res(&self, memory_set: &mut MemorySet) -> SysResult<()> {
+        let ustack_top = ustack_top_from_tid(self.tid);
+        memory_set.insert_stack_area(
+            (ustack_top - USER_STACK_SIZE).into(),
+            ustack_top.into(),
+            MapPermission::R | MapPermission::W | MapPermission::U,
+        );
+        let trap_cx_bottom = trap_cx_bottom_from_tid(self.tid);
+        memory_set.reserve_frames();
+        memory_set.insert_framed_area(
+            trap_cx_bottom.into(),
+            (trap_cx_bottom + PAGE_SIZE).into(),
+            MapPermission::R | MapPermission::W,
+        )
+    }
+    /// Unmap the user stack and the trap context, the tid is kept until
+    /// the thread has been waited for.
+    pub fn dealloc_user_res(&self) {
+        if let Some(process) = self.process.upgrade() {
+            self.unmap_user_res(&mut process.acquire_inner_lock().memory_set);
+        }
+    }
+    /// The same, for a caller holding the PCB lock.
+    pub fn unmap_user_res(&self, memory_set: &mut MemorySet) {
+        // the stack may have grown anywhere in its room
+        let ustack_top = ustack_top_from_tid(self.tid);
+        memory_set.remove_range(
+            VirtAddr::from(ustack_top - USER_STACK_LIMIT_MAX).into(),
+            VirtAddr::from(ustack_top).into(),
+        );
+        let trap_cx_bottom_va: VirtAddr = trap_cx_bottom_from_tid(self.tid).into();
+        memory_set.remove_area_with_start_vpn(trap_cx_bottom_va.into());
+    }
+    pub fn trap_cx_user_va(&self) -> usize {
+        trap_cx_bottom_from_tid(self.tid)
+    }
+    pub fn trap_cx_ppn(&self) -> PhysPageNum {
+        let process = self.process.upgrade().unwrap();
+        let process_inner = process.acquire_inner_lock();
+        let trap_cx_bottom_va: VirtAddr = trap_cx_bottom_from_tid(self.tid).into();
+        process_inner.memory_set.translate(trap_cx_bottom_va.into()).unwrap().ppn()
+    }
+    pub fn ustack_top(&self) -> usize {
+        ustack_top_from_tid(self.tid)
+    }
+}
+
+impl Drop for TaskUserRes {
+    fn drop(&mut self) {
+        self.dealloc_user_res();
+        // the whole address space goes away with the process anyway
+        if let Some(process) = self.process.upgrade() {
+            process.acquire_inner_lock().dealloc_tid(self.tid);
+        }
+    }
+}
@@ -1,28 +1,20 @@
-use super::TaskControlBlock;
-use alloc::collections::VecDeque;
-use alloc::sync::Arc;
+use super::{TaskControlBlock, ProcessControlBlock};
+use super::scheduler::{Scheduler, TaskManager};
+use alloc::collections::BTreeMap;
+use alloc::sync::{Arc, Weak};
+use alloc::vec::Vec;
 use spin::Mutex;
 use lazy_static::*;
 
-pub struct TaskManager {
-    ready_queue: VecDeque<Arc<TaskControlBlock>>,
-}
-
-/// A simple FIFO scheduler.
-impl TaskManager {
-    pub fn new() -> Self {
-        Self { ready_queue: VecDeque::new(), }
-    }
-    pub fn add(&mut self, task: Arc<TaskControlBlock>) {
-        self.ready_queue.push_back(task);
-    }
-    pub fn fetch(&mut self) -> Option<Arc<TaskControlBlock>> {
-        self.ready_queue.pop_front()
-    }
-}
-
 lazy_static! {
     pub static ref TASK_MANAGER: Mutex<TaskManager> = Mutex::new(TaskManager::new());
+    /// All processes which have not exited yet, indexed by pid.
+    pub static ref PID2PCB: Mutex<BTreeMap<usize, Arc<ProcessControlBlock>>> =
+        Mutex::new(BTreeMap::new());
+    /// Every thread which has not been dropped yet, whatever its state,
+    /// indexed by the address of its TCB.
+    static ref TASK_REGISTRY: Mutex<BTreeMap<usize, Weak<TaskControlBlock>>> =
+        Mutex::new(BTreeMap::new());
 }
 
 pub fn add_task(task: Arc<TaskControlBlock>) {
@@ -31,4 +23,82 @@ pub fn add_task(task: Arc<TaskControlBlock>) {
 
 pub fn fetch_task() -> Option<Arc<TaskControlBlock>> {
     TASK_MANAGER.lock().fetch()
-}
\ No newline at end of file
+}
+
+/// Take a thread which is not running out of the ready queue.
+pub fn remove_task(task: &Arc<TaskControlBlock>) {
+    TASK_MANAGER.lock().remove(task);
+}
+
+/// Charge a timer tick to the running thread, returns whether its time slice is over.
+pub fn tick_task(task: &Arc<TaskControlBlock>) -> bool {
+    TASK_MANAGER.lock().on_tick(task)
+}
+
+pub fn block_task(task: &Arc<TaskControlBlock>) {
+    TASK_MANAGER.lock().on_block(task);
+}
+
+/// Put a thread which has been blocked back to the ready queue.
+pub fn wake_task(task: Arc<TaskControlBlock>) {
+    let mut task_manager = TASK_MANAGER.lock();
+    task_manager.on_wake(&task);
+    task_manager.add(task);
+}
+
+pub fn pid2process(pid: usize) -> Option<Arc<ProcessControlBlock>> {
+    PID2PCB.lock().get(&pid).map(|process| Arc::clone(process))
+}
+
+/// All live processes.
+pub fn all_processes() -> Vec<Arc<ProcessControlBlock>> {
+    PID2PCB.lock().values().cloned().collect()
+}
+
+/// Live processes in the process group `pgid`.
+pub fn pgid2processes(pgid: usize) -> Vec<Arc<ProcessControlBlock>> {
+    all_processes()
+        .into_iter()
+        .filter(|process| process.acquire_inner_lock().pgid == pgid)
+        .collect()
+}
+
+pub fn insert_into_pid2process(pid: usize, process: Arc<ProcessControlBlock>) {
+    PID2PCB.lock().insert(pid, process);
+}
+
+pub fn register_task(task: &Arc<TaskControlBlock>) {
+    TASK_REGISTRY.lock().insert(Arc::as_ptr(task) as usize, Arc::downgrade(task));
+}
+
+/// Called when the TCB at `addr` is dropped.
+pub fn unregister_task(addr: usize) {
+    TASK_REGISTRY.lock().remove(&addr);
+}
+
+/// All threads which are alive, including blocked and zombie ones.
+pub fn registered_tasks() -> Vec<Arc<TaskControlBlock>> {
+    TASK_REGISTRY.lock().values().filter_map(|task| task.upgrade()).collect()
+}
+
+/// Swap out a page of a process whose address space is not in use right now,
+/// to free a frame. Returns false if no process has a page to spare.
+/// The caller may hold the lock of its own process, so the others are only
+/// tried and PID2PCB is not held while a page is written out.
+pub fn swap_out_page() -> bool {
+    let processes: Vec<_> = PID2PCB.lock().values().cloned().collect();
+    for process in processes {
+        if let Some(mut inner) = process.try_acquire_inner_lock() {
+            if inner.memory_set.swap_out_one() {
+                return true;
+            }
+        }
+    }
+    false
+}
+
+pub fn remove_from_pid2process(pid: usize) {
+    if PID2PCB.lock().remove(&pid).is_none() {
+        panic!("cannot find pid {} in pid2process!", pid);
+    }
+}
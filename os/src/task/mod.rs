@@ -3,26 +3,61 @@ mod switch;
 mod task;
 mod manager;
 mod processor;
-mod pid;
+mod id;
+mod process;
+mod signal;
+mod wait_queue;
+mod scheduler;
 
 use crate::fs::{open_file, OpenFlags};
+use crate::mm::copy_to_user;
+use crate::timer::{remove_timer, get_time};
 use switch::__switch;
-use task::{TaskControlBlock, TaskStatus};
+pub use task::{TaskControlBlock, TaskStatus, CpuTimes};
+pub use process::{ProcessControlBlock, ProcessControlBlockInner, Personality};
+use crate::smp::tlb_shootdown;
 use alloc::sync::Arc;
-use manager::fetch_task;
+use alloc::vec::Vec;
+use core::sync::atomic::{Ordering, spin_loop_hint};
+use manager::{fetch_task, remove_task, remove_from_pid2process, tick_task, block_task, wake_task};
 use lazy_static::*;
 pub use context::TaskContext;
+pub use signal::{
+    SignalFlags,
+    SignalAction,
+    SignalActions,
+    SignalFrame,
+    DefaultAction,
+    default_action,
+    SIG_DFL,
+    SIG_IGN,
+    SIG_BLOCK,
+    SIG_UNBLOCK,
+    SIG_SETMASK,
+};
 
 pub use processor::{
     run_tasks,
     current_task,
+    current_process,
     current_user_token,
     current_trap_cx,
+    current_trap_cx_user_va,
+    account_current_time,
     take_current_task,
     schedule,
 };
-pub use manager::add_task;
-pub use pid::{PidHandle, pid_alloc, KernelStack};
+pub use manager::{
+    add_task,
+    pid2process,
+    pgid2processes,
+    all_processes,
+    insert_into_pid2process,
+    registered_tasks,
+    swap_out_page,
+};
+pub use wait_queue::WaitQueue;
+pub use id::{PidHandle, pid_alloc, RecycleAllocator, KernelStack, kstack_alloc, TaskUserRes};
 
 pub fn suspend_current_and_run_next() {
     // There must be an application running.
@@ -31,43 +66,205 @@ pub fn suspend_current_and_run_next() {
     // ---- hold current PCB lock
     let mut task_inner = task.acquire_inner_lock();
     let task_cx_ptr2 = task_inner.get_task_cx_ptr2();
-    // Change status to Ready
-    task_inner.task_status = TaskStatus::Ready;
+    // a thread stopped by the exit of its process never runs again
+    let killed = task_inner.is_zombie();
+    if !killed {
+        // Change status to Ready
+        task_inner.task_status = TaskStatus::Ready;
+    }
     drop(task_inner);
     // ---- release current PCB lock
 
-    // push back to ready queue.
-    add_task(task);
+    if !killed {
+        // push back to ready queue.
+        add_task(task);
+    } else {
+        drop(task);
+    }
     // jump to scheduling cycle
     schedule(task_cx_ptr2);
 }
 
+/// Block the current task until someone calls `wakeup_task` on it.
+/// The caller must keep another reference to the task, e.g. in a `WaitQueue`.
+/// It returns at once if the task has been woken up since it was registered there.
+pub fn block_current_and_run_next() {
+    let task = current_task().unwrap();
+
+    // ---- hold current PCB lock
+    let mut task_inner = task.acquire_inner_lock();
+    if task_inner.wakeup_pending {
+        task_inner.wakeup_pending = false;
+        return;
+    }
+    let task_cx_ptr2 = task_inner.get_task_cx_ptr2();
+    // a thread stopped by the exit of its process never runs again
+    if !task_inner.is_zombie() {
+        // Change status to Blocked
+        task_inner.task_status = TaskStatus::Blocked;
+    }
+    drop(task_inner);
+    // ---- release current PCB lock
+
+    block_task(&task);
+    drop(task);
+    drop(take_current_task());
+    // jump to scheduling cycle
+    schedule(task_cx_ptr2);
+}
+
+/// Put a blocked task back to the ready queue, return false if it was not blocked.
+/// A task still running, e.g. on another hart on its way to block, will not block
+/// the next time it tries.
+pub fn wakeup_task(task: Arc<TaskControlBlock>) -> bool {
+    let mut task_inner = task.acquire_inner_lock();
+    if task_inner.task_status == TaskStatus::Running {
+        task_inner.wakeup_pending = true;
+    }
+    if task_inner.task_status != TaskStatus::Blocked {
+        return false;
+    }
+    task_inner.task_status = TaskStatus::Ready;
+    drop(task_inner);
+    wake_task(task);
+    true
+}
+
+/// Called on each timer tick, the current task goes on running
+/// unless the scheduler says its time slice is over.
+pub fn tick_current_and_run_next() {
+    let task = current_task().unwrap();
+    if tick_task(&task) {
+        drop(task);
+        suspend_current_and_run_next();
+    }
+}
+
+/// Status words reported by waitpid, encoded as in Linux.
+fn exit_status(exit_code: i32) -> i32 {
+    (exit_code & 0xff) << 8
+}
+
+fn signaled_status(signum: usize) -> i32 {
+    let core_dump = if default_action(signum) == DefaultAction::CoreDump { 0x80 } else { 0 };
+    signum as i32 | core_dump
+}
+
+fn stopped_status(signum: usize) -> i32 {
+    (signum as i32) << 8 | 0x7f
+}
+
+const CONTINUED_STATUS: i32 = 0xffff;
+
+/// The process exits with the main thread, other threads exit alone.
 pub fn exit_current_and_run_next(exit_code: i32) {
+    if current_task().unwrap().gettid() == 0 {
+        exit_current_process(exit_status(exit_code));
+    } else {
+        exit_current_thread(exit_code);
+    }
+}
+
+/// The whole process exits, whichever thread calls this.
+pub fn exit_current_group_and_run_next(exit_code: i32) {
+    exit_current_process(exit_status(exit_code));
+}
+
+/// Terminate the current process as the default action of signal `signum`.
+pub fn kill_current_and_run_next(signum: usize) {
+    exit_current_process(signaled_status(signum));
+}
+
+fn exit_current_thread(exit_code: i32) {
     // take from Processor
     let task = take_current_task().unwrap();
+    let process = task.process.upgrade().unwrap();
+    // ---- hold current PCB lock, before the thread lock as in waittid
+    let mut process_inner = process.acquire_inner_lock();
+    // **** hold current thread lock
+    let mut task_inner = task.acquire_inner_lock();
+    task_inner.account_time(get_time(), false);
+    task_inner.task_status = TaskStatus::Zombie;
+    task_inner.exit_code = Some(exit_code);
+    // we are still on the kernel stack, it is released in waittid
+    task_inner.res.unmap_user_res(&mut process_inner.memory_set);
+    drop(task_inner);
+    // **** release current thread lock
+    drop(process_inner);
+    // ---- release current PCB lock
+    process.thread_exit_queue.wake_all();
+    drop(process);
+    // drop task manually to maintain rc correctly
+    drop(task);
+    // we do not have to save task context
+    let _unused: usize = 0;
+    schedule(&_unused as *const _);
+}
+
+fn exit_current_process(status: i32) {
+    // take from Processor
+    let task = take_current_task().unwrap();
+    task.acquire_inner_lock().task_status = TaskStatus::Zombie;
+    let process = task.process.upgrade().unwrap();
+    if !stop_other_threads(&process, &task) {
+        // another thread is exiting the process and waits for this one to switch away
+        drop(process);
+        drop(task);
+        let _unused: usize = 0;
+        schedule(&_unused as *const _);
+        // never back here
+        return;
+    }
+    remove_from_pid2process(process.getpid());
+    task.acquire_inner_lock().account_time(get_time(), false);
     // **** hold current PCB lock
-    let mut inner = task.acquire_inner_lock();
+    let mut inner = process.acquire_inner_lock();
+    // the threads are not running any more, keep the total for the parent
+    inner.exited_times = inner.cpu_times();
     // Change status to Zombie
-    inner.task_status = TaskStatus::Zombie;
-    // Record exit code
-    inner.exit_code = exit_code;
-    // do not move to its parent but under initproc
-
-    // ++++++ hold initproc PCB lock here
-    {
-        let mut initproc_inner = INITPROC.acquire_inner_lock();
-        for child in inner.children.iter() {
-            child.acquire_inner_lock().parent = Some(Arc::downgrade(&INITPROC));
-            initproc_inner.children.push(child.clone());
+    inner.is_zombie = true;
+    // Record exit status
+    inner.exit_code = status;
+    // the other threads are not running, take them out of the process;
+    // the current one is kept since we are still on its kernel stack
+    let mut other_tasks = Vec::new();
+    for slot in inner.tasks.iter_mut() {
+        if let Some(other) = slot.as_ref() {
+            if !Arc::ptr_eq(other, &task) {
+                other_tasks.push(slot.take().unwrap());
+            }
         }
     }
-    // ++++++ release parent PCB lock here
-
-    inner.children.clear();
+    let parent = inner.parent.as_ref().and_then(|parent| parent.upgrade());
+    let children = core::mem::take(&mut inner.children);
     // deallocate user space
     inner.memory_set.recycle_data_pages();
+    // close files so that pipe peers see the end
+    inner.fd_table.clear();
     drop(inner);
     // **** release current PCB lock
+    // their user resources refer to the PCB lock
+    drop(other_tasks);
+    // do not move to its parent but under initproc; waitpid locks a parent
+    // before its children, so ours must be released by now
+    let adopted = !children.is_empty();
+    if adopted {
+        // ++++++ hold initproc PCB lock here
+        let mut initproc_inner = INITPROC.acquire_inner_lock();
+        for child in children {
+            child.acquire_inner_lock().parent = Some(Arc::downgrade(&INITPROC));
+            initproc_inner.children.push(child);
+        }
+        // ++++++ release initproc PCB lock here
+    }
+    // notify the parent waiting in waitpid, initproc may have got zombies as well
+    if let Some(parent) = parent {
+        notify_parent(&parent);
+    }
+    if adopted {
+        INITPROC.child_exit_queue.wake_all();
+    }
+    drop(process);
     // drop task manually to maintain rc correctly
     drop(task);
     // we do not have to save task context
@@ -75,14 +272,243 @@ pub fn exit_current_and_run_next(exit_code: i32) {
     schedule(&_unused as *const _);
 }
 
+/// Make the threads of `process` other than `task` zombies which never run
+/// again and wait until none of them is on a hart any more. Return false
+/// if another thread is doing so already.
+fn stop_other_threads(process: &ProcessControlBlock, task: &Arc<TaskControlBlock>) -> bool {
+    let mut inner = process.acquire_inner_lock();
+    if inner.exiting {
+        return false;
+    }
+    inner.exiting = true;
+    let token = inner.get_user_token();
+    let others: Vec<_> = inner.tasks
+        .iter()
+        .filter_map(|other| other.clone())
+        .filter(|other| !Arc::ptr_eq(other, task))
+        .collect();
+    drop(inner);
+    for other in others.iter() {
+        // a ready or sleeping thread is simply dropped, a running one
+        // switches away at its next trap or return to user space
+        other.acquire_inner_lock().task_status = TaskStatus::Zombie;
+        remove_task(other);
+        remove_timer(other);
+    }
+    // the IPIs make the threads in user space trap right away
+    tlb_shootdown(token);
+    for other in others.iter() {
+        while other.on_cpu.load(Ordering::Acquire) {
+            spin_loop_hint();
+        }
+    }
+    true
+}
+
+/// Switch away for good if the process has been exited by another thread
+/// while the current one was running.
+pub fn exit_current_if_killed() {
+    if !current_task().unwrap().acquire_inner_lock().is_zombie() {
+        return;
+    }
+    drop(take_current_task());
+    let _unused: usize = 0;
+    schedule(&_unused as *const _);
+}
+
 lazy_static! {
-    pub static ref INITPROC: Arc<TaskControlBlock> = Arc::new({
+    pub static ref INITPROC: Arc<ProcessControlBlock> = {
         let inode = open_file("initproc", OpenFlags::RDONLY).unwrap();
         let v = inode.read_all();
-        TaskControlBlock::new(v.as_slice())
-    });
+        ProcessControlBlock::new("initproc", Arc::new(v))
+    };
+}
+
+/// initproc must neither die nor stop, so it only gets the signals it has
+/// a handler for.
+fn is_initproc(process: &ProcessControlBlock) -> bool {
+    core::ptr::eq(process, Arc::as_ptr(&INITPROC))
 }
 
+/// Creating initproc puts its main thread in the ready queue.
 pub fn add_initproc() {
-    add_task(INITPROC.clone());
+    let _initproc = INITPROC.clone();
+}
+
+/// Wake up every blocked thread of a process, e.g. to let it notice a signal.
+pub fn wakeup_process(process: &ProcessControlBlock) {
+    let tasks: Vec<_> = process.acquire_inner_lock()
+        .tasks
+        .iter()
+        .filter_map(|task| task.clone())
+        .collect();
+    for task in tasks {
+        wakeup_task(task);
+    }
+}
+
+/// Whether a blocking syscall of the current thread should be interrupted.
+pub fn current_has_pending_signal() -> bool {
+    current_process().acquire_inner_lock().has_pending_signal()
+}
+
+/// Raise a synchronous fault signal in the current task.
+/// The task dies at once if the signal would not reach a user handler,
+/// otherwise it would fault again immediately.
+pub fn current_add_fault_signal(signal: SignalFlags) {
+    let process = current_process();
+    let mut inner = process.acquire_inner_lock();
+    let signum = signal.lowest_signum().unwrap();
+    let handler = inner.signal_actions.table[signum].handler;
+    if inner.signal_mask.contains(signal) || handler == SIG_DFL || handler == SIG_IGN {
+        drop(inner);
+        drop(process);
+        kill_current_and_run_next(signum);
+    } else {
+        inner.signals |= signal;
+    }
+}
+
+/// Tell the parent a child has exited, stopped or continued.
+fn notify_parent(parent: &ProcessControlBlock) {
+    parent.acquire_inner_lock().signals |= SignalFlags::SIGCHLD;
+    parent.child_exit_queue.wake_all();
+}
+
+/// Post `signal` to `process` and wake up its blocked threads. Pending stop
+/// signals and SIGCONT cancel each other, and SIGCONT or SIGKILL resume a
+/// stopped process right away.
+pub fn send_signal(process: &ProcessControlBlock, signal: SignalFlags) {
+    let stop_signals = SignalFlags::SIGSTOP | SignalFlags::SIGTSTP
+        | SignalFlags::SIGTTIN | SignalFlags::SIGTTOU;
+    let mut inner = process.acquire_inner_lock();
+    let signum = signal.lowest_signum().unwrap();
+    if is_initproc(process) && inner.signal_actions.table[signum].handler == SIG_DFL {
+        return;
+    }
+    let mut continued = false;
+    if stop_signals.contains(signal) {
+        inner.signals.remove(SignalFlags::SIGCONT);
+    }
+    if signal == SignalFlags::SIGCONT || signal == SignalFlags::SIGKILL {
+        inner.signals.remove(stop_signals);
+        if inner.stopped {
+            inner.stopped = false;
+            if signal == SignalFlags::SIGCONT {
+                inner.stop_status = Some(CONTINUED_STATUS);
+                continued = true;
+            }
+        }
+    }
+    inner.signals |= signal;
+    let parent = inner.parent.as_ref().and_then(|parent| parent.upgrade());
+    drop(inner);
+    if let (true, Some(parent)) = (continued, parent) {
+        notify_parent(&parent);
+    }
+    // interrupt blocking syscalls, they check the pending signals after waking up
+    wakeup_process(process);
+}
+
+/// Send `signal` to every process in group `pgid`, false if there is none.
+pub fn kill_process_group(pgid: usize, signal: SignalFlags) -> bool {
+    let processes = pgid2processes(pgid);
+    for process in processes.iter() {
+        send_signal(process, signal);
+    }
+    !processes.is_empty()
+}
+
+/// Stop the whole process for signal `signum`, the other threads follow at
+/// their next return to user space.
+fn stop_current_process(signum: usize) {
+    let process = current_process();
+    let mut inner = process.acquire_inner_lock();
+    if !inner.stopped {
+        inner.stopped = true;
+        inner.stop_status = Some(stopped_status(signum));
+        let parent = inner.parent.as_ref().and_then(|parent| parent.upgrade());
+        drop(inner);
+        if let Some(parent) = parent {
+            notify_parent(&parent);
+        }
+    }
+}
+
+/// Block the current thread while its process is stopped.
+fn stop_current() {
+    loop {
+        if !current_process().acquire_inner_lock().stopped {
+            break;
+        }
+        // SIGCONT and SIGKILL wake up all the threads
+        block_current_and_run_next();
+    }
+}
+
+/// Deliver a pending and unblocked signal before returning to user space.
+/// A caught signal gets a `SignalFrame` pushed onto the user stack and the
+/// trap context redirected to its handler, which returns through `restorer`.
+pub fn handle_signals() {
+    loop {
+        let process = current_process();
+        let mut inner = process.acquire_inner_lock();
+        if inner.stopped {
+            drop(inner);
+            drop(process);
+            stop_current();
+            continue;
+        }
+        let signum = match (inner.signals & !inner.signal_mask).lowest_signum() {
+            Some(signum) => signum,
+            None => return,
+        };
+        let signal = SignalFlags::from_signum(signum).unwrap();
+        inner.signals.remove(signal);
+        let action = inner.signal_actions.table[signum];
+        match action.handler {
+            SIG_IGN => continue,
+            // queued before initproc reset its handler
+            SIG_DFL if is_initproc(&process) => continue,
+            SIG_DFL => match default_action(signum) {
+                DefaultAction::Ignore | DefaultAction::Continue => continue,
+                DefaultAction::Stop => {
+                    drop(inner);
+                    drop(process);
+                    stop_current_process(signum);
+                    continue;
+                }
+                DefaultAction::Terminate | DefaultAction::CoreDump => {
+                    drop(inner);
+                    drop(process);
+                    kill_current_and_run_next(signum);
+                    return;
+                }
+            },
+            handler => {
+                let token = inner.get_user_token();
+                let trap_cx = current_trap_cx();
+                let frame = SignalFrame::new(trap_cx, inner.signal_mask);
+                let frame_size = core::mem::size_of::<SignalFrame>();
+                // keep the stack 16 bytes aligned as the calling convention requires
+                let frame_addr = (trap_cx.x[2] - frame_size) & !0xf;
+                let pushed = inner.memory_set
+                    .prepare_user_access(frame_addr, frame_size, true)
+                    .and_then(|_| copy_to_user(token, frame_addr as *mut SignalFrame, &frame));
+                if pushed.is_err() {
+                    // no room on the user stack
+                    drop(inner);
+                    drop(process);
+                    kill_current_and_run_next(SignalFlags::SIGSEGV.lowest_signum().unwrap());
+                    return;
+                }
+                inner.signal_mask |= action.mask | signal;
+                trap_cx.x[1] = action.restorer;
+                trap_cx.x[2] = frame_addr;
+                trap_cx.x[10] = signum;
+                trap_cx.sepc = handler;
+                return;
+            }
+        }
+    }
 }
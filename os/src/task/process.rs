@@ -0,0 +1,581 @@
+use crate::mm::{
+    MemorySet,
+    ElfInfo,
+    Executable,
+    KERNEL_SPACE,
+    VirtAddr,
+    translated_refmut,
+};
+use crate::trap::{TrapContext, trap_handler};
+use crate::config::{MAX_FD_NUM, USER_STACK_SIZE, PAGE_SIZE};
+use crate::timer::{get_time, TICKS_PER_SEC};
+use crate::error::{SysError, SysResult};
+use super::{TaskControlBlock, CpuTimes};
+use super::{PidHandle, pid_alloc, RecycleAllocator};
+use super::{SignalFlags, SignalActions, WaitQueue, DefaultAction, default_action, SIG_DFL, SIG_IGN};
+use super::{add_task, current_task, insert_into_pid2process};
+use alloc::sync::{Weak, Arc};
+use alloc::vec;
+use alloc::vec::Vec;
+use alloc::string::String;
+use spin::{Mutex, MutexGuard};
+use core::sync::atomic::{AtomicU8, Ordering};
+use crate::fs::{File, Stdin, Stdout};
+use crate::sync::{Mutex as UserMutex, Semaphore, Condvar};
+
+pub struct ProcessControlBlock {
+    // immutable
+    pub pid: PidHandle,
+    /// The main thread sleeps here in waitpid until one of the children exits.
+    pub child_exit_queue: WaitQueue,
+    /// Threads sleep here in waittid until another thread of the process exits.
+    pub thread_exit_queue: WaitQueue,
+    // mutable
+    /// Which system call table serves the process, inherited and kept by exec.
+    /// It is looked at on every system call, so it is kept out of the lock.
+    personality: AtomicU8,
+    inner: Mutex<ProcessControlBlockInner>,
+}
+
+/// The system calls a process makes are those of this kernel, or those of
+/// Linux on riscv64 so that statically linked Linux programs can run.
+#[repr(u8)]
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Personality {
+    Native = 0,
+    Linux = 1,
+}
+
+impl Personality {
+    fn from_u8(value: u8) -> Self {
+        match value {
+            0 => Self::Native,
+            _ => Self::Linux,
+        }
+    }
+}
+
+pub struct ProcessControlBlockInner {
+    /// Path of the program last run by the process.
+    pub name: String,
+    pub is_zombie: bool,
+    /// Set by the thread exiting the process, the others are stopped before
+    /// its resources are released.
+    pub exiting: bool,
+    pub memory_set: MemorySet,
+    /// The heap area starts right after the elf segments and ends at the
+    /// page holding `program_brk`, which is moved by brk/sbrk.
+    pub heap_bottom: usize,
+    pub program_brk: usize,
+    pub parent: Option<Weak<ProcessControlBlock>>,
+    pub children: Vec<Arc<ProcessControlBlock>>,
+    pub exit_code: i32,
+    pub fd_table: Vec<Option<Arc<dyn File + Send + Sync>>>,
+    pub signals: SignalFlags,
+    pub signal_mask: SignalFlags,
+    pub signal_actions: SignalActions,
+    /// Process group and session, inherited from the parent.
+    pub pgid: usize,
+    pub sid: usize,
+    /// Set by a stop signal until SIGCONT or SIGKILL arrives, meanwhile
+    /// the threads wait in `stop_current`.
+    pub stopped: bool,
+    /// Status of the last stop or continue, until waitpid reports it.
+    pub stop_status: Option<i32>,
+    /// CPU time of the threads which have been waited for, or of the whole
+    /// process once it is a zombie.
+    pub exited_times: CpuTimes,
+    /// CPU time of the children which have been waited for, and of their children.
+    pub children_times: CpuTimes,
+    /// Indexed by tid, a slot is freed when the exited thread is waited for.
+    pub tasks: Vec<Option<Arc<TaskControlBlock>>>,
+    pub task_res_allocator: RecycleAllocator,
+    /// Synchronization primitives of the threads, indexed by the ids returned
+    /// at creation. They are neither inherited by fork nor kept by exec.
+    pub mutex_list: Vec<Option<Arc<dyn UserMutex>>>,
+    pub semaphore_list: Vec<Option<Arc<Semaphore>>>,
+    pub condvar_list: Vec<Option<Arc<Condvar>>>,
+}
+
+impl ProcessControlBlockInner {
+    pub fn get_user_token(&self) -> usize {
+        self.memory_set.token()
+    }
+    /// Whether a signal would interrupt a blocking syscall, i.e. it is
+    /// pending, unblocked and not ignored.
+    pub fn has_pending_signal(&self) -> bool {
+        let mut pending = self.signals & !self.signal_mask;
+        while let Some(signum) = pending.lowest_signum() {
+            pending.remove(SignalFlags::from_signum(signum).unwrap());
+            match self.signal_actions.table[signum].handler {
+                SIG_IGN => {}
+                SIG_DFL => match default_action(signum) {
+                    DefaultAction::Ignore | DefaultAction::Continue => {}
+                    _ => return true,
+                },
+                _ => return true,
+            }
+        }
+        false
+    }
+    /// CPU time of the process so far, summed over its threads.
+    pub fn cpu_times(&self) -> CpuTimes {
+        let mut times = self.exited_times;
+        if !self.is_zombie {
+            for task in self.tasks.iter().filter_map(|task| task.as_ref()) {
+                times += task.acquire_inner_lock().times;
+            }
+        }
+        times
+    }
+    pub fn alloc_fd(&mut self) -> SysResult {
+        if let Some(fd) = (0..self.fd_table.len())
+            .find(|fd| self.fd_table[*fd].is_none()) {
+            Ok(fd)
+        } else if self.fd_table.len() < MAX_FD_NUM {
+            self.fd_table.push(None);
+            Ok(self.fd_table.len() - 1)
+        } else {
+            Err(SysError::EMFILE)
+        }
+    }
+    pub fn alloc_tid(&mut self) -> usize {
+        self.task_res_allocator.alloc()
+    }
+    pub fn dealloc_tid(&mut self, tid: usize) {
+        self.task_res_allocator.dealloc(tid)
+    }
+    pub fn get_task(&self, tid: usize) -> Option<Arc<TaskControlBlock>> {
+        self.tasks.get(tid).and_then(|task| task.clone())
+    }
+    /// Number of threads which have not exited yet.
+    pub fn thread_count(&self) -> usize {
+        self.tasks
+            .iter()
+            .filter_map(|task| task.as_ref())
+            .filter(|task| !task.acquire_inner_lock().is_zombie())
+            .count()
+    }
+}
+
+impl ProcessControlBlock {
+    pub fn acquire_inner_lock(&self) -> MutexGuard<ProcessControlBlockInner> {
+        self.inner.lock()
+    }
+    /// None if the lock is held, e.g. by a syscall working on the address space.
+    pub fn try_acquire_inner_lock(&self) -> Option<MutexGuard<ProcessControlBlockInner>> {
+        self.inner.try_lock()
+    }
+    /// Create a process with a main thread from an elf file and put it in the ready queue.
+    pub fn new(name: &str, elf_data: Arc<Vec<u8>>) -> Arc<Self> {
+        let fd_table: Vec<Option<Arc<dyn File + Send + Sync>>> = vec![
+            // 0 -> stdin
+            Some(Arc::new(Stdin)),
+            // 1 -> stdout
+            Some(Arc::new(Stdout)),
+            // 2 -> stderr
+            Some(Arc::new(Stdout)),
+        ];
+        Self::from_elf(name, Executable::new(elf_data), Vec::new(), Vec::new(), None, fd_table, None).unwrap()
+    }
+    /// Create a child process running a new program, with the given fd table,
+    /// and put it in the ready queue. The rest is inherited as by fork and exec,
+    /// except for the process group if `pgid` is given, 0 meaning a new group.
+    pub fn spawn(
+        self: &Arc<Self>,
+        name: &str,
+        executable: Executable,
+        args: Vec<String>,
+        envs: Vec<String>,
+        fd_table: Vec<Option<Arc<dyn File + Send + Sync>>>,
+        pgid: Option<usize>,
+    ) -> SysResult<Arc<Self>> {
+        Self::from_elf(name, executable, args, envs, Some(self), fd_table, pgid)
+    }
+    fn from_elf(
+        name: &str,
+        executable: Executable,
+        args: Vec<String>,
+        envs: Vec<String>,
+        parent: Option<&Arc<Self>>,
+        fd_table: Vec<Option<Arc<dyn File + Send + Sync>>>,
+        pgid: Option<usize>,
+    ) -> SysResult<Arc<Self>> {
+        if initial_stack_size(&args, &envs) > USER_STACK_SIZE {
+            return Err(SysError::E2BIG);
+        }
+        // memory_set with elf program headers/trampoline
+        let (mut memory_set, heap_bottom, elf_info) = MemorySet::from_elf(&executable)?;
+        // allocate a pid
+        let pid_handle = pid_alloc();
+        let mut signal_mask = SignalFlags::empty();
+        let mut signal_actions = SignalActions::default();
+        // a process without a parent leads a session of its own
+        let mut parent_pgid = pid_handle.0;
+        let mut sid = pid_handle.0;
+        let mut personality = Personality::Native;
+        if let Some(parent) = parent {
+            let parent_inner = parent.acquire_inner_lock();
+            memory_set.set_stack_limit(parent_inner.memory_set.stack_limit());
+            signal_mask = parent_inner.signal_mask;
+            signal_actions = parent_inner.signal_actions.clone();
+            signal_actions.reset_handlers();
+            parent_pgid = parent_inner.pgid;
+            sid = parent_inner.sid;
+            personality = parent.personality();
+        }
+        let pgid = match pgid {
+            None => parent_pgid,
+            Some(0) => pid_handle.0,
+            Some(pgid) => pgid,
+        };
+        let process = Arc::new(Self {
+            pid: pid_handle,
+            child_exit_queue: WaitQueue::new(),
+            thread_exit_queue: WaitQueue::new(),
+            personality: AtomicU8::new(personality as u8),
+            inner: Mutex::new(ProcessControlBlockInner {
+                name: String::from(name),
+                is_zombie: false,
+                exiting: false,
+                memory_set,
+                heap_bottom,
+                program_brk: heap_bottom,
+                parent: parent.map(Arc::downgrade),
+                children: Vec::new(),
+                exit_code: 0,
+                fd_table,
+                signals: SignalFlags::empty(),
+                signal_mask,
+                signal_actions,
+                pgid,
+                sid,
+                stopped: false,
+                stop_status: None,
+                exited_times: CpuTimes::default(),
+                children_times: CpuTimes::default(),
+                tasks: Vec::new(),
+                task_res_allocator: RecycleAllocator::new(),
+                mutex_list: Vec::new(),
+                semaphore_list: Vec::new(),
+                condvar_list: Vec::new(),
+            }),
+        });
+        // create the main thread, with its user stack and trap context mapped
+        let task = TaskControlBlock::new(Arc::clone(&process), true)?;
+        // prepare TrapContext in user space
+        let task_inner = task.acquire_inner_lock();
+        let trap_cx = task_inner.get_trap_cx();
+        let ustack_top = task_inner.res.ustack_top();
+        let kstack_top = task.kernel_stack.get_top();
+        drop(task_inner);
+        let (user_sp, argv_base) = push_initial_stack(
+            &mut process.acquire_inner_lock().memory_set,
+            ustack_top,
+            &args,
+            &envs,
+            &elf_info,
+            process.getpid(),
+        )?;
+        *trap_cx = TrapContext::app_init_context(
+            elf_info.start,
+            user_sp,
+            KERNEL_SPACE.lock().token(),
+            kstack_top,
+            trap_handler as usize,
+        );
+        trap_cx.x[10] = args.len();
+        trap_cx.x[11] = argv_base;
+        // a spawned child inherits the priority of the spawning thread
+        if parent.is_some() {
+            let priority = current_task().unwrap().acquire_inner_lock().priority;
+            task.acquire_inner_lock().priority = priority;
+        }
+        process.acquire_inner_lock().tasks.push(Some(Arc::clone(&task)));
+        if let Some(parent) = parent {
+            parent.acquire_inner_lock().children.push(Arc::clone(&process));
+        }
+        insert_into_pid2process(process.getpid(), Arc::clone(&process));
+        add_task(task);
+        Ok(process)
+    }
+    /// Only a process with a single thread can exec, which has to be the main thread.
+    pub fn exec(
+        self: &Arc<Self>,
+        name: &str,
+        executable: Executable,
+        args: Vec<String>,
+        envs: Vec<String>,
+    ) -> SysResult<()> {
+        if self.acquire_inner_lock().thread_count() > 1 {
+            return Err(SysError::EBUSY);
+        }
+        if initial_stack_size(&args, &envs) > USER_STACK_SIZE {
+            return Err(SysError::E2BIG);
+        }
+        // memory_set with elf program headers/trampoline
+        let (mut memory_set, heap_bottom, elf_info) = MemorySet::from_elf(&executable)?;
+        // the stack limit is kept across exec
+        memory_set.set_stack_limit(self.acquire_inner_lock().memory_set.stack_limit());
+        let task = self.acquire_inner_lock().get_task(0).unwrap();
+
+        // the new image is set up whole before it replaces the old one,
+        // which is left untouched if anything fails
+        let task_inner = task.acquire_inner_lock();
+        task_inner.res.map_user_res(&mut memory_set)?;
+        let trap_cx_user_va = task_inner.res.trap_cx_user_va();
+        let ustack_top = task_inner.res.ustack_top();
+        drop(task_inner);
+        let trap_cx_ppn = memory_set.translate(VirtAddr::from(trap_cx_user_va).into()).unwrap().ppn();
+        let (user_sp, argv_base) = push_initial_stack(
+            &mut memory_set,
+            ustack_top,
+            &args,
+            &envs,
+            &elf_info,
+            self.getpid(),
+        )?;
+        // initialize trap_cx
+        let trap_cx: &mut TrapContext = trap_cx_ppn.get_mut();
+        *trap_cx = TrapContext::app_init_context(
+            elf_info.start,
+            user_sp,
+            KERNEL_SPACE.lock().token(),
+            task.kernel_stack.get_top(),
+            trap_handler as usize,
+        );
+        trap_cx.x[10] = args.len();
+        trap_cx.x[11] = argv_base;
+
+        // **** hold current PCB lock
+        let mut inner = self.acquire_inner_lock();
+        inner.name = String::from(name);
+        // substitute memory_set
+        inner.memory_set = memory_set;
+        // start over with an empty heap
+        inner.heap_bottom = heap_bottom;
+        inner.program_brk = heap_bottom;
+        // handlers of the old image are gone
+        inner.signal_actions.reset_handlers();
+        // so are the synchronization primitives
+        inner.mutex_list.clear();
+        inner.semaphore_list.clear();
+        inner.condvar_list.clear();
+        drop(inner);
+        // **** release current PCB lock
+
+        // only this thread uses its trap context, so it may switch over last
+        task.acquire_inner_lock().trap_cx_ppn = trap_cx_ppn;
+        Ok(())
+    }
+    /// Only a process with a single thread can fork, the child gets a copy
+    /// of the main thread and is put in the ready queue.
+    pub fn fork(self: &Arc<Self>) -> SysResult<Arc<Self>> {
+        // ---- hold parent PCB lock
+        let mut parent_inner = self.acquire_inner_lock();
+        if parent_inner.thread_count() > 1 {
+            return Err(SysError::EBUSY);
+        }
+        // page tables of the child and its kernel stack need some frames
+        parent_inner.memory_set.reserve_frames();
+        // copy user space(include trap contexts and user stacks)
+        let memory_set = MemorySet::from_existed_user(
+            &mut parent_inner.memory_set
+        )?;
+        // alloc a pid
+        let pid_handle = pid_alloc();
+        // copy fd table
+        let mut new_fd_table: Vec<Option<Arc<dyn File + Send + Sync>>> = Vec::new();
+        for fd in parent_inner.fd_table.iter() {
+            if let Some(file) = fd {
+                new_fd_table.push(Some(file.clone()));
+            } else {
+                new_fd_table.push(None);
+            }
+        }
+        let child = Arc::new(Self {
+            pid: pid_handle,
+            child_exit_queue: WaitQueue::new(),
+            thread_exit_queue: WaitQueue::new(),
+            personality: AtomicU8::new(self.personality() as u8),
+            inner: Mutex::new(ProcessControlBlockInner {
+                name: parent_inner.name.clone(),
+                is_zombie: false,
+                exiting: false,
+                memory_set,
+                // the heap has been copied along with the rest of user space
+                heap_bottom: parent_inner.heap_bottom,
+                program_brk: parent_inner.program_brk,
+                parent: Some(Arc::downgrade(self)),
+                children: Vec::new(),
+                exit_code: 0,
+                fd_table: new_fd_table,
+                // pending signals are not inherited
+                signals: SignalFlags::empty(),
+                signal_mask: parent_inner.signal_mask,
+                signal_actions: parent_inner.signal_actions.clone(),
+                pgid: parent_inner.pgid,
+                sid: parent_inner.sid,
+                stopped: false,
+                stop_status: None,
+                exited_times: CpuTimes::default(),
+                children_times: CpuTimes::default(),
+                tasks: Vec::new(),
+                task_res_allocator: RecycleAllocator::new(),
+                mutex_list: Vec::new(),
+                semaphore_list: Vec::new(),
+                condvar_list: Vec::new(),
+            }),
+        });
+        drop(parent_inner);
+        // ---- release parent PCB lock
+
+        // the main thread of the child, its user stack and trap context have been copied
+        let task = TaskControlBlock::new(Arc::clone(&child), false)?;
+        // add child, once nothing can fail any more
+        self.acquire_inner_lock().children.push(Arc::clone(&child));
+        // modify kernel_sp in trap_cx
+        let priority = current_task().unwrap().acquire_inner_lock().priority;
+        // **** acquire child thread lock
+        let mut task_inner = task.acquire_inner_lock();
+        task_inner.priority = priority;
+        let trap_cx = task_inner.get_trap_cx();
+        drop(task_inner);
+        // **** release child thread lock
+        trap_cx.kernel_sp = task.kernel_stack.get_top();
+        // we do not have to move to next instruction since we have done it before
+        // for child process, fork returns 0
+        trap_cx.x[10] = 0;
+        child.acquire_inner_lock().tasks.push(Some(Arc::clone(&task)));
+        insert_into_pid2process(child.getpid(), Arc::clone(&child));
+        add_task(task);
+        Ok(child)
+    }
+    pub fn getpid(&self) -> usize {
+        self.pid.0
+    }
+    pub fn personality(&self) -> Personality {
+        Personality::from_u8(self.personality.load(Ordering::Relaxed))
+    }
+    /// Returns the previous personality.
+    pub fn set_personality(&self, personality: Personality) -> Personality {
+        Personality::from_u8(self.personality.swap(personality as u8, Ordering::Relaxed))
+    }
+}
+
+const AT_NULL: usize = 0;
+const AT_PHDR: usize = 3;
+const AT_PHENT: usize = 4;
+const AT_PHNUM: usize = 5;
+const AT_PAGESZ: usize = 6;
+const AT_BASE: usize = 7;
+const AT_ENTRY: usize = 9;
+const AT_UID: usize = 11;
+const AT_EUID: usize = 12;
+const AT_GID: usize = 13;
+const AT_EGID: usize = 14;
+const AT_HWCAP: usize = 16;
+const AT_CLKTCK: usize = 17;
+const AT_SECURE: usize = 23;
+const AT_RANDOM: usize = 25;
+/// Entries of the auxiliary vector, including `AT_NULL`.
+const AUXV_LEN: usize = 15;
+/// Bytes `AT_RANDOM` points to.
+const RANDOM_LEN: usize = 16;
+
+/// argc, argv, envp, auxv, the strings and room for alignment all go on the user stack.
+fn initial_stack_size(args: &[String], envs: &[String]) -> usize {
+    let words = 1 + (args.len() + 1) + (envs.len() + 1) + 2 * AUXV_LEN;
+    words * core::mem::size_of::<usize>()
+        + args.iter().chain(envs.iter()).map(|s| s.len() + 1).sum::<usize>()
+        + RANDOM_LEN
+        // alignment of the strings and of the stack pointer
+        + 2 * 16
+}
+
+/// There is no source of entropy but the timer, which is good enough to
+/// keep programs from relying on the bytes being the same every time.
+fn random_bytes(seed: usize) -> [u8; RANDOM_LEN] {
+    let mut state = (get_time() ^ seed) as u64;
+    let mut bytes = [0u8; RANDOM_LEN];
+    for chunk in bytes.chunks_mut(8) {
+        // splitmix64
+        state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^= z >> 31;
+        chunk.copy_from_slice(&z.to_le_bytes());
+    }
+    bytes
+}
+
+/// Lay out the initial stack below `user_sp` as the RISC-V psABI does:
+/// the strings and the `AT_RANDOM` bytes on top, then auxv, envp and argv
+/// and finally argc, where the returned 16B-aligned user_sp points to.
+/// The address of argv is returned as well.
+fn push_initial_stack(
+    memory_set: &mut MemorySet,
+    mut user_sp: usize,
+    args: &[String],
+    envs: &[String],
+    elf_info: &ElfInfo,
+    seed: usize,
+) -> SysResult<(usize, usize)> {
+    let token = memory_set.token();
+    // the stack is mapped lazily, so fault in the pages of the arguments first
+    let size = initial_stack_size(args, envs);
+    memory_set.prepare_user_access(user_sp - size, size, true)?;
+    let push_bytes = |user_sp: &mut usize, bytes: &[u8]| {
+        *user_sp -= bytes.len();
+        for (i, c) in bytes.iter().enumerate() {
+            *translated_refmut(token, (*user_sp + i) as *mut u8).unwrap() = *c;
+        }
+        *user_sp
+    };
+    let push_str = |user_sp: &mut usize, s: &String| {
+        push_bytes(user_sp, &[0]);
+        push_bytes(user_sp, s.as_bytes())
+    };
+    let env_ptrs: Vec<usize> = envs.iter().map(|env| push_str(&mut user_sp, env)).collect();
+    let arg_ptrs: Vec<usize> = args.iter().map(|arg| push_str(&mut user_sp, arg)).collect();
+    user_sp -= user_sp % 16;
+    let random = push_bytes(&mut user_sp, &random_bytes(seed));
+    let auxv: [(usize, usize); AUXV_LEN] = [
+        (AT_PHDR, elf_info.phdr),
+        (AT_PHENT, elf_info.phent),
+        (AT_PHNUM, elf_info.phnum),
+        (AT_PAGESZ, PAGE_SIZE),
+        (AT_BASE, elf_info.base),
+        (AT_ENTRY, elf_info.entry_point),
+        // everyone is root, so libc has no reason to distrust the environment
+        (AT_UID, 0),
+        (AT_EUID, 0),
+        (AT_GID, 0),
+        (AT_EGID, 0),
+        (AT_SECURE, 0),
+        (AT_HWCAP, 0),
+        (AT_CLKTCK, TICKS_PER_SEC),
+        (AT_RANDOM, random),
+        (AT_NULL, 0),
+    ];
+    let mut words: Vec<usize> = Vec::new();
+    words.push(args.len());
+    words.extend(arg_ptrs.iter());
+    words.push(0);
+    words.extend(env_ptrs.iter());
+    words.push(0);
+    for (key, value) in auxv.iter() {
+        words.push(*key);
+        words.push(*value);
+    }
+    user_sp -= words.len() * core::mem::size_of::<usize>();
+    user_sp -= user_sp % 16;
+    for (i, word) in words.iter().enumerate() {
+        *translated_refmut(
+            token,
+            (user_sp + i * core::mem::size_of::<usize>()) as *mut usize
+        ).unwrap() = *word;
+    }
+    Ok((user_sp, user_sp + core::mem::size_of::<usize>()))
+}
@@ -1,11 +1,18 @@
-use super::TaskControlBlock;
+use super::{TaskControlBlock, ProcessControlBlock};
 use alloc::sync::Arc;
+use alloc::vec::Vec;
 use core::cell::RefCell;
+use core::sync::atomic::{Ordering, spin_loop_hint};
 use lazy_static::*;
 use super::{fetch_task, TaskStatus};
 use super::__switch;
 use crate::trap::TrapContext;
+use crate::timer::{check_timer, get_time};
+use crate::fs::poll_console;
+use crate::config::MAX_HARTS;
+use crate::smp::hart_id;
 
+/// Each hart has a processor of its own, only touched by that hart.
 pub struct Processor {
     inner: RefCell<ProcessorInner>,
 }
@@ -33,20 +40,45 @@ impl Processor {
     pub fn run(&self) {
         loop {
             if let Some(task) = fetch_task() {
+                // it is back in the ready queue, but the hart it ran on
+                // may not have switched away from it yet
+                while task.on_cpu.load(Ordering::Acquire) {
+                    spin_loop_hint();
+                }
+                task.on_cpu.store(true, Ordering::Relaxed);
                 let idle_task_cx_ptr2 = self.get_idle_task_cx_ptr2();
                 // acquire
                 let mut task_inner = task.acquire_inner_lock();
+                if task_inner.is_zombie() {
+                    // its process has been exited since it was fetched
+                    drop(task_inner);
+                    task.on_cpu.store(false, Ordering::Release);
+                    continue;
+                }
                 let next_task_cx_ptr2 = task_inner.get_task_cx_ptr2();
                 task_inner.task_status = TaskStatus::Running;
+                task_inner.time_stamp = get_time();
                 drop(task_inner);
                 // release
-                self.inner.borrow_mut().current = Some(task);
+                self.inner.borrow_mut().current = Some(Arc::clone(&task));
                 unsafe {
+                    // the kernel stack may have been mapped again since this
+                    // hart last used its address
+                    llvm_asm!("sfence.vma" :::: "volatile");
                     __switch(
                         idle_task_cx_ptr2,
                         next_task_cx_ptr2,
                     );
                 }
+                // back from the task, its context is saved; it is released
+                // only now if it has exited, since we were on its kernel stack
+                task.acquire_inner_lock().account_time(get_time(), false);
+                task.on_cpu.store(false, Ordering::Release);
+            } else {
+                // timer interrupts are off in the kernel, so poll the
+                // sleeping tasks and the console while nothing is ready to run
+                check_timer();
+                poll_console();
             }
         }
     }
@@ -59,33 +91,55 @@ impl Processor {
 }
 
 lazy_static! {
-    pub static ref PROCESSOR: Processor = Processor::new();
+    static ref PROCESSORS: Vec<Processor> = (0..MAX_HARTS).map(|_| Processor::new()).collect();
+}
+
+/// The processor of the current hart.
+fn processor() -> &'static Processor {
+    &PROCESSORS[hart_id()]
 }
 
 pub fn run_tasks() {
-    PROCESSOR.run();
+    processor().run();
 }
 
 pub fn take_current_task() -> Option<Arc<TaskControlBlock>> {
-    PROCESSOR.take_current()
+    processor().take_current()
 }
 
 pub fn current_task() -> Option<Arc<TaskControlBlock>> {
-    PROCESSOR.current()
+    processor().current()
+}
+
+pub fn current_process() -> Arc<ProcessControlBlock> {
+    current_task().unwrap().process.upgrade().unwrap()
 }
 
 pub fn current_user_token() -> usize {
     let task = current_task().unwrap();
-    let token = task.acquire_inner_lock().get_user_token();
+    let token = task.get_user_token();
     token
 }
 
+/// Charge the time since the last stamp of the current task to user or kernel mode.
+pub fn account_current_time(in_user: bool) {
+    current_task().unwrap().acquire_inner_lock().account_time(get_time(), in_user);
+}
+
 pub fn current_trap_cx() -> &'static mut TrapContext {
     current_task().unwrap().acquire_inner_lock().get_trap_cx()
 }
 
+pub fn current_trap_cx_user_va() -> usize {
+    current_task()
+        .unwrap()
+        .acquire_inner_lock()
+        .res
+        .trap_cx_user_va()
+}
+
 pub fn schedule(switched_task_cx_ptr2: *const usize) {
-    let idle_task_cx_ptr2 = PROCESSOR.get_idle_task_cx_ptr2();
+    let idle_task_cx_ptr2 = processor().get_idle_task_cx_ptr2();
     unsafe {
         __switch(
             switched_task_cx_ptr2,
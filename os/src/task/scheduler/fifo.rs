@@ -0,0 +1,27 @@
+use super::Scheduler;
+use crate::task::TaskControlBlock;
+use alloc::collections::VecDeque;
+use alloc::sync::Arc;
+
+/// A simple FIFO scheduler, switching threads on every timer tick.
+pub struct FifoScheduler {
+    ready_queue: VecDeque<Arc<TaskControlBlock>>,
+}
+
+impl FifoScheduler {
+    pub fn new() -> Self {
+        Self { ready_queue: VecDeque::new(), }
+    }
+}
+
+impl Scheduler for FifoScheduler {
+    fn add(&mut self, task: Arc<TaskControlBlock>) {
+        self.ready_queue.push_back(task);
+    }
+    fn fetch(&mut self) -> Option<Arc<TaskControlBlock>> {
+        self.ready_queue.pop_front()
+    }
+    fn remove(&mut self, task: &Arc<TaskControlBlock>) {
+        self.ready_queue.retain(|t| !Arc::ptr_eq(t, task));
+    }
+}
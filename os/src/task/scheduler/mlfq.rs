@@ -0,0 +1,76 @@
+use super::Scheduler;
+use crate::task::TaskControlBlock;
+use alloc::collections::VecDeque;
+use alloc::sync::Arc;
+
+/// Number of queues, level 0 being the highest.
+const MLFQ_LEVELS: usize = 3;
+/// Every this many ticks all ready threads go back to the highest level,
+/// so that CPU-bound ones are not starved by a stream of interactive ones.
+const BOOST_TICKS: usize = 100;
+
+/// Length of a time slice at `level` in timer ticks, longer for lower levels.
+fn slice_ticks(level: usize) -> usize {
+    1 << level
+}
+
+/// A multi-level feedback queue. A thread using up its time slice is demoted
+/// one level, one which blocks before is promoted one level on wakeup,
+/// so interactive threads stay ahead of CPU-bound ones.
+pub struct MlfqScheduler {
+    queues: [VecDeque<Arc<TaskControlBlock>>; MLFQ_LEVELS],
+    ticks: usize,
+}
+
+impl MlfqScheduler {
+    pub fn new() -> Self {
+        Self {
+            queues: [VecDeque::new(), VecDeque::new(), VecDeque::new()],
+            ticks: 0,
+        }
+    }
+    fn boost(&mut self) {
+        for level in 1..MLFQ_LEVELS {
+            while let Some(task) = self.queues[level].pop_front() {
+                task.acquire_inner_lock().level = 0;
+                self.queues[0].push_back(task);
+            }
+        }
+    }
+}
+
+impl Scheduler for MlfqScheduler {
+    fn add(&mut self, task: Arc<TaskControlBlock>) {
+        let level = task.acquire_inner_lock().level;
+        self.queues[level].push_back(task);
+    }
+    fn fetch(&mut self) -> Option<Arc<TaskControlBlock>> {
+        let task = self.queues.iter_mut().find_map(|queue| queue.pop_front())?;
+        // a new time slice
+        task.acquire_inner_lock().slice_used = 0;
+        Some(task)
+    }
+    fn remove(&mut self, task: &Arc<TaskControlBlock>) {
+        for queue in self.queues.iter_mut() {
+            queue.retain(|t| !Arc::ptr_eq(t, task));
+        }
+    }
+    fn on_tick(&mut self, task: &Arc<TaskControlBlock>) -> bool {
+        self.ticks += 1;
+        if self.ticks % BOOST_TICKS == 0 {
+            self.boost();
+        }
+        let mut task_inner = task.acquire_inner_lock();
+        task_inner.slice_used += 1;
+        if task_inner.slice_used >= slice_ticks(task_inner.level) {
+            task_inner.level = (task_inner.level + 1).min(MLFQ_LEVELS - 1);
+            return true;
+        }
+        // a thread of a higher level has been woken up
+        self.queues[..task_inner.level].iter().any(|queue| !queue.is_empty())
+    }
+    fn on_wake(&mut self, task: &Arc<TaskControlBlock>) {
+        let mut task_inner = task.acquire_inner_lock();
+        task_inner.level = task_inner.level.saturating_sub(1);
+    }
+}
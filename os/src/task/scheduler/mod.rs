@@ -0,0 +1,41 @@
+#[cfg(not(any(feature = "sched_stride", feature = "sched_mlfq")))]
+mod fifo;
+#[cfg(feature = "sched_stride")]
+mod stride;
+#[cfg(feature = "sched_mlfq")]
+mod mlfq;
+
+use super::TaskControlBlock;
+use alloc::sync::Arc;
+
+#[cfg(all(feature = "sched_stride", feature = "sched_mlfq"))]
+compile_error!("only one scheduler feature can be enabled");
+
+// the policy is chosen by a cargo feature, FIFO unless another one is enabled
+#[cfg(not(any(feature = "sched_stride", feature = "sched_mlfq")))]
+pub type TaskManager = fifo::FifoScheduler;
+#[cfg(feature = "sched_stride")]
+pub type TaskManager = stride::StrideScheduler;
+#[cfg(feature = "sched_mlfq")]
+pub type TaskManager = mlfq::MlfqScheduler;
+
+/// Keeps the ready threads and decides which one runs next.
+/// The hooks let a policy learn how the threads use the processor.
+pub trait Scheduler {
+    /// A thread becomes ready: it is new, has yielded, used up its time slice
+    /// or has been woken up.
+    fn add(&mut self, task: Arc<TaskControlBlock>);
+    /// Take the thread to run next out of the scheduler.
+    fn fetch(&mut self) -> Option<Arc<TaskControlBlock>>;
+    /// Take a thread out of the scheduler before it runs again, e.g. it has been killed.
+    fn remove(&mut self, task: &Arc<TaskControlBlock>);
+    /// A timer tick while `task` is running, returns whether it should give
+    /// up the processor.
+    fn on_tick(&mut self, _task: &Arc<TaskControlBlock>) -> bool {
+        true
+    }
+    /// The running `task` blocks before its time slice is over.
+    fn on_block(&mut self, _task: &Arc<TaskControlBlock>) {}
+    /// A blocked `task` is woken up, it is added right after.
+    fn on_wake(&mut self, _task: &Arc<TaskControlBlock>) {}
+}
@@ -0,0 +1,47 @@
+use super::Scheduler;
+use crate::task::TaskControlBlock;
+use alloc::collections::VecDeque;
+use alloc::sync::Arc;
+
+/// Each time a thread is picked it is charged `BIG_STRIDE / priority`,
+/// so the share of the processor it gets is proportional to its priority.
+const BIG_STRIDE: u64 = 1 << 32;
+
+/// A stride scheduler, threads of the same pass are picked in FIFO order.
+pub struct StrideScheduler {
+    ready_queue: VecDeque<Arc<TaskControlBlock>>,
+    /// Pass of the thread picked last. A thread joining the queue starts
+    /// no lower, so that it cannot make up for the time it was away.
+    current_pass: u64,
+}
+
+impl StrideScheduler {
+    pub fn new() -> Self {
+        Self { ready_queue: VecDeque::new(), current_pass: 0 }
+    }
+}
+
+impl Scheduler for StrideScheduler {
+    fn add(&mut self, task: Arc<TaskControlBlock>) {
+        let mut task_inner = task.acquire_inner_lock();
+        task_inner.pass = task_inner.pass.max(self.current_pass);
+        drop(task_inner);
+        self.ready_queue.push_back(task);
+    }
+    fn fetch(&mut self) -> Option<Arc<TaskControlBlock>> {
+        let idx = self.ready_queue
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, task)| task.acquire_inner_lock().pass)
+            .map(|(idx, _)| idx)?;
+        let task = self.ready_queue.remove(idx).unwrap();
+        let mut task_inner = task.acquire_inner_lock();
+        self.current_pass = task_inner.pass;
+        task_inner.pass += BIG_STRIDE / task_inner.priority as u64;
+        drop(task_inner);
+        Some(task)
+    }
+    fn remove(&mut self, task: &Arc<TaskControlBlock>) {
+        self.ready_queue.retain(|t| !Arc::ptr_eq(t, task));
+    }
+}
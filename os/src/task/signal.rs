@@ -0,0 +1,164 @@
+use crate::trap::TrapContext;
+
+pub const MAX_SIG: usize = 31;
+
+/// Special values of `SignalAction::handler`.
+pub const SIG_DFL: usize = 0;
+pub const SIG_IGN: usize = 1;
+
+/// `how` argument of sigprocmask.
+pub const SIG_BLOCK: usize = 0;
+pub const SIG_UNBLOCK: usize = 1;
+pub const SIG_SETMASK: usize = 2;
+
+bitflags! {
+    /// Bit `1 << signum` represents signal `signum`, numbered as in Linux.
+    pub struct SignalFlags: u32 {
+        const SIGHUP = 1 << 1;
+        const SIGINT = 1 << 2;
+        const SIGQUIT = 1 << 3;
+        const SIGILL = 1 << 4;
+        const SIGTRAP = 1 << 5;
+        const SIGABRT = 1 << 6;
+        const SIGBUS = 1 << 7;
+        const SIGFPE = 1 << 8;
+        const SIGKILL = 1 << 9;
+        const SIGUSR1 = 1 << 10;
+        const SIGSEGV = 1 << 11;
+        const SIGUSR2 = 1 << 12;
+        const SIGPIPE = 1 << 13;
+        const SIGALRM = 1 << 14;
+        const SIGTERM = 1 << 15;
+        const SIGSTKFLT = 1 << 16;
+        const SIGCHLD = 1 << 17;
+        const SIGCONT = 1 << 18;
+        const SIGSTOP = 1 << 19;
+        const SIGTSTP = 1 << 20;
+        const SIGTTIN = 1 << 21;
+        const SIGTTOU = 1 << 22;
+        const SIGURG = 1 << 23;
+        const SIGXCPU = 1 << 24;
+        const SIGXFSZ = 1 << 25;
+        const SIGVTALRM = 1 << 26;
+        const SIGPROF = 1 << 27;
+        const SIGWINCH = 1 << 28;
+        const SIGIO = 1 << 29;
+        const SIGPWR = 1 << 30;
+        const SIGSYS = 1 << 31;
+    }
+}
+
+impl SignalFlags {
+    pub fn from_signum(signum: usize) -> Option<Self> {
+        if signum == 0 || signum > MAX_SIG {
+            None
+        } else {
+            Self::from_bits(1 << signum)
+        }
+    }
+    /// Signals that can be neither caught, blocked nor ignored.
+    pub fn unmaskable() -> Self {
+        Self::SIGKILL | Self::SIGSTOP
+    }
+    /// The smallest signal number in the set.
+    pub fn lowest_signum(&self) -> Option<usize> {
+        if self.is_empty() {
+            None
+        } else {
+            Some(self.bits.trailing_zeros() as usize)
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum DefaultAction {
+    Terminate,
+    CoreDump,
+    Ignore,
+    Stop,
+    Continue,
+}
+
+pub fn default_action(signum: usize) -> DefaultAction {
+    match SignalFlags::from_signum(signum).unwrap() {
+        SignalFlags::SIGQUIT | SignalFlags::SIGILL | SignalFlags::SIGTRAP |
+        SignalFlags::SIGABRT | SignalFlags::SIGBUS | SignalFlags::SIGFPE |
+        SignalFlags::SIGSEGV | SignalFlags::SIGXCPU | SignalFlags::SIGXFSZ |
+        SignalFlags::SIGSYS => DefaultAction::CoreDump,
+        SignalFlags::SIGCHLD | SignalFlags::SIGURG | SignalFlags::SIGWINCH => DefaultAction::Ignore,
+        SignalFlags::SIGSTOP | SignalFlags::SIGTSTP |
+        SignalFlags::SIGTTIN | SignalFlags::SIGTTOU => DefaultAction::Stop,
+        SignalFlags::SIGCONT => DefaultAction::Continue,
+        _ => DefaultAction::Terminate,
+    }
+}
+
+/// Layout shared with user space.
+/// `restorer` is where the handler returns to, it should call sigreturn.
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct SignalAction {
+    pub handler: usize,
+    pub restorer: usize,
+    pub mask: SignalFlags,
+}
+
+impl Default for SignalAction {
+    fn default() -> Self {
+        Self {
+            handler: SIG_DFL,
+            restorer: 0,
+            mask: SignalFlags::empty(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct SignalActions {
+    pub table: [SignalAction; MAX_SIG + 1],
+}
+
+impl Default for SignalActions {
+    fn default() -> Self {
+        Self {
+            table: [SignalAction::default(); MAX_SIG + 1],
+        }
+    }
+}
+
+impl SignalActions {
+    /// Caught signals are reset to their default actions by exec, ignored ones stay ignored.
+    pub fn reset_handlers(&mut self) {
+        for action in self.table.iter_mut() {
+            if action.handler != SIG_IGN {
+                *action = SignalAction::default();
+            }
+        }
+    }
+}
+
+/// Saved on the user stack before entering a handler and restored by sigreturn.
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct SignalFrame {
+    pub x: [usize; 32],
+    pub sepc: usize,
+    pub mask: SignalFlags,
+}
+
+impl SignalFrame {
+    pub fn new(trap_cx: &TrapContext, mask: SignalFlags) -> Self {
+        Self {
+            x: trap_cx.x,
+            sepc: trap_cx.sepc,
+            mask,
+        }
+    }
+    pub fn empty() -> Self {
+        Self {
+            x: [0; 32],
+            sepc: 0,
+            mask: SignalFlags::empty(),
+        }
+    }
+}
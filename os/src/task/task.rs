@@ -1,39 +1,49 @@
-use crate::mm::{
-    MemorySet,
-    PhysPageNum,
-    KERNEL_SPACE, 
-    VirtAddr,
-    translated_refmut,
-};
-use crate::trap::{TrapContext, trap_handler};
-use crate::config::{TRAP_CONTEXT};
+use crate::mm::PhysPageNum;
+use crate::trap::TrapContext;
+use crate::config::DEFAULT_PRIORITY;
+use crate::error::SysResult;
 use super::TaskContext;
-use super::{PidHandle, pid_alloc, KernelStack};
+use super::{KernelStack, kstack_alloc, TaskUserRes, ProcessControlBlock};
+use super::manager::{register_task, unregister_task};
 use alloc::sync::{Weak, Arc};
-use alloc::vec;
-use alloc::vec::Vec;
-use alloc::string::String;
+use core::ops::AddAssign;
+use core::sync::atomic::AtomicBool;
 use spin::{Mutex, MutexGuard};
-use crate::fs::{File, Stdin, Stdout};
 
+/// A thread, sharing the address space and resources of its process.
 pub struct TaskControlBlock {
     // immutable
-    pub pid: PidHandle,
+    pub process: Weak<ProcessControlBlock>,
     pub kernel_stack: KernelStack,
+    /// Set while a hart runs on the kernel stack of the thread, which no other
+    /// hart may switch to before the context has been saved.
+    pub on_cpu: AtomicBool,
     // mutable
     inner: Mutex<TaskControlBlockInner>,
 }
 
 pub struct TaskControlBlockInner {
+    pub res: TaskUserRes,
     pub trap_cx_ppn: PhysPageNum,
-    pub base_size: usize,
     pub task_cx_ptr: usize,
     pub task_status: TaskStatus,
-    pub memory_set: MemorySet,
-    pub parent: Option<Weak<TaskControlBlock>>,
-    pub children: Vec<Arc<TaskControlBlock>>,
-    pub exit_code: i32,
-    pub fd_table: Vec<Option<Arc<dyn File + Send + Sync>>>,
+    pub exit_code: Option<i32>,
+    /// A thread with a higher priority runs more often under the stride scheduler.
+    pub priority: usize,
+    /// Stride scheduling picks the ready thread with the lowest pass, which
+    /// goes up by a stride inversely proportional to the priority each time.
+    pub pass: u64,
+    /// Queue of the thread under the MLFQ scheduler, 0 being the highest.
+    pub level: usize,
+    /// Timer ticks the thread has run for in its current time slice.
+    pub slice_used: usize,
+    /// A wakeup came while the thread was still running, e.g. on another hart
+    /// before it blocked, so the next block returns at once.
+    pub wakeup_pending: bool,
+    pub times: CpuTimes,
+    /// `get_time` when the thread last entered or left user space, or was
+    /// switched to; the time since then is charged at the next of these.
+    pub time_stamp: usize,
 }
 
 impl TaskControlBlockInner {
@@ -43,23 +53,18 @@ impl TaskControlBlockInner {
     pub fn get_trap_cx(&self) -> &'static mut TrapContext {
         self.trap_cx_ppn.get_mut()
     }
-    pub fn get_user_token(&self) -> usize {
-        self.memory_set.token()
-    }
-    fn get_status(&self) -> TaskStatus {
-        self.task_status
-    }
     pub fn is_zombie(&self) -> bool {
-        self.get_status() == TaskStatus::Zombie
+        self.task_status == TaskStatus::Zombie
     }
-    pub fn alloc_fd(&mut self) -> usize {
-        if let Some(fd) = (0..self.fd_table.len())
-            .find(|fd| self.fd_table[*fd].is_none()) {
-            fd
+    /// Charge the time since the last stamp to user or kernel mode.
+    pub fn account_time(&mut self, now: usize, in_user: bool) {
+        let elapsed = now.saturating_sub(self.time_stamp);
+        if in_user {
+            self.times.user += elapsed;
         } else {
-            self.fd_table.push(None);
-            self.fd_table.len() - 1
+            self.times.kernel += elapsed;
         }
+        self.time_stamp = now;
     }
 }
 
@@ -67,164 +72,72 @@ impl TaskControlBlock {
     pub fn acquire_inner_lock(&self) -> MutexGuard<TaskControlBlockInner> {
         self.inner.lock()
     }
-    pub fn new(elf_data: &[u8]) -> Self {
-        // memory_set with elf program headers/trampoline/trap context/user stack
-        let (memory_set, user_sp, entry_point) = MemorySet::from_elf(elf_data);
-        let trap_cx_ppn = memory_set
-            .translate(VirtAddr::from(TRAP_CONTEXT).into())
-            .unwrap()
-            .ppn();
-        // alloc a pid and a kernel stack in kernel space
-        let pid_handle = pid_alloc();
-        let kernel_stack = KernelStack::new(&pid_handle);
-        let kernel_stack_top = kernel_stack.get_top();
+    /// A new thread of `process`, its user stack and trap context are mapped
+    /// unless they exist already, as in a forked address space.
+    /// Fails with ENOMEM if there are no frames for them or the kernel stack,
+    /// the caller must not hold the lock of `process`.
+    pub fn new(process: Arc<ProcessControlBlock>, alloc_user_res: bool) -> SysResult<Arc<Self>> {
+        let res = TaskUserRes::new(Arc::clone(&process), alloc_user_res)?;
+        let trap_cx_ppn = res.trap_cx_ppn();
+        let kernel_stack = kstack_alloc()?;
         // push a task context which goes to trap_return to the top of kernel stack
         let task_cx_ptr = kernel_stack.push_on_top(TaskContext::goto_trap_return());
-        let task_control_block = Self {
-            pid: pid_handle,
+        let task = Arc::new(Self {
+            process: Arc::downgrade(&process),
             kernel_stack,
+            on_cpu: AtomicBool::new(false),
             inner: Mutex::new(TaskControlBlockInner {
+                res,
                 trap_cx_ppn,
-                base_size: user_sp,
                 task_cx_ptr: task_cx_ptr as usize,
                 task_status: TaskStatus::Ready,
-                memory_set,
-                parent: None,
-                children: Vec::new(),
-                exit_code: 0,
-                fd_table: vec![
-                    // 0 -> stdin
-                    Some(Arc::new(Stdin)),
-                    // 1 -> stdout
-                    Some(Arc::new(Stdout)),
-                    // 2 -> stderr
-                    Some(Arc::new(Stdout)),
-                ],
-            }),
-        };
-        // prepare TrapContext in user space
-        let trap_cx = task_control_block.acquire_inner_lock().get_trap_cx();
-        *trap_cx = TrapContext::app_init_context(
-            entry_point,
-            user_sp,
-            KERNEL_SPACE.lock().token(),
-            kernel_stack_top,
-            trap_handler as usize,
-        );
-        task_control_block
-    }
-    pub fn exec(&self, elf_data: &[u8], args: Vec<String>) {
-        // memory_set with elf program headers/trampoline/trap context/user stack
-        let (memory_set, mut user_sp, entry_point) = MemorySet::from_elf(elf_data);
-        let trap_cx_ppn = memory_set
-            .translate(VirtAddr::from(TRAP_CONTEXT).into())
-            .unwrap()
-            .ppn();
-        // push arguments on user stack
-        user_sp -= (args.len() + 1) * core::mem::size_of::<usize>();
-        let argv_base = user_sp;
-        let mut argv: Vec<_> = (0..=args.len())
-            .map(|arg| {
-                translated_refmut(
-                    memory_set.token(),
-                    (argv_base + arg * core::mem::size_of::<usize>()) as *mut usize
-                )
-            })
-            .collect();
-        *argv[args.len()] = 0;
-        for i in 0..args.len() {
-            user_sp -= args[i].len() + 1;
-            *argv[i] = user_sp;
-            let mut p = user_sp;
-            for c in args[i].as_bytes() {
-                *translated_refmut(memory_set.token(), p as *mut u8) = *c;
-                p += 1;
-            }
-            *translated_refmut(memory_set.token(), p as *mut u8) = 0;
-        }
-        // make the user_sp aligned to 8B for k210 platform
-        user_sp -= user_sp % core::mem::size_of::<usize>();
-
-        // **** hold current PCB lock
-        let mut inner = self.acquire_inner_lock();
-        // substitute memory_set
-        inner.memory_set = memory_set;
-        // update trap_cx ppn
-        inner.trap_cx_ppn = trap_cx_ppn;
-        // initialize trap_cx
-        let mut trap_cx = TrapContext::app_init_context(
-            entry_point,
-            user_sp,
-            KERNEL_SPACE.lock().token(),
-            self.kernel_stack.get_top(),
-            trap_handler as usize,
-        );
-        trap_cx.x[10] = args.len();
-        trap_cx.x[11] = argv_base;
-        *inner.get_trap_cx() = trap_cx;
-        // **** release current PCB lock
-    }
-    pub fn fork(self: &Arc<TaskControlBlock>) -> Arc<TaskControlBlock> {
-        // ---- hold parent PCB lock
-        let mut parent_inner = self.acquire_inner_lock();
-        // copy user space(include trap context)
-        let memory_set = MemorySet::from_existed_user(
-            &parent_inner.memory_set
-        );
-        let trap_cx_ppn = memory_set
-            .translate(VirtAddr::from(TRAP_CONTEXT).into())
-            .unwrap()
-            .ppn();
-        // alloc a pid and a kernel stack in kernel space
-        let pid_handle = pid_alloc();
-        let kernel_stack = KernelStack::new(&pid_handle);
-        let kernel_stack_top = kernel_stack.get_top();
-        // push a goto_trap_return task_cx on the top of kernel stack
-        let task_cx_ptr = kernel_stack.push_on_top(TaskContext::goto_trap_return());
-        // copy fd table
-        let mut new_fd_table: Vec<Option<Arc<dyn File + Send + Sync>>> = Vec::new();
-        for fd in parent_inner.fd_table.iter() {
-            if let Some(file) = fd {
-                new_fd_table.push(Some(file.clone()));
-            } else {
-                new_fd_table.push(None);
-            }
-        }
-        let task_control_block = Arc::new(TaskControlBlock {
-            pid: pid_handle,
-            kernel_stack,
-            inner: Mutex::new(TaskControlBlockInner {
-                trap_cx_ppn,
-                base_size: parent_inner.base_size,
-                task_cx_ptr: task_cx_ptr as usize,
-                task_status: TaskStatus::Ready,
-                memory_set,
-                parent: Some(Arc::downgrade(self)),
-                children: Vec::new(),
-                exit_code: 0,
-                fd_table: new_fd_table,
+                exit_code: None,
+                priority: DEFAULT_PRIORITY,
+                pass: 0,
+                level: 0,
+                slice_used: 0,
+                wakeup_pending: false,
+                times: CpuTimes::default(),
+                time_stamp: 0,
             }),
         });
-        // add child
-        parent_inner.children.push(task_control_block.clone());
-        // modify kernel_sp in trap_cx
-        // **** acquire child PCB lock
-        let trap_cx = task_control_block.acquire_inner_lock().get_trap_cx();
-        // **** release child PCB lock
-        trap_cx.kernel_sp = kernel_stack_top;
-        // return
-        task_control_block
-        // ---- release parent PCB lock
+        register_task(&task);
+        Ok(task)
+    }
+    pub fn gettid(&self) -> usize {
+        self.acquire_inner_lock().res.tid
     }
-    pub fn getpid(&self) -> usize {
-        self.pid.0
+    pub fn get_user_token(&self) -> usize {
+        let process = self.process.upgrade().unwrap();
+        let token = process.acquire_inner_lock().get_user_token();
+        token
     }
+}
 
+impl Drop for TaskControlBlock {
+    fn drop(&mut self) {
+        unregister_task(self as *const Self as usize);
+    }
 }
 
 #[derive(Copy, Clone, PartialEq)]
 pub enum TaskStatus {
     Ready,
     Running,
+    Blocked,
     Zombie,
-}
\ No newline at end of file
+}
+
+/// CPU time spent in user and kernel mode, in cycles of `get_time`.
+#[derive(Copy, Clone, Default)]
+pub struct CpuTimes {
+    pub user: usize,
+    pub kernel: usize,
+}
+
+impl AddAssign for CpuTimes {
+    fn add_assign(&mut self, other: Self) {
+        self.user += other.user;
+        self.kernel += other.kernel;
+    }
+}
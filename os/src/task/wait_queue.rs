@@ -0,0 +1,72 @@
+use super::TaskControlBlock;
+use super::{current_task, block_current_and_run_next, wakeup_task};
+use alloc::collections::VecDeque;
+use alloc::sync::Arc;
+use spin::Mutex;
+
+/// Tasks blocked until some event happens.
+/// A task may be woken for other reasons as well (e.g. a signal), so the
+/// waiter should always check its condition again after `wait` returns.
+pub struct WaitQueue {
+    inner: Mutex<WaitQueueInner>,
+}
+
+struct WaitQueueInner {
+    tasks: VecDeque<Arc<TaskControlBlock>>,
+    /// Number of wakeups so far, which tickets are taken from.
+    wakeups: usize,
+}
+
+impl WaitQueue {
+    pub fn new() -> Self {
+        Self {
+            inner: Mutex::new(WaitQueueInner {
+                tasks: VecDeque::new(),
+                wakeups: 0,
+            }),
+        }
+    }
+    /// Taken before checking the condition to wait for. Another hart may
+    /// wake the queue after the check but before the task is on the queue,
+    /// the ticket makes `wait` return at once in that case.
+    pub fn ticket(&self) -> usize {
+        self.inner.lock().wakeups
+    }
+    /// Block the current task on this queue unless woken since `ticket`.
+    pub fn wait(&self, ticket: usize) {
+        let task = current_task().unwrap();
+        let mut inner = self.inner.lock();
+        if inner.wakeups != ticket {
+            return;
+        }
+        inner.tasks.push_back(task.clone());
+        drop(inner);
+        block_current_and_run_next();
+        // woken by someone else (e.g. a signal), do not leave a stale entry
+        self.inner.lock().tasks.retain(|waiter| !Arc::ptr_eq(waiter, &task));
+    }
+    /// Wake the first task which is still blocked, return whether there was one.
+    pub fn wake_one(&self) -> bool {
+        let mut inner = self.inner.lock();
+        inner.wakeups = inner.wakeups.wrapping_add(1);
+        drop(inner);
+        loop {
+            let task = match self.inner.lock().tasks.pop_front() {
+                Some(task) => task,
+                None => return false,
+            };
+            if wakeup_task(task) {
+                return true;
+            }
+        }
+    }
+    pub fn wake_all(&self) {
+        let mut inner = self.inner.lock();
+        inner.wakeups = inner.wakeups.wrapping_add(1);
+        let tasks = core::mem::take(&mut inner.tasks);
+        drop(inner);
+        for task in tasks {
+            wakeup_task(task);
+        }
+    }
+}
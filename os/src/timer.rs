@@ -1,9 +1,17 @@
 use riscv::register::time;
 use crate::sbi::set_timer;
 use crate::config::CLOCK_FREQ;
+use crate::task::{TaskControlBlock, wakeup_task};
+use alloc::collections::BinaryHeap;
+use alloc::sync::Arc;
+use core::cmp::Ordering;
+use spin::Mutex;
+use lazy_static::*;
 
-const TICKS_PER_SEC: usize = 100;
+pub const TICKS_PER_SEC: usize = 100;
 const MSEC_PER_SEC: usize = 1000;
+pub const NSEC_PER_SEC: usize = 1_000_000_000;
+const USEC_PER_SEC: usize = 1_000_000;
 
 pub fn get_time() -> usize {
     time::read()
@@ -13,6 +21,115 @@ pub fn get_time_ms() -> usize {
     time::read() / (CLOCK_FREQ / MSEC_PER_SEC)
 }
 
+/// The `get_time` value one timer tick from now.
+pub fn next_tick_time() -> usize {
+    get_time() + CLOCK_FREQ / TICKS_PER_SEC
+}
+
+/// Convert cycles of `get_time` to timer ticks, which are the clock ticks of `times`.
+pub fn cycles_to_ticks(cycles: usize) -> usize {
+    cycles / (CLOCK_FREQ / TICKS_PER_SEC)
+}
+
 pub fn set_next_trigger() {
-    set_timer(get_time() + CLOCK_FREQ / TICKS_PER_SEC);
-}
\ No newline at end of file
+    set_timer(next_tick_time());
+}
+
+/// Same layout as `struct timespec` in Linux.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default)]
+pub struct TimeSpec {
+    pub sec: usize,
+    pub nsec: usize,
+}
+
+impl TimeSpec {
+    pub fn is_valid(&self) -> bool {
+        self.nsec < NSEC_PER_SEC
+    }
+    /// Convert to the unit of `get_time`, saturating at `usize::MAX`,
+    /// which is never reached. `nsec` must be valid.
+    pub fn to_cycles(&self) -> usize {
+        self.sec
+            .saturating_mul(CLOCK_FREQ)
+            .saturating_add(self.nsec * CLOCK_FREQ / NSEC_PER_SEC)
+    }
+    pub fn from_cycles(cycles: usize) -> Self {
+        Self {
+            sec: cycles / CLOCK_FREQ,
+            nsec: cycles % CLOCK_FREQ * NSEC_PER_SEC / CLOCK_FREQ,
+        }
+    }
+}
+
+/// Same layout as `struct timeval` in Linux.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default)]
+pub struct TimeVal {
+    pub sec: usize,
+    pub usec: usize,
+}
+
+impl TimeVal {
+    pub fn from_cycles(cycles: usize) -> Self {
+        Self {
+            sec: cycles / CLOCK_FREQ,
+            usec: cycles % CLOCK_FREQ * USEC_PER_SEC / CLOCK_FREQ,
+        }
+    }
+}
+
+/// A sleeping task and the `get_time` value when it should be woken up.
+pub struct TimerCondVar {
+    pub expire: usize,
+    pub task: Arc<TaskControlBlock>,
+}
+
+impl PartialEq for TimerCondVar {
+    fn eq(&self, other: &Self) -> bool {
+        self.expire == other.expire
+    }
+}
+impl Eq for TimerCondVar {}
+impl PartialOrd for TimerCondVar {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+impl Ord for TimerCondVar {
+    /// Reversed, so that `BinaryHeap` pops the earliest deadline first.
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.expire.cmp(&self.expire)
+    }
+}
+
+lazy_static! {
+    static ref TIMERS: Mutex<BinaryHeap<TimerCondVar>> = Mutex::new(BinaryHeap::new());
+}
+
+pub fn add_timer(expire: usize, task: Arc<TaskControlBlock>) {
+    TIMERS.lock().push(TimerCondVar { expire, task });
+}
+
+/// Drop the timers of a task woken up before its deadline.
+pub fn remove_timer(task: &Arc<TaskControlBlock>) {
+    let mut timers = TIMERS.lock();
+    let remained: BinaryHeap<TimerCondVar> = core::mem::take(&mut *timers)
+        .into_iter()
+        .filter(|timer| !Arc::ptr_eq(&timer.task, task))
+        .collect();
+    *timers = remained;
+}
+
+/// Wake up every task whose deadline has passed.
+pub fn check_timer() {
+    let current = get_time();
+    let mut timers = TIMERS.lock();
+    while let Some(timer) = timers.peek() {
+        if timer.expire > current {
+            break;
+        }
+        let timer = timers.pop().unwrap();
+        wakeup_task(timer.task);
+    }
+}
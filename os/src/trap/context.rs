@@ -9,6 +9,9 @@ pub struct TrapContext {
     pub kernel_satp: usize,
     pub kernel_sp: usize,
     pub trap_handler: usize,
+    /// Loaded into `tp` on a trap, set on each return to user space
+    /// since the thread may have moved to another hart.
+    pub hart_id: usize,
 }
 
 impl TrapContext {
@@ -30,6 +33,7 @@ impl TrapContext {
             kernel_satp,
             kernel_sp,
             trap_handler,
+            hart_id: 0,
         };
         cx.set_sp(sp);
         cx
@@ -14,13 +14,23 @@ use riscv::register::{
 };
 use crate::syscall::syscall;
 use crate::task::{
-    exit_current_and_run_next,
-    suspend_current_and_run_next,
+    tick_current_and_run_next,
     current_user_token,
     current_trap_cx,
+    current_trap_cx_user_va,
+    current_add_fault_signal,
+    current_process,
+    handle_signals,
+    exit_current_if_killed,
+    account_current_time,
+    SignalFlags,
 };
-use crate::timer::set_next_trigger;
-use crate::config::{TRAP_CONTEXT, TRAMPOLINE};
+use crate::smp::{hart_id, enter_user, leave_user, handle_ipi};
+use crate::timer::{set_next_trigger, check_timer};
+use crate::fs::poll_console;
+use crate::config::TRAMPOLINE;
+use crate::mm::{VirtAddr, MapPermission};
+use crate::error::SysError;
 
 global_asm!(include_str!("trap.S"));
 
@@ -44,22 +54,37 @@ pub fn enable_timer_interrupt() {
     unsafe { sie::set_stimer(); }
 }
 
+/// Other harts send IPIs for TLB shootdowns.
+pub fn enable_software_interrupt() {
+    unsafe { sie::set_ssoft(); }
+}
+
 #[no_mangle]
 pub fn trap_handler() -> ! {
     set_kernel_trap_entry();
+    leave_user();
+    account_current_time(true);
     let scause = scause::read();
     let stval = stval::read();
+    // another thread has exited the process while this one was in user space
+    exit_current_if_killed();
     match scause.cause() {
         Trap::Exception(Exception::UserEnvCall) => {
             // jump to next instruction anyway
             let mut cx = current_trap_cx();
             cx.sepc += 4;
             // get system call return value
-            let result = syscall(cx.x[17], [cx.x[10], cx.x[11], cx.x[12]]);
-            // cx is changed during sys_exec, so we have to call it again
+            let result = syscall(cx.x[17], [cx.x[10], cx.x[11], cx.x[12], cx.x[13], cx.x[14], cx.x[15]]);
+            // cx is changed during sys_execve, so we have to call it again
             cx = current_trap_cx();
             cx.x[10] = result as usize;
         }
+        Trap::Exception(Exception::StorePageFault) |
+        Trap::Exception(Exception::InstructionPageFault) |
+        Trap::Exception(Exception::LoadPageFault) if handle_page_fault(scause.cause(), stval) => {
+            // the page has been loaded on first touch, read back from swap
+            // or copied on first store
+        }
         Trap::Exception(Exception::StoreFault) |
         Trap::Exception(Exception::StorePageFault) |
         Trap::Exception(Exception::InstructionFault) |
@@ -67,22 +92,25 @@ pub fn trap_handler() -> ! {
         Trap::Exception(Exception::LoadFault) |
         Trap::Exception(Exception::LoadPageFault) => {
             println!(
-                "[kernel] {:?} in application, bad addr = {:#x}, bad instruction = {:#x}, core dumped.",
+                "[kernel] {:?} in application, bad addr = {:#x}, bad instruction = {:#x}, SIGSEGV.",
                 scause.cause(),
                 stval,
                 current_trap_cx().sepc,
             );
-            // page fault exit code
-            exit_current_and_run_next(-2);
+            current_add_fault_signal(SignalFlags::SIGSEGV);
         }
         Trap::Exception(Exception::IllegalInstruction) => {
-            println!("[kernel] IllegalInstruction in application, core dumped.");
-            // illegal instruction exit code
-            exit_current_and_run_next(-3);
+            println!("[kernel] IllegalInstruction in application, SIGILL.");
+            current_add_fault_signal(SignalFlags::SIGILL);
         }
         Trap::Interrupt(Interrupt::SupervisorTimer) => {
             set_next_trigger();
-            suspend_current_and_run_next();
+            check_timer();
+            poll_console();
+            tick_current_and_run_next();
+        }
+        Trap::Interrupt(Interrupt::SupervisorSoft) => {
+            handle_ipi();
         }
         _ => {
             panic!("Unsupported trap {:?}, stval = {:#x}!", scause.cause(), stval);
@@ -92,11 +120,40 @@ pub fn trap_handler() -> ! {
     trap_return();
 }
 
+/// Let the address space of the current process resolve a page fault at `addr`,
+/// returns false if it is a real violation. The process is killed if it is
+/// out of memory, there being no frame for the page.
+fn handle_page_fault(cause: Trap, addr: usize) -> bool {
+    let access = match cause {
+        Trap::Exception(Exception::StorePageFault) => MapPermission::W,
+        Trap::Exception(Exception::InstructionPageFault) => MapPermission::X,
+        _ => MapPermission::R,
+    };
+    let result = current_process()
+        .acquire_inner_lock()
+        .memory_set
+        .handle_page_fault(VirtAddr::from(addr).floor(), access);
+    match result {
+        Ok(()) => true,
+        Err(SysError::ENOMEM) => {
+            println!("[kernel] Out of memory in application, addr = {:#x}, SIGKILL.", addr);
+            current_add_fault_signal(SignalFlags::SIGKILL);
+            true
+        }
+        Err(_) => false,
+    }
+}
+
 #[no_mangle]
 pub fn trap_return() -> ! {
+    exit_current_if_killed();
+    handle_signals();
     set_user_trap_entry();
-    let trap_cx_ptr = TRAP_CONTEXT;
+    let trap_cx_ptr = current_trap_cx_user_va();
     let user_satp = current_user_token();
+    current_trap_cx().hart_id = hart_id();
+    account_current_time(false);
+    enter_user(user_satp);
     extern "C" {
         fn __alltraps();
         fn __restore();
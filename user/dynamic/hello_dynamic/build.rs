@@ -0,0 +1,15 @@
+use std::env;
+
+fn main() {
+    let manifest_dir = env::var("CARGO_MANIFEST_DIR").unwrap();
+    let profile = env::var("PROFILE").unwrap();
+    // libuser.so is linked by the Makefile before we are built, rustc
+    // links statically for this target unless told otherwise
+    println!("cargo:rustc-link-arg=-pie");
+    println!("cargo:rustc-link-arg=--dynamic-linker=ld.so");
+    println!("cargo:rustc-link-arg=-Bdynamic");
+    println!(
+        "cargo:rustc-link-arg={}/../target/riscv64gc-unknown-none-elf/{}/libuser.so",
+        manifest_dir, profile
+    );
+}
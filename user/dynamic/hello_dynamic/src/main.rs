@@ -0,0 +1,37 @@
+#![no_std]
+#![no_main]
+
+use core::panic::PanicInfo;
+
+// resolved in libuser.so by ld.so
+extern "C" {
+    fn puts(s: *const u8) -> isize;
+    fn getpid() -> isize;
+    fn exit(exit_code: i32) -> !;
+}
+
+/// Pointers in data are fixed up by R_RISCV_RELATIVE relocations, as we
+/// are loaded at an address we do not know at link time.
+static GREETINGS: [&str; 2] = [
+    "Hello from a dynamically linked program!\0",
+    "libuser.so has been loaded by ld.so.\0",
+];
+
+/// Exits with argc, so that a parent can tell that argv made it here.
+#[no_mangle]
+pub extern "C" fn _start(argc: usize, argv: *const *const u8) -> ! {
+    unsafe {
+        for greeting in GREETINGS.iter() {
+            puts(greeting.as_ptr());
+        }
+        assert!(getpid() > 0);
+        assert!(argc > 0 && !(*argv).is_null());
+        puts(*argv);
+        exit(argc as i32)
+    }
+}
+
+#[panic_handler]
+fn panic_handler(_panic_info: &PanicInfo) -> ! {
+    unsafe { exit(-1) }
+}
@@ -0,0 +1,5 @@
+fn main() {
+    // a static position-independent executable, which relocates itself
+    println!("cargo:rustc-link-arg=-pie");
+    println!("cargo:rustc-link-arg=--no-dynamic-linker");
+}
@@ -0,0 +1,104 @@
+#![allow(unused)]
+
+// The parts of the elf format a dynamic loader needs, for 64-bit files.
+
+pub const EHDR_SIZE: usize = 64;
+pub const E_TYPE_OFFSET: usize = 16;
+pub const E_MACHINE_OFFSET: usize = 18;
+pub const E_PHOFF_OFFSET: usize = 32;
+pub const E_PHENTSIZE_OFFSET: usize = 54;
+pub const E_PHNUM_OFFSET: usize = 56;
+pub const ELF_MAGIC: [u8; 4] = [0x7f, b'E', b'L', b'F'];
+pub const ET_DYN: u16 = 3;
+pub const EM_RISCV: u16 = 243;
+
+pub const PT_LOAD: u32 = 1;
+pub const PT_DYNAMIC: u32 = 2;
+pub const PT_PHDR: u32 = 6;
+pub const PF_X: u32 = 1;
+pub const PF_R: u32 = 4;
+
+pub const DT_NULL: i64 = 0;
+pub const DT_NEEDED: i64 = 1;
+pub const DT_PLTRELSZ: i64 = 2;
+pub const DT_HASH: i64 = 4;
+pub const DT_STRTAB: i64 = 5;
+pub const DT_SYMTAB: i64 = 6;
+pub const DT_RELA: i64 = 7;
+pub const DT_RELASZ: i64 = 8;
+pub const DT_JMPREL: i64 = 23;
+
+pub const R_RISCV_NONE: u32 = 0;
+pub const R_RISCV_64: u32 = 2;
+pub const R_RISCV_RELATIVE: u32 = 3;
+pub const R_RISCV_JUMP_SLOT: u32 = 5;
+
+pub const SHN_UNDEF: u16 = 0;
+pub const STB_LOCAL: u8 = 0;
+
+pub const AT_NULL: usize = 0;
+pub const AT_PHDR: usize = 3;
+pub const AT_PHNUM: usize = 5;
+pub const AT_BASE: usize = 7;
+pub const AT_ENTRY: usize = 9;
+
+#[repr(C)]
+#[derive(Copy, Clone, Default)]
+pub struct Phdr {
+    pub p_type: u32,
+    pub p_flags: u32,
+    pub p_offset: u64,
+    pub p_vaddr: u64,
+    pub p_paddr: u64,
+    pub p_filesz: u64,
+    pub p_memsz: u64,
+    pub p_align: u64,
+}
+
+#[repr(C)]
+pub struct Dyn {
+    pub d_tag: i64,
+    pub d_val: u64,
+}
+
+#[repr(C)]
+pub struct Rela {
+    pub r_offset: u64,
+    pub r_info: u64,
+    pub r_addend: i64,
+}
+
+impl Rela {
+    pub fn sym(&self) -> usize {
+        (self.r_info >> 32) as usize
+    }
+    pub fn kind(&self) -> u32 {
+        self.r_info as u32
+    }
+}
+
+#[repr(C)]
+pub struct Sym {
+    pub st_name: u32,
+    pub st_info: u8,
+    pub st_other: u8,
+    pub st_shndx: u16,
+    pub st_value: u64,
+    pub st_size: u64,
+}
+
+impl Sym {
+    pub fn binding(&self) -> u8 {
+        self.st_info >> 4
+    }
+}
+
+pub fn read_u16(data: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([data[offset], data[offset + 1]])
+}
+
+pub fn read_u64(data: &[u8], offset: usize) -> u64 {
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(&data[offset..offset + 8]);
+    u64::from_le_bytes(bytes)
+}
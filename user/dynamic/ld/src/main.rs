@@ -0,0 +1,426 @@
+#![no_std]
+#![no_main]
+#![feature(llvm_asm)]
+#![feature(global_asm)]
+
+mod elf;
+
+use core::mem::size_of;
+use core::panic::PanicInfo;
+use core::slice;
+use elf::*;
+
+const SYSCALL_OPEN: usize = 56;
+const SYSCALL_CLOSE: usize = 57;
+const SYSCALL_READ: usize = 63;
+const SYSCALL_WRITE: usize = 64;
+const SYSCALL_EXIT: usize = 93;
+const SYSCALL_MUNMAP: usize = 215;
+const SYSCALL_MMAP: usize = 222;
+
+const PROT_READ: usize = 1 << 0;
+const PROT_WRITE: usize = 1 << 1;
+const PROT_EXEC: usize = 1 << 2;
+
+const STDERR: usize = 2;
+const PAGE_SIZE: usize = 0x1000;
+/// The program and the shared libraries it needs.
+const MAX_OBJECTS: usize = 8;
+/// Program headers of a shared library.
+const MAX_PHNUM: usize = 16;
+
+// The kernel starts us with the stack it has laid out for the program,
+// which is handed over as is once the libraries are loaded. Only sp is
+// relied on, so the program gets argc and argv in a0 and a1 again.
+global_asm!(r#"
+    .section .text.entry
+    .globl _start
+_start:
+    mv s0, sp
+    mv a0, sp
+    call loader_main
+    mv t0, a0
+    mv sp, s0
+    ld a0, 0(sp)
+    addi a1, sp, 8
+    jr t0
+"#);
+
+fn syscall(id: usize, args: [usize; 3]) -> isize {
+    let mut ret: isize;
+    unsafe {
+        llvm_asm!("ecall"
+            : "={x10}" (ret)
+            : "{x10}" (args[0]), "{x11}" (args[1]), "{x12}" (args[2]), "{x17}" (id)
+            : "memory"
+            : "volatile"
+        );
+    }
+    ret
+}
+
+fn open(path: *const u8) -> isize {
+    syscall(SYSCALL_OPEN, [path as usize, 0, 0])
+}
+
+fn close(fd: usize) {
+    syscall(SYSCALL_CLOSE, [fd, 0, 0]);
+}
+
+fn write(fd: usize, buf: &[u8]) {
+    syscall(SYSCALL_WRITE, [fd, buf.as_ptr() as usize, buf.len()]);
+}
+
+fn exit(exit_code: i32) -> ! {
+    syscall(SYSCALL_EXIT, [exit_code as usize, 0, 0]);
+    unreachable!()
+}
+
+/// Read exactly `len` bytes to `buf`, false on an error or at the end of the file.
+fn read_exact(fd: usize, buf: *mut u8, len: usize) -> bool {
+    let mut done = 0;
+    while done < len {
+        let ret = syscall(SYSCALL_READ, [fd, buf as usize + done, len - done]);
+        if ret <= 0 {
+            return false;
+        }
+        done += ret as usize;
+    }
+    true
+}
+
+/// There is no lseek, so the bytes in the way are read and dropped.
+fn skip(fd: usize, mut len: usize) -> bool {
+    let mut buffer = [0u8; 256];
+    while len > 0 {
+        let chunk = len.min(buffer.len());
+        if !read_exact(fd, buffer.as_mut_ptr(), chunk) {
+            return false;
+        }
+        len -= chunk;
+    }
+    true
+}
+
+fn page_floor(addr: usize) -> usize {
+    addr / PAGE_SIZE * PAGE_SIZE
+}
+
+fn page_ceil(addr: usize) -> usize {
+    (addr + PAGE_SIZE - 1) / PAGE_SIZE * PAGE_SIZE
+}
+
+/// The null-terminated string at `addr`.
+unsafe fn cstr(addr: usize) -> &'static [u8] {
+    let len = (0usize..).find(|i| *((addr + i) as *const u8) == 0).unwrap();
+    slice::from_raw_parts(addr as *const u8, len)
+}
+
+fn fail(message: &str, name: &[u8]) -> ! {
+    write(STDERR, b"ld.so: ");
+    write(STDERR, message.as_bytes());
+    write(STDERR, name);
+    write(STDERR, b"\n");
+    exit(127);
+}
+
+/// What we need to know from the auxiliary vector.
+struct AuxInfo {
+    phdr: usize,
+    phnum: usize,
+    base: usize,
+    entry: usize,
+}
+
+/// `sp` points to argc, followed by argv, envp and auxv.
+unsafe fn read_auxv(sp: *const usize) -> AuxInfo {
+    let argc = *sp;
+    let mut p = sp.add(1 + argc + 1);
+    while *p != 0 {
+        p = p.add(1);
+    }
+    p = p.add(1);
+    let mut info = AuxInfo { phdr: 0, phnum: 0, base: 0, entry: 0 };
+    while *p != AT_NULL {
+        let value = *p.add(1);
+        match *p {
+            AT_PHDR => info.phdr = value,
+            AT_PHNUM => info.phnum = value,
+            AT_BASE => info.base = value,
+            AT_ENTRY => info.entry = value,
+            _ => {}
+        }
+        p = p.add(2);
+    }
+    info
+}
+
+/// A loaded program or shared library and the tables of its dynamic section.
+#[derive(Copy, Clone, Default)]
+struct Object {
+    /// What the addresses in the file are relative to.
+    base: usize,
+    /// Address of the path it was loaded from, 0 for the program.
+    path: usize,
+    dynamic: usize,
+    strtab: usize,
+    symtab: usize,
+    /// Number of symbols, 0 without a hash table.
+    sym_count: usize,
+    rela: usize,
+    rela_size: usize,
+    jmprel: usize,
+    jmprel_size: usize,
+}
+
+impl Object {
+    /// Only the stack is written to, this also runs before we are relocated.
+    unsafe fn new(base: usize, dynamic: usize) -> Self {
+        let mut object = Self { base, dynamic, ..Self::default() };
+        let mut entry = dynamic as *const Dyn;
+        while (*entry).d_tag != DT_NULL {
+            let value = (*entry).d_val as usize;
+            // no match, a jump table would need relocations
+            let tag = (*entry).d_tag;
+            if tag == DT_STRTAB {
+                object.strtab = base + value;
+            } else if tag == DT_SYMTAB {
+                object.symtab = base + value;
+            } else if tag == DT_HASH {
+                // nbucket then nchain, which is the number of symbols
+                object.sym_count = *((base + value + 4) as *const u32) as usize;
+            } else if tag == DT_RELA {
+                object.rela = base + value;
+            } else if tag == DT_RELASZ {
+                object.rela_size = value;
+            } else if tag == DT_JMPREL {
+                object.jmprel = base + value;
+            } else if tag == DT_PLTRELSZ {
+                object.jmprel_size = value;
+            }
+            entry = entry.add(1);
+        }
+        object
+    }
+
+    /// Apply the R_RISCV_RELATIVE relocations in DT_RELA, which are all
+    /// we have ourselves and need no symbols.
+    unsafe fn relocate_relative(&self) {
+        let mut rela = self.rela;
+        while rela < self.rela + self.rela_size {
+            let entry = &*(rela as *const Rela);
+            if entry.kind() == R_RISCV_RELATIVE {
+                let addr = (self.base + entry.r_offset as usize) as *mut usize;
+                *addr = self.base.wrapping_add(entry.r_addend as usize);
+            }
+            rela += size_of::<Rela>();
+        }
+    }
+
+    /// Offsets in the string table of the libraries we need.
+    unsafe fn needed(&self) -> impl Iterator<Item = usize> {
+        let mut entry = self.dynamic as *const Dyn;
+        core::iter::from_fn(move || {
+            while (*entry).d_tag != DT_NULL {
+                let current = &*entry;
+                entry = entry.add(1);
+                if current.d_tag == DT_NEEDED {
+                    return Some(current.d_val as usize);
+                }
+            }
+            None
+        })
+    }
+
+    unsafe fn symbol(&self, idx: usize) -> &'static Sym {
+        &*((self.symtab + idx * size_of::<Sym>()) as *const Sym)
+    }
+
+    unsafe fn name(&self, sym: &Sym) -> &'static [u8] {
+        cstr(self.strtab + sym.st_name as usize)
+    }
+
+    /// Address of the global symbol `name`, if we define it.
+    unsafe fn lookup(&self, name: &[u8]) -> Option<usize> {
+        (1..self.sym_count)
+            .map(|idx| self.symbol(idx))
+            .find(|sym| {
+                sym.st_shndx != SHN_UNDEF && sym.binding() != STB_LOCAL && self.name(sym) == name
+            })
+            .map(|sym| self.base + sym.st_value as usize)
+    }
+
+    /// Apply the relocations in DT_RELA and DT_JMPREL, symbols being looked
+    /// up in `scope` in order, i.e. the program first.
+    unsafe fn relocate(&self, scope: &[Object]) {
+        for &(table, size) in [(self.rela, self.rela_size), (self.jmprel, self.jmprel_size)].iter() {
+            if size == 0 {
+                continue;
+            }
+            let entries = slice::from_raw_parts(table as *const Rela, size / size_of::<Rela>());
+            for entry in entries {
+                let addr = (self.base + entry.r_offset as usize) as *mut usize;
+                let addend = entry.r_addend as usize;
+                match entry.kind() {
+                    R_RISCV_NONE => {}
+                    R_RISCV_RELATIVE => *addr = self.base.wrapping_add(addend),
+                    R_RISCV_64 | R_RISCV_JUMP_SLOT => {
+                        let value = match entry.sym() {
+                            0 => 0,
+                            idx => {
+                                let sym = self.symbol(idx);
+                                if sym.st_shndx != SHN_UNDEF && sym.binding() == STB_LOCAL {
+                                    self.base + sym.st_value as usize
+                                } else {
+                                    let name = self.name(sym);
+                                    scope
+                                        .iter()
+                                        .find_map(|object| object.lookup(name))
+                                        .unwrap_or_else(|| fail("undefined symbol ", name))
+                                }
+                            }
+                        };
+                        *addr = value.wrapping_add(addend);
+                    }
+                    _ => fail("unsupported relocation in ", self.path_bytes()),
+                }
+            }
+        }
+    }
+
+    unsafe fn path_bytes(&self) -> &'static [u8] {
+        if self.path == 0 { b"the program" } else { cstr(self.path) }
+    }
+}
+
+/// Load the shared library at the null-terminated `path` where mmap finds
+/// room for it.
+unsafe fn load_library(path: usize) -> Object {
+    let name = cstr(path);
+    let fd = open(path as *const u8);
+    if fd < 0 {
+        fail("cannot find ", name);
+    }
+    let fd = fd as usize;
+    let mut ehdr = [0u8; EHDR_SIZE];
+    if !read_exact(fd, ehdr.as_mut_ptr(), EHDR_SIZE)
+        || ehdr[..4] != ELF_MAGIC
+        || read_u16(&ehdr, E_TYPE_OFFSET) != ET_DYN
+        || read_u16(&ehdr, E_MACHINE_OFFSET) != EM_RISCV {
+        fail("not a shared library: ", name);
+    }
+    let ph_offset = read_u64(&ehdr, E_PHOFF_OFFSET) as usize;
+    let ph_count = read_u16(&ehdr, E_PHNUM_OFFSET) as usize;
+    let mut phdrs = [Phdr::default(); MAX_PHNUM];
+    if ph_offset < EHDR_SIZE
+        || ph_count > MAX_PHNUM
+        || read_u16(&ehdr, E_PHENTSIZE_OFFSET) as usize != size_of::<Phdr>()
+        || !skip(fd, ph_offset - EHDR_SIZE)
+        || !read_exact(fd, phdrs.as_mut_ptr() as *mut u8, ph_count * size_of::<Phdr>()) {
+        fail("bad program headers in ", name);
+    }
+    close(fd);
+    let phdrs = &phdrs[..ph_count];
+    let loads = || phdrs.iter().filter(|ph| ph.p_type == PT_LOAD && ph.p_memsz > 0);
+    let low = loads().map(|ph| page_floor(ph.p_vaddr as usize)).min();
+    let high = loads().map(|ph| page_ceil((ph.p_vaddr + ph.p_memsz) as usize)).max();
+    let (low, high) = match (low, high) {
+        (Some(low), Some(high)) => (low, high),
+        _ => fail("nothing to load in ", name),
+    };
+    // find room for the whole library, then map the segments in it one by one
+    let room = syscall(SYSCALL_MMAP, [0, high - low, PROT_READ]);
+    if room < 0 {
+        fail("out of memory for ", name);
+    }
+    syscall(SYSCALL_MUNMAP, [room as usize, high - low, 0]);
+    let base = room as usize - low;
+    for ph in loads() {
+        let va = base + ph.p_vaddr as usize;
+        let start = page_floor(va);
+        let end = page_ceil(va + ph.p_memsz as usize);
+        // there is no mprotect, so the segments stay writable after they are read in
+        let mut prot = PROT_WRITE;
+        if ph.p_flags & PF_R != 0 { prot |= PROT_READ; }
+        if ph.p_flags & PF_X != 0 { prot |= PROT_EXEC; }
+        if syscall(SYSCALL_MMAP, [start, end - start, prot]) != start as isize {
+            fail("cannot map a segment of ", name);
+        }
+        // the rest of the segment is zeroed by mmap
+        let fd = open(path as *const u8);
+        if fd < 0
+            || !skip(fd as usize, ph.p_offset as usize)
+            || !read_exact(fd as usize, va as *mut u8, ph.p_filesz as usize) {
+            fail("cannot read ", name);
+        }
+        close(fd as usize);
+    }
+    let dynamic = match phdrs.iter().find(|ph| ph.p_type == PT_DYNAMIC) {
+        Some(ph) => base + ph.p_vaddr as usize,
+        None => fail("no dynamic section in ", name),
+    };
+    let mut object = Object::new(base, dynamic);
+    object.path = path;
+    object
+}
+
+/// We are linked at 0 and `__ehdr_start` is where we begin, it and
+/// `_DYNAMIC` are found pc-relative as nothing may be read from the GOT yet.
+#[inline(never)]
+unsafe fn relocate_self() {
+    let base: usize;
+    let dynamic: usize;
+    llvm_asm!("lla $0, __ehdr_start" : "=r" (base) ::: "volatile");
+    llvm_asm!("lla $0, _DYNAMIC" : "=r" (dynamic) ::: "volatile");
+    Object::new(base, dynamic).relocate_relative();
+}
+
+/// Load the libraries the program needs and link it with them, then
+/// return where the program starts.
+#[no_mangle]
+unsafe extern "C" fn loader_main(sp: *const usize) -> usize {
+    relocate_self();
+    let aux = read_auxv(sp);
+    if aux.base == 0 {
+        fail("run a program naming ld.so as its interpreter instead", b"");
+    }
+    let phdrs = slice::from_raw_parts(aux.phdr as *const Phdr, aux.phnum);
+    // a position-independent program is not where it is linked
+    let bias = phdrs
+        .iter()
+        .find(|ph| ph.p_type == PT_PHDR)
+        .map_or(0, |ph| aux.phdr - ph.p_vaddr as usize);
+    let dynamic = match phdrs.iter().find(|ph| ph.p_type == PT_DYNAMIC) {
+        Some(ph) => bias + ph.p_vaddr as usize,
+        None => return aux.entry,
+    };
+    let mut objects = [Object::default(); MAX_OBJECTS];
+    objects[0] = Object::new(bias, dynamic);
+    let mut count = 1;
+    // the libraries needed by the libraries are loaded as well, once each
+    let mut idx = 0;
+    while idx < count {
+        let object = objects[idx];
+        for offset in object.needed() {
+            let path = object.strtab + offset;
+            if objects[1..count].iter().any(|loaded| cstr(loaded.path) == cstr(path)) {
+                continue;
+            }
+            if count == MAX_OBJECTS {
+                fail("too many libraries, at ", cstr(path));
+            }
+            objects[count] = load_library(path);
+            count += 1;
+        }
+        idx += 1;
+    }
+    for object in objects[..count].iter() {
+        object.relocate(&objects[..count]);
+    }
+    aux.entry
+}
+
+#[panic_handler]
+fn panic_handler(_panic_info: &PanicInfo) -> ! {
+    fail("panicked", b"");
+}
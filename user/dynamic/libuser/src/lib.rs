@@ -0,0 +1,90 @@
+#![no_std]
+#![feature(llvm_asm)]
+
+use core::panic::PanicInfo;
+
+const SYSCALL_OPEN: usize = 56;
+const SYSCALL_CLOSE: usize = 57;
+const SYSCALL_READ: usize = 63;
+const SYSCALL_WRITE: usize = 64;
+const SYSCALL_EXIT: usize = 93;
+const SYSCALL_YIELD: usize = 124;
+const SYSCALL_GET_TIME: usize = 169;
+const SYSCALL_GETPID: usize = 172;
+
+const STDOUT: usize = 1;
+
+fn syscall(id: usize, args: [usize; 3]) -> isize {
+    let mut ret: isize;
+    unsafe {
+        llvm_asm!("ecall"
+            : "={x10}" (ret)
+            : "{x10}" (args[0]), "{x11}" (args[1]), "{x12}" (args[2]), "{x17}" (id)
+            : "memory"
+            : "volatile"
+        );
+    }
+    ret
+}
+
+// The functions below are what dynamically linked programs get from
+// libuser.so, with C linkage so that their names do not depend on the
+// compiler. They return the raw values of the syscalls, negative errnos
+// included.
+
+#[no_mangle]
+pub extern "C" fn open(path: *const u8, flags: u32) -> isize {
+    syscall(SYSCALL_OPEN, [path as usize, flags as usize, 0])
+}
+
+#[no_mangle]
+pub extern "C" fn close(fd: usize) -> isize {
+    syscall(SYSCALL_CLOSE, [fd, 0, 0])
+}
+
+#[no_mangle]
+pub extern "C" fn read(fd: usize, buf: *mut u8, len: usize) -> isize {
+    syscall(SYSCALL_READ, [fd, buf as usize, len])
+}
+
+#[no_mangle]
+pub extern "C" fn write(fd: usize, buf: *const u8, len: usize) -> isize {
+    syscall(SYSCALL_WRITE, [fd, buf as usize, len])
+}
+
+#[no_mangle]
+pub extern "C" fn exit(exit_code: i32) -> ! {
+    syscall(SYSCALL_EXIT, [exit_code as usize, 0, 0]);
+    unreachable!()
+}
+
+#[no_mangle]
+pub extern "C" fn yield_() -> isize {
+    syscall(SYSCALL_YIELD, [0, 0, 0])
+}
+
+#[no_mangle]
+pub extern "C" fn get_time() -> isize {
+    syscall(SYSCALL_GET_TIME, [0, 0, 0])
+}
+
+#[no_mangle]
+pub extern "C" fn getpid() -> isize {
+    syscall(SYSCALL_GETPID, [0, 0, 0])
+}
+
+/// Write the null-terminated string `s` and a newline to stdout.
+#[no_mangle]
+pub extern "C" fn puts(s: *const u8) -> isize {
+    let len = (0usize..).find(|i| unsafe { *s.add(*i) == 0 }).unwrap();
+    let ret = write(STDOUT, s, len);
+    if ret < 0 {
+        return ret;
+    }
+    write(STDOUT, b"\n".as_ptr(), 1)
+}
+
+#[panic_handler]
+fn panic_handler(_panic_info: &PanicInfo) -> ! {
+    exit(-1);
+}
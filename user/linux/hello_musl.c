@@ -0,0 +1,7 @@
+/* Built with a riscv64 musl gcc by `make linux`, run by linux_test. */
+#include <stdio.h>
+
+int main(int argc, char *argv[]) {
+    printf("Hello from musl, %s!\n", argc > 1 ? argv[1] : "world");
+    return 7;
+}
@@ -0,0 +1,85 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+extern crate alloc;
+
+use alloc::vec;
+use user_lib::{fork, exit, pipe, read, write, close, wait_exit_code};
+
+const LEN: usize = 4096;
+
+static mut DATA: [usize; LEN] = [0; LEN];
+
+/// Stores on either side of a fork are not seen by the other side.
+fn isolation_test() {
+    let mut heap = vec![1usize; LEN];
+    unsafe { DATA.iter_mut().for_each(|x| *x = 1); }
+    let pid = fork().unwrap();
+    if pid == 0 {
+        // the child sees the data of its parent and may change it
+        assert!(heap.iter().all(|x| *x == 1));
+        assert!(unsafe { DATA.iter().all(|x| *x == 1) });
+        heap.iter_mut().for_each(|x| *x = 2);
+        unsafe { DATA.iter_mut().for_each(|x| *x = 2); }
+        assert!(heap.iter().all(|x| *x == 2));
+        exit(0);
+    }
+    assert_eq!(wait_exit_code(pid), Ok(0));
+    assert!(heap.iter().all(|x| *x == 1));
+    assert!(unsafe { DATA.iter().all(|x| *x == 1) });
+    // the parent is left with the only reference and can write again
+    heap.iter_mut().for_each(|x| *x = 3);
+    assert!(heap.iter().all(|x| *x == 3));
+    println!("isolation test passed!");
+}
+
+/// The kernel writing into a shared page must not leak into the other process.
+fn kernel_write_test() {
+    let mut pipe_fd = [0usize; 2];
+    pipe(&mut pipe_fd).unwrap();
+    let mut buffer = [0u8; 16];
+    let pid = fork().unwrap();
+    if pid == 0 {
+        close(pipe_fd[1]).unwrap();
+        assert_eq!(read(pipe_fd[0], &mut buffer), Ok(5));
+        assert_eq!(&buffer[..5], b"hello");
+        exit(0);
+    }
+    close(pipe_fd[0]).unwrap();
+    assert_eq!(write(pipe_fd[1], b"hello"), Ok(5));
+    close(pipe_fd[1]).unwrap();
+    assert_eq!(wait_exit_code(pid), Ok(0));
+    assert!(buffer.iter().all(|x| *x == 0));
+    println!("kernel write test passed!");
+}
+
+/// Many children sharing the same pages, each one writing to them.
+fn many_children_test() {
+    let heap = vec![7usize; LEN];
+    let mut pids = [0usize; 8];
+    for (i, pid) in pids.iter_mut().enumerate() {
+        *pid = fork().unwrap();
+        if *pid == 0 {
+            let mut heap = heap.clone();
+            heap.iter_mut().for_each(|x| *x += i);
+            assert!(heap.iter().all(|x| *x == 7 + i));
+            exit(0);
+        }
+    }
+    for pid in pids.iter() {
+        assert_eq!(wait_exit_code(*pid), Ok(0));
+    }
+    assert!(heap.iter().all(|x| *x == 7));
+    println!("many children test passed!");
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    isolation_test();
+    kernel_write_test();
+    many_children_test();
+    println!("cow_test passed!");
+    0
+}
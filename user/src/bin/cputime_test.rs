@@ -0,0 +1,81 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    fork,
+    exit,
+    waitpid,
+    wexitstatus,
+    times,
+    Tms,
+    getrusage,
+    RUsage,
+    RUSAGE_SELF,
+    RUSAGE_CHILDREN,
+    RUSAGE_THREAD,
+    CLK_TCK,
+    Errno,
+};
+
+/// CPU time the child burns in user mode, in microseconds.
+const BUSY_US: usize = 100_000;
+
+fn rusage(who: isize) -> RUsage {
+    let mut usage = RUsage::default();
+    getrusage(who, &mut usage).unwrap();
+    usage
+}
+
+/// Spin in user mode until the process has run there for `us` more microseconds.
+fn busy(us: usize) {
+    let end = rusage(RUSAGE_SELF).ru_utime.as_us() + us;
+    let mut x: usize = 0;
+    while rusage(RUSAGE_SELF).ru_utime.as_us() < end {
+        for i in 0..10000 {
+            x = x.wrapping_mul(31).wrapping_add(i);
+        }
+    }
+    assert_ne!(unsafe { (&x as *const usize).read_volatile() }, 1);
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let mut before = Tms::default();
+    let start = times(&mut before).unwrap();
+    busy(BUSY_US / 10);
+    let usage = rusage(RUSAGE_SELF);
+    assert!(usage.ru_utime.as_us() >= BUSY_US / 10);
+    // the syscalls above ran in the kernel
+    assert!(usage.ru_stime.as_us() > 0);
+    assert!(rusage(RUSAGE_THREAD).ru_utime.as_us() <= rusage(RUSAGE_SELF).ru_utime.as_us());
+    assert_eq!(rusage(RUSAGE_CHILDREN).ru_utime.as_us(), 0);
+    assert_eq!(getrusage(42, &mut RUsage::default()), Err(Errno::EINVAL));
+
+    // the time of a child is added to ours once it is waited for
+    let pid = fork().unwrap();
+    if pid == 0 {
+        busy(BUSY_US);
+        exit(0);
+    }
+    let mut exit_code: i32 = 0;
+    assert_eq!(waitpid(pid, &mut exit_code), Ok(pid));
+    assert_eq!(wexitstatus(exit_code), 0);
+    let children = rusage(RUSAGE_CHILDREN);
+    assert!(children.ru_utime.as_us() >= BUSY_US);
+    let mut after = Tms::default();
+    let end = times(&mut after).unwrap();
+    assert!(after.tms_cutime >= BUSY_US * CLK_TCK / 1_000_000);
+    assert!(after.tms_utime >= before.tms_utime);
+    // the parent slept meanwhile, the clock went on
+    assert!(end - start >= after.tms_cutime);
+    println!(
+        "cputime_test passed! user {}us, kernel {}us, children user {}us",
+        usage.ru_utime.as_us(),
+        usage.ru_stime.as_us(),
+        children.ru_utime.as_us(),
+    );
+    0
+}
@@ -0,0 +1,69 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+extern crate alloc;
+
+use alloc::vec::Vec;
+use user_lib::{
+    spawn,
+    fork,
+    exec,
+    exit,
+    waitpid,
+    wexitstatus,
+    wait_exit_code,
+    read_file,
+    write_file,
+    Errno,
+};
+
+/// Linked with libuser.so, it exits with its argc.
+const HELLO: &str = "hello_dynamic\0";
+const LOADER: &str = "ld.so\0";
+const NO_LOADER: &str = "dynamic_test_no_loader\0";
+const BAD_LOADER: &str = "dynamic_test_bad_loader\0";
+/// Same length as "ld.so", so that PT_INTERP can be patched in place.
+const MISSING_INTERP: &[u8] = b"lx.so\0";
+const STATIC_INTERP: &str = "lz.so\0";
+
+fn run(path: &str, args: &[*const u8]) -> i32 {
+    wait_exit_code(spawn(path, args, &[]).unwrap()).unwrap()
+}
+
+/// A copy of hello_dynamic asking for another interpreter.
+fn with_interpreter(interp: &[u8]) -> Vec<u8> {
+    let mut data = read_file(HELLO).unwrap();
+    let idx = data.windows(LOADER.len()).position(|window| window == LOADER.as_bytes()).unwrap();
+    data[idx..idx + interp.len()].copy_from_slice(interp);
+    data
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let arg = "arg\0";
+    assert_eq!(run(HELLO, &[HELLO.as_ptr(), arg.as_ptr(), arg.as_ptr(), 0 as *const u8]), 3);
+
+    let pid = fork().unwrap();
+    if pid == 0 {
+        exec(HELLO, &[HELLO.as_ptr(), 0 as *const u8]).unwrap();
+        exit(100);
+    }
+    let mut exit_code: i32 = 0;
+    assert_eq!(waitpid(pid, &mut exit_code), Ok(pid));
+    assert_eq!(wexitstatus(exit_code), 1);
+
+    // the loader is position-independent itself, but has nothing to load
+    assert_eq!(run(LOADER, &[LOADER.as_ptr(), 0 as *const u8]), 127);
+
+    let args = [0 as *const u8];
+    write_file(NO_LOADER, &with_interpreter(MISSING_INTERP)).unwrap();
+    assert_eq!(spawn(NO_LOADER, &args, &[]), Err(Errno::ENOENT));
+    // the interpreter has to be position-independent
+    write_file(STATIC_INTERP, &read_file("hello_world\0").unwrap()).unwrap();
+    write_file(BAD_LOADER, &with_interpreter(STATIC_INTERP.as_bytes())).unwrap();
+    assert_eq!(spawn(BAD_LOADER, &args, &[]), Err(Errno::ENOEXEC));
+    println!("dynamic_test passed!");
+    0
+}
@@ -0,0 +1,102 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+extern crate alloc;
+
+use user_lib::{
+    spawn,
+    execve,
+    waitpid,
+    wexitstatus,
+    read_file,
+    write_file,
+    Errno,
+};
+
+const BAD_ELF: &str = "elf_test_bad\0";
+/// Offsets in the header of a 64-bit elf file.
+const EI_CLASS: usize = 4;
+const E_MACHINE: usize = 18;
+const E_ENTRY: usize = 24;
+const E_PHOFF: usize = 32;
+const E_PHENTSIZE: usize = 54;
+/// Offsets in a program header.
+const P_FLAGS: usize = 4;
+const P_OFFSET: usize = 8;
+const P_FILESZ: usize = 32;
+const PT_LOAD: u32 = 1;
+const PF_X: u32 = 1;
+const PF_W: u32 = 2;
+
+static mut ALIVE: usize = 0x5a5a;
+
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    let mut bytes = [0u8; 4];
+    bytes.copy_from_slice(&data[offset..offset + 4]);
+    u32::from_le_bytes(bytes)
+}
+
+fn read_u64(data: &[u8], offset: usize) -> usize {
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(&data[offset..offset + 8]);
+    u64::from_le_bytes(bytes) as usize
+}
+
+/// Offset of the first loadable segment in the program header table.
+fn first_load(data: &[u8]) -> usize {
+    let phoff = read_u64(data, E_PHOFF);
+    (0..).map(|i| phoff + i * 56).find(|ph| read_u32(data, *ph) == PT_LOAD).unwrap()
+}
+
+/// Both spawn and execve refuse the file, and the caller goes on.
+fn check_rejected(data: &[u8]) {
+    write_file(BAD_ELF, data).unwrap();
+    let args = [BAD_ELF.as_ptr(), 0 as *const u8];
+    assert_eq!(spawn(BAD_ELF, &args, &[]), Err(Errno::ENOEXEC));
+    assert_eq!(execve(BAD_ELF, &args, &[0 as *const u8]), Err(Errno::ENOEXEC));
+    assert_eq!(unsafe { (&ALIVE as *const usize).read_volatile() }, 0x5a5a);
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let elf = read_file("hello_world\0").unwrap();
+    // the copy runs as it is
+    write_file(BAD_ELF, &elf).unwrap();
+    let pid = spawn(BAD_ELF, &[BAD_ELF.as_ptr(), 0 as *const u8], &[]).unwrap();
+    let mut exit_code: i32 = 0;
+    assert_eq!(waitpid(pid, &mut exit_code), Ok(pid));
+    assert_eq!(wexitstatus(exit_code), 0);
+
+    check_rejected(b"not an elf\n");
+    check_rejected(&elf[..40]);
+    let mut bad = elf.clone();
+    bad[0] = 0;
+    check_rejected(&bad);
+    let mut bad = elf.clone();
+    bad[EI_CLASS] = 1;
+    check_rejected(&bad);
+    let mut bad = elf.clone();
+    bad[E_MACHINE] = 62;
+    check_rejected(&bad);
+    let mut bad = elf.clone();
+    bad[E_ENTRY..E_ENTRY + 8].copy_from_slice(&0xdead_0000usize.to_le_bytes());
+    check_rejected(&bad);
+    let mut bad = elf.clone();
+    bad[E_PHENTSIZE] = 0;
+    check_rejected(&bad);
+    let mut bad = elf.clone();
+    bad[E_PHOFF..E_PHOFF + 8].copy_from_slice(&(elf.len() as u64).to_le_bytes());
+    check_rejected(&bad);
+    let ph = first_load(&elf);
+    let flags = ph + P_FLAGS;
+    let mut bad = elf.clone();
+    bad[flags..flags + 4].copy_from_slice(&(read_u32(&elf, flags) | PF_W | PF_X).to_le_bytes());
+    check_rejected(&bad);
+    // the file ends before the data of the segment
+    let segment_end = read_u64(&elf, ph + P_OFFSET) + read_u64(&elf, ph + P_FILESZ);
+    check_rejected(&elf[..segment_end - 1]);
+    println!("elf_test passed!");
+    0
+}
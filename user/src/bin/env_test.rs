@@ -0,0 +1,70 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    spawn_with_env,
+    execve,
+    waitpid,
+    wexitstatus,
+};
+use user_lib::env::{self, AT_PAGESZ, AT_ENTRY, AT_PHDR, AT_PHENT, AT_PHNUM, AT_RANDOM};
+
+/// Type of a program header loading a segment.
+const PT_LOAD: u32 = 1;
+
+fn check_auxv() {
+    assert_eq!(env::auxval(AT_PAGESZ), Some(4096));
+    assert_ne!(env::auxval(AT_ENTRY), None);
+    let phdr = env::auxval(AT_PHDR).unwrap();
+    let phent = env::auxval(AT_PHENT).unwrap();
+    let phnum = env::auxval(AT_PHNUM).unwrap();
+    assert!(phent >= 56 && phnum > 0);
+    // the program headers are only mapped if the linker put them in a segment
+    if phdr != 0 {
+        assert!((0..phnum).any(|i| unsafe { ((phdr + i * phent) as *const u32).read_volatile() } == PT_LOAD));
+    }
+    let random = env::auxval(AT_RANDOM).unwrap() as *const [u8; 16];
+    assert_ne!(unsafe { random.read_volatile() }, [0u8; 16]);
+    assert_eq!(env::auxval(12345), None);
+}
+
+#[no_mangle]
+pub fn main(argc: usize, argv: &[&str]) -> i32 {
+    check_auxv();
+    match argc {
+        // spawned with its own environment, then exec with another one
+        2 if argv[1] == "spawned" => {
+            assert_eq!(env::var("FOO"), Some("bar"));
+            assert_eq!(env::var("EMPTY"), Some(""));
+            assert_eq!(env::var("MISSING"), None);
+            assert_eq!(env::vars(), [("FOO", "bar"), ("EMPTY", "")]);
+            execve(
+                "env_test\0",
+                &["env_test\0".as_ptr(), "exec\0".as_ptr(), 0 as *const u8],
+                &["BAZ=qux\0".as_ptr(), 0 as *const u8],
+            ).unwrap();
+            unreachable!();
+        }
+        2 if argv[1] == "exec" => {
+            assert_eq!(env::var("FOO"), None);
+            assert_eq!(env::vars(), [("BAZ", "qux")]);
+            0
+        }
+        _ => {
+            let pid = spawn_with_env(
+                "env_test\0",
+                &["env_test\0".as_ptr(), "spawned\0".as_ptr(), 0 as *const u8],
+                &["FOO=bar\0".as_ptr(), "EMPTY=\0".as_ptr(), 0 as *const u8],
+                &[],
+            ).unwrap();
+            let mut exit_code: i32 = 0;
+            assert_eq!(waitpid(pid, &mut exit_code), Ok(pid));
+            assert_eq!(wexitstatus(exit_code), 0);
+            println!("env_test passed!");
+            0
+        }
+    }
+}
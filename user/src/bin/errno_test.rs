@@ -0,0 +1,33 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    open,
+    close,
+    read,
+    write,
+    dup,
+    OpenFlags,
+    Errno,
+};
+
+#[no_mangle]
+pub fn main() -> i32 {
+    // file missing
+    assert_eq!(open("no_such_file\0", OpenFlags::RDONLY), Err(Errno::ENOENT));
+    // bad fd
+    let mut buffer = [0u8; 4];
+    assert_eq!(read(42, &mut buffer), Err(Errno::EBADF));
+    assert_eq!(close(42), Err(Errno::EBADF));
+    assert_eq!(dup(42), Err(Errno::EBADF));
+    // wrong direction
+    assert_eq!(write(0, b"x"), Err(Errno::EBADF));
+    // bad address
+    let bad = unsafe { core::slice::from_raw_parts(0x20_0000_0000 as *const u8, 4) };
+    assert_eq!(write(1, bad), Err(Errno::EFAULT));
+    println!("errno_test passed!");
+    0
+}
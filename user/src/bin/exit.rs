@@ -3,14 +3,14 @@
 
 #[macro_use]
 extern crate user_lib;
-use user_lib::{fork, yield_, waitpid, exit, wait};
+use user_lib::{fork, yield_, waitpid, exit, wait, wifexited, wexitstatus};
 
 const MAGIC: i32 = -0x10384;
 
 #[no_mangle]
 pub fn main() -> i32 {
     println!("I am the parent. Forking the child...");
-    let pid = fork();
+    let pid = fork().unwrap();
     if pid == 0 {
         println!("I am the child.");
         for _ in 0..7 { yield_(); }
@@ -20,8 +20,10 @@ pub fn main() -> i32 {
     }
     println!("I am the parent, waiting now..");
     let mut xstate: i32 = 0;
-    assert!(waitpid(pid as usize, &mut xstate) == pid && xstate == MAGIC);
-    assert!(waitpid(pid as usize, &mut xstate) < 0 && wait(&mut xstate) <= 0);
+    assert!(waitpid(pid, &mut xstate) == Ok(pid) && wifexited(xstate));
+    // only the low 8 bits of the exit code are reported
+    assert_eq!(wexitstatus(xstate), MAGIC & 0xff);
+    assert!(waitpid(pid, &mut xstate).is_err() && wait(&mut xstate).is_err());
     println!("waitpid {} ok.", pid);
     println!("exit pass.");
     0
@@ -11,7 +11,7 @@ const MAX_CHILD: usize = 40;
 #[no_mangle]
 pub fn main() -> i32 {
     for i in 0..MAX_CHILD {
-        let pid = fork();
+        let pid = fork().unwrap();
         if pid == 0 {
             println!("I am child {}", i);
             exit(0);
@@ -22,11 +22,11 @@ pub fn main() -> i32 {
     }
     let mut exit_code: i32 = 0;
     for _ in 0..MAX_CHILD {
-        if wait(&mut exit_code) <= 0 {
+        if wait(&mut exit_code).is_err() {
             panic!("wait stopped early");
         }
     }
-    if wait(&mut exit_code) > 0 {
+    if wait(&mut exit_code).is_ok() {
         panic!("wait got too many");
     }
     println!("forktest pass.");
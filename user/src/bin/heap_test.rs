@@ -0,0 +1,95 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+extern crate alloc;
+
+use alloc::vec::Vec;
+use user_lib::{brk, sbrk, mmap, munmap, fork, exit, waitpid, wexitstatus, MmapProt, Errno};
+
+const PAGE_SIZE: usize = 0x1000;
+
+/// Much more than the 32 KiB the heap used to be limited to.
+fn large_alloc_test() {
+    let len = 1 << 15;
+    let mut v: Vec<usize> = Vec::new();
+    for i in 0..len {
+        v.push(i);
+    }
+    assert!(v.iter().enumerate().all(|(i, x)| i == *x));
+    drop(v);
+    // many small allocations as well
+    let boxes: Vec<Vec<u8>> = (0..256).map(|i| alloc::vec![i as u8; 1000]).collect();
+    for (i, b) in boxes.iter().enumerate() {
+        assert!(b.iter().all(|x| *x == i as u8));
+    }
+    println!("large alloc test passed!");
+}
+
+fn fork_test() {
+    let v: Vec<usize> = (0..10000).collect();
+    let pid = fork().unwrap();
+    if pid == 0 {
+        // the child gets a copy of the heap and can keep growing it
+        assert!(v.iter().enumerate().all(|(i, x)| i == *x));
+        let w: Vec<usize> = (0..10000).map(|x| x * 2).collect();
+        assert_eq!(w[9999], 19998);
+        exit(0);
+    }
+    let mut exit_code: i32 = 0;
+    assert_eq!(waitpid(pid, &mut exit_code), Ok(pid));
+    assert_eq!(wexitstatus(exit_code), 0);
+    assert!(v.iter().enumerate().all(|(i, x)| i == *x));
+    println!("fork test passed!");
+}
+
+fn brk_test() {
+    let old_brk = brk(0).unwrap();
+    assert_eq!(sbrk(0), Ok(old_brk));
+    // grow by a few pages and use them
+    assert_eq!(sbrk(3 * PAGE_SIZE as isize), Ok(old_brk));
+    assert_eq!(brk(0), Ok(old_brk + 3 * PAGE_SIZE));
+    for addr in (old_brk..old_brk + 3 * PAGE_SIZE).step_by(8) {
+        unsafe { (addr as *mut usize).write_volatile(addr); }
+    }
+    for addr in (old_brk..old_brk + 3 * PAGE_SIZE).step_by(8) {
+        assert_eq!(unsafe { (addr as *const usize).read_volatile() }, addr);
+    }
+    // and give them back
+    assert_eq!(brk(old_brk), Ok(old_brk));
+    assert_eq!(sbrk(0), Ok(old_brk));
+    // the heap never goes below its bottom or into the mmap region
+    assert_eq!(brk(PAGE_SIZE), Err(Errno::ENOMEM));
+    assert_eq!(sbrk(isize::MIN), Err(Errno::ENOMEM));
+    assert_eq!(sbrk(1 << 40), Err(Errno::ENOMEM));
+    assert_eq!(sbrk(0), Ok(old_brk));
+    println!("brk test passed!");
+}
+
+/// The heap and the pages it may grow into are left to brk.
+fn mmap_test() {
+    let old_brk = brk(0).unwrap();
+    let heap_page = old_brk & !(PAGE_SIZE - 1);
+    assert_eq!(sbrk(PAGE_SIZE as isize), Ok(old_brk));
+    assert_eq!(munmap(heap_page, PAGE_SIZE), Err(Errno::EINVAL));
+    let above = heap_page + 16 * PAGE_SIZE;
+    assert_eq!(mmap(above, PAGE_SIZE, MmapProt::READ | MmapProt::WRITE), Err(Errno::ENOMEM));
+    assert_eq!(munmap(above, PAGE_SIZE), Err(Errno::EINVAL));
+    // so it can still grow and shrink
+    assert_eq!(sbrk(32 * PAGE_SIZE as isize), Ok(old_brk + PAGE_SIZE));
+    unsafe { (above as *mut usize).write_volatile(above); }
+    assert_eq!(unsafe { (above as *const usize).read_volatile() }, above);
+    assert_eq!(brk(old_brk), Ok(old_brk));
+    println!("mmap test passed!");
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    large_alloc_test();
+    fork_test();
+    brk_test();
+    mmap_test();
+    println!("heap_test passed!");
+    0
+}
@@ -5,30 +5,27 @@
 extern crate user_lib;
 
 use user_lib::{
-    fork,
+    spawn,
     wait,
-    exec,
     yield_,
 };
 
 #[no_mangle]
 fn main() -> i32 {
-    if fork() == 0 {
-        exec("user_shell\0", &[0 as *const u8]);
-    } else {
-        loop {
-            let mut exit_code: i32 = 0;
-            let pid = wait(&mut exit_code);
-            if pid == -1 {
+    spawn("user_shell\0", &[0 as *const u8], &[]).unwrap();
+    loop {
+        let mut exit_code: i32 = 0;
+        let pid = match wait(&mut exit_code) {
+            Ok(pid) => pid,
+            Err(_) => {
                 yield_();
                 continue;
             }
-            println!(
-                "[initproc] Released a zombie process, pid={}, exit_code={}",
-                pid,
-                exit_code,
-            );
-        }
+        };
+        println!(
+            "[initproc] Released a zombie process, pid={}, exit_code={}",
+            pid,
+            exit_code,
+        );
     }
-    0
-}
\ No newline at end of file
+}
@@ -0,0 +1,151 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    fork,
+    exit,
+    getpid,
+    sleep,
+    spawn,
+    wait_exit_code,
+    SpawnAction,
+    waitpid,
+    waitpid_with_options,
+    WaitOptions,
+    wifstopped,
+    wstopsig,
+    wifcontinued,
+    wifsignaled,
+    wtermsig,
+    kill,
+    killpg,
+    setpgid,
+    getpgid,
+    setsid,
+    tcgetpgrp,
+    tcsetpgrp,
+    pipe,
+    close,
+    Errno,
+};
+use user_lib::signal::{SIGSTOP, SIGCONT, SIGKILL};
+
+/// A child which runs until it is killed.
+fn fork_sleeper() -> usize {
+    let pid = fork().unwrap();
+    if pid == 0 {
+        loop {
+            sleep(10);
+        }
+    }
+    pid
+}
+
+fn test_session() {
+    let pid = fork().unwrap();
+    if pid == 0 {
+        let pid = getpid() as usize;
+        assert_eq!(setsid(), Ok(pid));
+        assert_eq!(getpgid(0), Ok(pid));
+        // it leads its group now
+        assert_eq!(setsid(), Err(Errno::EPERM));
+        assert_eq!(setpgid(0, 0), Err(Errno::EPERM));
+        // the console belongs to a group of another session
+        assert_eq!(tcsetpgrp(0, tcgetpgrp(0).unwrap()), Err(Errno::EPERM));
+        exit(0);
+    }
+    assert_eq!(wait_exit_code(pid), Ok(0));
+}
+
+fn test_process_group() {
+    let first = fork_sleeper();
+    let second = fork_sleeper();
+    setpgid(first, 0).unwrap();
+    setpgid(second, first).unwrap();
+    assert_eq!(getpgid(first), Ok(first));
+    assert_eq!(getpgid(second), Ok(first));
+    assert_eq!(setpgid(second, 12345), Err(Errno::EPERM));
+    assert_eq!(setpgid(12345, 0), Err(Errno::ESRCH));
+    assert_eq!(getpgid(12345), Err(Errno::ESRCH));
+    assert_eq!(killpg(12345, SIGKILL), Err(Errno::ESRCH));
+    killpg(first, SIGKILL).unwrap();
+    for pid in [first, second].iter() {
+        let mut exit_code: i32 = 0;
+        assert_eq!(waitpid(*pid, &mut exit_code), Ok(*pid));
+        assert!(wifsignaled(exit_code) && wtermsig(exit_code) == SIGKILL);
+    }
+}
+
+fn test_stop_and_continue() {
+    let pid = fork_sleeper();
+    let mut exit_code: i32 = 0;
+    kill(pid, SIGSTOP).unwrap();
+    assert_eq!(waitpid_with_options(pid as isize, &mut exit_code, WaitOptions::WUNTRACED), Ok(pid));
+    assert!(wifstopped(exit_code) && wstopsig(exit_code) == SIGSTOP);
+    // a stop is only reported once
+    assert_eq!(
+        waitpid_with_options(pid as isize, &mut exit_code, WaitOptions::WNOHANG | WaitOptions::WUNTRACED),
+        Ok(0),
+    );
+    kill(pid, SIGCONT).unwrap();
+    assert_eq!(waitpid_with_options(pid as isize, &mut exit_code, WaitOptions::WCONTINUED), Ok(pid));
+    assert!(wifcontinued(exit_code));
+    // a stopped process can still be killed
+    kill(pid, SIGSTOP).unwrap();
+    assert_eq!(waitpid_with_options(pid as isize, &mut exit_code, WaitOptions::WUNTRACED), Ok(pid));
+    kill(pid, SIGKILL).unwrap();
+    assert_eq!(waitpid(pid, &mut exit_code), Ok(pid));
+    assert!(wifsignaled(exit_code) && wtermsig(exit_code) == SIGKILL);
+}
+
+fn test_terminal() {
+    let mut pipe_fd = [0usize; 2];
+    pipe(&mut pipe_fd).unwrap();
+    assert_eq!(tcgetpgrp(pipe_fd[0]), Err(Errno::ENOTTY));
+    assert_eq!(tcsetpgrp(pipe_fd[1], 0), Err(Errno::ENOTTY));
+    close(pipe_fd[0]).unwrap();
+    close(pipe_fd[1]).unwrap();
+    assert_eq!(tcgetpgrp(42), Err(Errno::EBADF));
+    assert_eq!(tcsetpgrp(0, 12345), Err(Errno::EPERM));
+    // the foreground group can be given away and taken back
+    let foreground = tcgetpgrp(0).unwrap();
+    let pgid = getpgid(0).unwrap();
+    if foreground == pgid {
+        let pid = fork_sleeper();
+        setpgid(pid, 0).unwrap();
+        tcsetpgrp(0, pid).unwrap();
+        assert_eq!(tcgetpgrp(0), Ok(pid));
+        tcsetpgrp(0, pgid).unwrap();
+        kill(pid, SIGKILL).unwrap();
+        let mut exit_code: i32 = 0;
+        assert_eq!(waitpid(pid, &mut exit_code), Ok(pid));
+    }
+}
+
+fn test_spawn_setpgid() {
+    let args = ["jobctl_test\0".as_ptr(), "leader\0".as_ptr(), 0 as *const u8];
+    let pid = spawn("jobctl_test\0", &args, &[SpawnAction::setpgid(0)]).unwrap();
+    assert_eq!(wait_exit_code(pid), Ok(0));
+    assert_eq!(
+        spawn("jobctl_test\0", &args, &[SpawnAction::setpgid(12345)]),
+        Err(Errno::EPERM),
+    );
+}
+
+#[no_mangle]
+pub fn main(argc: usize, argv: &[&str]) -> i32 {
+    if argc == 2 && argv[1] == "leader" {
+        assert_eq!(getpgid(0), Ok(getpid() as usize));
+        return 0;
+    }
+    test_session();
+    test_process_group();
+    test_stop_and_continue();
+    test_terminal();
+    test_spawn_setpgid();
+    println!("jobctl_test passed!");
+    0
+}
@@ -0,0 +1,76 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{fork, exit, waitpid, wexitstatus, pipe, read, write, close};
+
+const PAGE_SIZE: usize = 0x1000;
+/// Twice the physical memory of the machine, only the touched pages get frames.
+const BIG_SIZE: usize = 16 << 20;
+
+static mut BIG: [u8; BIG_SIZE] = [0; BIG_SIZE];
+
+static MESSAGE: &[u8] = b"loaded on demand";
+
+fn big_addr(offset: usize) -> *mut u8 {
+    unsafe { BIG.as_mut_ptr().add(offset) }
+}
+
+fn bss_test() {
+    for offset in (0..BIG_SIZE).step_by(BIG_SIZE / 64) {
+        unsafe {
+            assert_eq!(big_addr(offset).read_volatile(), 0);
+            big_addr(offset).write_volatile(offset as u8 + 1);
+        }
+    }
+    for offset in (0..BIG_SIZE).step_by(BIG_SIZE / 64) {
+        assert_eq!(unsafe { big_addr(offset).read_volatile() }, offset as u8 + 1);
+    }
+    println!("bss test passed!");
+}
+
+/// The kernel reads and writes pages which the program has never touched.
+fn kernel_access_test() {
+    let mut pipe_fd = [0usize; 2];
+    pipe(&mut pipe_fd).unwrap();
+    // rodata which is only read by the kernel
+    assert_eq!(write(pipe_fd[1], MESSAGE), Ok(MESSAGE.len()));
+    close(pipe_fd[1]).unwrap();
+    // somewhere in the middle of bss, far from the pages touched above
+    let offset = BIG_SIZE / 2 + PAGE_SIZE / 2 + 3 * PAGE_SIZE;
+    let buffer = unsafe { core::slice::from_raw_parts_mut(big_addr(offset), PAGE_SIZE) };
+    assert_eq!(read(pipe_fd[0], buffer), Ok(MESSAGE.len()));
+    assert_eq!(&buffer[..MESSAGE.len()], MESSAGE);
+    assert!(buffer[MESSAGE.len()..].iter().all(|x| *x == 0));
+    close(pipe_fd[0]).unwrap();
+    println!("kernel access test passed!");
+}
+
+/// A child loads the pages its parent has not touched on its own.
+fn fork_test() {
+    let offset = BIG_SIZE - PAGE_SIZE;
+    let pid = fork().unwrap();
+    if pid == 0 {
+        unsafe {
+            assert_eq!(big_addr(offset).read_volatile(), 0);
+            big_addr(offset).write_volatile(42);
+        }
+        exit(0);
+    }
+    let mut exit_code: i32 = 0;
+    assert_eq!(waitpid(pid, &mut exit_code), Ok(pid));
+    assert_eq!(wexitstatus(exit_code), 0);
+    assert_eq!(unsafe { big_addr(offset).read_volatile() }, 0);
+    println!("fork test passed!");
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    bss_test();
+    kernel_access_test();
+    fork_test();
+    println!("lazy_test passed!");
+    0
+}
@@ -0,0 +1,36 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+extern crate alloc;
+
+use user_lib::{personality, exec, PERSONALITY_LINUX};
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// `linux program [args...]` runs a statically linked Linux program, such
+/// as a musl build of busybox, with the system calls of Linux.
+#[no_mangle]
+pub fn main(argc: usize, argv: &[&str]) -> i32 {
+    if argc < 2 {
+        println!("usage: linux program [args...]");
+        return 1;
+    }
+    // the heap cannot grow once the system calls are those of Linux
+    let args: Vec<String> = argv[1..]
+        .iter()
+        .map(|arg| {
+            let mut arg = String::from(*arg);
+            arg.push('\0');
+            arg
+        })
+        .collect();
+    let mut arg_ptrs: Vec<*const u8> = args.iter().map(|arg| arg.as_ptr()).collect();
+    arg_ptrs.push(core::ptr::null());
+    personality(PERSONALITY_LINUX).unwrap();
+    // execve, write and exit have the same numbers in both tables
+    let errno = exec(args[0].as_str(), arg_ptrs.as_slice()).unwrap_err();
+    println!("linux: {}: {}", argv[1], errno);
+    127
+}
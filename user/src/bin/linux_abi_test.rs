@@ -0,0 +1,297 @@
+#![no_std]
+#![no_main]
+#![feature(llvm_asm)]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    personality,
+    spawn,
+    fork,
+    getpid,
+    waitpid,
+    wexitstatus,
+    wait_exit_code,
+    Errno,
+    PERSONALITY_NATIVE,
+    PERSONALITY_LINUX,
+};
+use user_lib::env::{self, AT_UID, AT_EUID, AT_SECURE, AT_CLKTCK};
+
+// Linux system calls, which only the child makes.
+const SYS_GETCWD: usize = 17;
+const SYS_IOCTL: usize = 29;
+const SYS_OPENAT: usize = 56;
+const SYS_CLOSE: usize = 57;
+const SYS_READV: usize = 65;
+const SYS_WRITEV: usize = 66;
+const SYS_NEWFSTATAT: usize = 79;
+const SYS_FSTAT: usize = 80;
+const SYS_PERSONALITY: usize = 92;
+const SYS_EXIT_GROUP: usize = 94;
+const SYS_SET_TID_ADDRESS: usize = 96;
+const SYS_CLOCK_GETTIME: usize = 113;
+const SYS_GETPID: usize = 172;
+const SYS_GETPPID: usize = 173;
+const SYS_GETUID: usize = 174;
+const SYS_GETTID: usize = 178;
+const SYS_BRK: usize = 214;
+const SYS_MUNMAP: usize = 215;
+const SYS_CLONE: usize = 220;
+const SYS_MMAP: usize = 222;
+const SYS_WAIT4: usize = 260;
+/// sbrk of this kernel, which Linux does not have.
+const SYS_NATIVE_SBRK: usize = 1040;
+
+const AT_FDCWD: usize = -100isize as usize;
+const AT_EMPTY_PATH: usize = 0x1000;
+const O_RDONLY: usize = 0;
+const O_WRONLY: usize = 1;
+const O_CREAT: usize = 0o100;
+const O_EXCL: usize = 0o200;
+const O_TRUNC: usize = 0o1000;
+const S_IFMT: u32 = 0o170000;
+const S_IFCHR: u32 = 0o020000;
+const S_IFREG: u32 = 0o100000;
+const TIOCGWINSZ: usize = 0x5413;
+const CLOCK_MONOTONIC: usize = 1;
+const PROT_READ: usize = 1;
+const PROT_WRITE: usize = 2;
+const MAP_PRIVATE: usize = 0x02;
+const MAP_FIXED: usize = 0x10;
+const MAP_ANONYMOUS: usize = 0x20;
+const SIGCHLD: usize = 17;
+const CLONE_THREAD: usize = 0x10000;
+
+const EEXIST: isize = 17;
+const ENODEV: isize = 19;
+const EINVAL: isize = 22;
+const ENOSYS: isize = 38;
+
+const PAGE_SIZE: usize = 0x1000;
+const FILE: &str = "/linux_abi_test_file\0";
+
+/// The raw ecall of Linux, with up to six arguments.
+fn linux_syscall(id: usize, args: [usize; 6]) -> isize {
+    let mut ret: isize;
+    unsafe {
+        llvm_asm!("ecall"
+            : "={x10}" (ret)
+            : "{x10}" (args[0]), "{x11}" (args[1]), "{x12}" (args[2]),
+              "{x13}" (args[3]), "{x14}" (args[4]), "{x15}" (args[5]), "{x17}" (id)
+            : "memory"
+            : "volatile"
+        );
+    }
+    ret
+}
+
+#[repr(C)]
+struct IoVec {
+    base: usize,
+    len: usize,
+}
+
+#[repr(C)]
+#[derive(Default)]
+struct Stat {
+    st_dev: u64,
+    st_ino: u64,
+    st_mode: u32,
+    st_nlink: u32,
+    st_uid: u32,
+    st_gid: u32,
+    st_rdev: u64,
+    __pad1: u64,
+    st_size: i64,
+    st_blksize: i32,
+    __pad2: i32,
+    st_blocks: i64,
+    st_times: [u64; 6],
+    __unused: [u32; 2],
+}
+
+#[repr(C)]
+#[derive(Default)]
+struct WinSize {
+    ws_row: u16,
+    ws_col: u16,
+    ws_xpixel: u16,
+    ws_ypixel: u16,
+}
+
+#[repr(C)]
+#[derive(Default)]
+struct TimeSpec {
+    sec: usize,
+    nsec: usize,
+}
+
+fn stat_path(path: &str, stat: &mut Stat) -> isize {
+    linux_syscall(SYS_NEWFSTATAT, [AT_FDCWD, path.as_ptr() as usize, stat as *mut _ as usize, 0, 0, 0])
+}
+
+fn fstat(fd: usize, stat: &mut Stat) -> isize {
+    linux_syscall(SYS_FSTAT, [fd, stat as *mut _ as usize, 0, 0, 0, 0])
+}
+
+fn file_test() {
+    let mut cwd = [0xffu8; 8];
+    assert_eq!(linux_syscall(SYS_GETCWD, [cwd.as_mut_ptr() as usize, cwd.len(), 0, 0, 0, 0]), 2);
+    assert_eq!(&cwd[..2], b"/\0");
+
+    let fd = linux_syscall(SYS_OPENAT, [AT_FDCWD, FILE.as_ptr() as usize, O_CREAT | O_WRONLY | O_TRUNC, 0o644, 0, 0]);
+    assert!(fd > 2);
+    let fd = fd as usize;
+    let (hello, linux) = (b"hello, ", b"linux!\n");
+    let iov = [
+        IoVec { base: hello.as_ptr() as usize, len: hello.len() },
+        IoVec { base: 0, len: 0 },
+        IoVec { base: linux.as_ptr() as usize, len: linux.len() },
+    ];
+    let len = hello.len() + linux.len();
+    assert_eq!(linux_syscall(SYS_WRITEV, [fd, iov.as_ptr() as usize, iov.len(), 0, 0, 0]), len as isize);
+    let mut stat = Stat::default();
+    assert_eq!(fstat(fd, &mut stat), 0);
+    assert_eq!(stat.st_mode & S_IFMT, S_IFREG);
+    assert_eq!(stat.st_size, len as i64);
+    let ino = stat.st_ino;
+    assert_eq!(linux_syscall(SYS_CLOSE, [fd, 0, 0, 0, 0, 0]), 0);
+
+    // O_CREAT alone keeps what is there, O_EXCL refuses it
+    let fd = linux_syscall(SYS_OPENAT, [AT_FDCWD, FILE[1..].as_ptr() as usize, O_CREAT | O_RDONLY, 0o644, 0, 0]);
+    assert!(fd > 2);
+    let fd = fd as usize;
+    let mut stat = Stat::default();
+    assert_eq!(stat_path(FILE, &mut stat), 0);
+    assert_eq!((stat.st_size, stat.st_ino), (len as i64, ino));
+    let mut stat = Stat::default();
+    let empty = "\0";
+    assert_eq!(
+        linux_syscall(SYS_NEWFSTATAT, [fd, empty.as_ptr() as usize, &mut stat as *mut _ as usize, AT_EMPTY_PATH, 0, 0]),
+        0,
+    );
+    assert_eq!(stat.st_ino, ino);
+    let (mut first, mut second) = ([0u8; 4], [0u8; 32]);
+    let iov = [
+        IoVec { base: first.as_mut_ptr() as usize, len: first.len() },
+        IoVec { base: second.as_mut_ptr() as usize, len: second.len() },
+    ];
+    assert_eq!(linux_syscall(SYS_READV, [fd, iov.as_ptr() as usize, iov.len(), 0, 0, 0]), len as isize);
+    assert_eq!(&first, b"hell");
+    assert_eq!(&second[..len - 4], b"o, linux!\n");
+    assert_eq!(linux_syscall(SYS_CLOSE, [fd, 0, 0, 0, 0, 0]), 0);
+    assert_eq!(
+        linux_syscall(SYS_OPENAT, [AT_FDCWD, FILE.as_ptr() as usize, O_CREAT | O_EXCL | O_WRONLY, 0o644, 0, 0]),
+        -EEXIST,
+    );
+
+    // the console is a terminal of the usual size
+    let mut stat = Stat::default();
+    assert_eq!(fstat(1, &mut stat), 0);
+    assert_eq!(stat.st_mode & S_IFMT, S_IFCHR);
+    let mut ws = WinSize::default();
+    assert_eq!(linux_syscall(SYS_IOCTL, [1, TIOCGWINSZ, &mut ws as *mut _ as usize, 0, 0, 0]), 0);
+    assert!(ws.ws_row > 0 && ws.ws_col > 0);
+    println!("file test passed!");
+}
+
+fn memory_test() {
+    let brk = linux_syscall(SYS_BRK, [0; 6]) as usize;
+    let new_brk = brk + 3 * PAGE_SIZE;
+    assert_eq!(linux_syscall(SYS_BRK, [new_brk, 0, 0, 0, 0, 0]), new_brk as isize);
+    unsafe { ((new_brk - 8) as *mut usize).write_volatile(new_brk); }
+    // a failure leaves the heap as it is
+    assert_eq!(linux_syscall(SYS_BRK, [usize::MAX - PAGE_SIZE, 0, 0, 0, 0, 0]), new_brk as isize);
+    assert_eq!(unsafe { ((new_brk - 8) as *const usize).read_volatile() }, new_brk);
+
+    let prot = PROT_READ | PROT_WRITE;
+    let flags = MAP_PRIVATE | MAP_ANONYMOUS;
+    let len = 2 * PAGE_SIZE;
+    let start = linux_syscall(SYS_MMAP, [0, len, prot, flags, usize::MAX, 0]);
+    assert!(start > 0 && start as usize % PAGE_SIZE == 0);
+    let start = start as usize;
+    unsafe { (start as *mut usize).write_volatile(start); }
+    // a fixed mapping replaces the old one with zeroed pages
+    assert_eq!(linux_syscall(SYS_MMAP, [start, PAGE_SIZE, prot, flags | MAP_FIXED, usize::MAX, 0]), start as isize);
+    assert_eq!(unsafe { (start as *const usize).read_volatile() }, 0);
+    assert_eq!(linux_syscall(SYS_MUNMAP, [start, len, 0, 0, 0, 0]), 0);
+    // files cannot be mapped
+    assert_eq!(linux_syscall(SYS_MMAP, [0, len, prot, MAP_PRIVATE, 0, 0]), -ENODEV);
+    println!("memory test passed!");
+}
+
+fn process_test(ppid: usize) {
+    let pid = linux_syscall(SYS_GETPID, [0; 6]);
+    assert_eq!(linux_syscall(SYS_GETPPID, [0; 6]), ppid as isize);
+    assert_eq!(linux_syscall(SYS_GETTID, [0; 6]), pid);
+    assert_eq!(linux_syscall(SYS_SET_TID_ADDRESS, [0; 6]), pid);
+    assert_eq!(linux_syscall(SYS_GETUID, [0; 6]), 0);
+    assert_eq!(linux_syscall(SYS_PERSONALITY, [0xffff_ffff, 0, 0, 0, 0, 0]), 0);
+    assert_eq!(linux_syscall(SYS_PERSONALITY, [PERSONALITY_LINUX, 0, 0, 0, 0, 0]), -EINVAL);
+    assert_eq!(linux_syscall(SYS_NATIVE_SBRK, [0; 6]), -ENOSYS);
+
+    let mut ts = [TimeSpec::default(), TimeSpec::default()];
+    for t in ts.iter_mut() {
+        assert_eq!(linux_syscall(SYS_CLOCK_GETTIME, [CLOCK_MONOTONIC, t as *mut _ as usize, 0, 0, 0, 0]), 0);
+        assert!(t.nsec < 1_000_000_000);
+    }
+    assert!((ts[1].sec, ts[1].nsec) >= (ts[0].sec, ts[0].nsec));
+    assert_eq!(linux_syscall(SYS_CLOCK_GETTIME, [99, &mut ts[0] as *mut _ as usize, 0, 0, 0, 0]), -EINVAL);
+
+    // the child of a fork keeps the personality
+    let child = linux_syscall(SYS_CLONE, [SIGCHLD, 0, 0, 0, 0, 0]);
+    assert!(child >= 0);
+    if child == 0 {
+        assert_eq!(linux_syscall(SYS_GETPPID, [0; 6]), pid);
+        linux_syscall(SYS_EXIT_GROUP, [7, 0, 0, 0, 0, 0]);
+        unreachable!();
+    }
+    let mut status: i32 = 0;
+    let mut rusage = [usize::MAX; 18];
+    assert_eq!(
+        linux_syscall(SYS_WAIT4, [child as usize, &mut status as *mut _ as usize, 0, rusage.as_mut_ptr() as usize, 0, 0]),
+        child,
+    );
+    assert_eq!(wexitstatus(status), 7);
+    assert_eq!(rusage, [0; 18]);
+    assert_eq!(linux_syscall(SYS_CLONE, [CLONE_THREAD | SIGCHLD, 0, 0, 0, 0, 0]), -EINVAL);
+    println!("process test passed!");
+}
+
+fn run(path: &str, args: &[*const u8]) -> i32 {
+    wait_exit_code(spawn(path, args, &[]).unwrap()).unwrap()
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    assert_eq!(personality(PERSONALITY_NATIVE), Ok(PERSONALITY_NATIVE));
+    assert_eq!(personality(2), Err(Errno::EINVAL));
+    assert_eq!(env::auxval(AT_CLKTCK), Some(100));
+    assert_eq!(env::auxval(AT_UID), Some(0));
+    assert_eq!(env::auxval(AT_EUID), Some(0));
+    assert_eq!(env::auxval(AT_SECURE), Some(0));
+
+    let linux = "linux\0";
+    let missing = "linux_abi_test_missing\0";
+    assert_eq!(run(linux, &[linux.as_ptr(), missing.as_ptr(), 0 as *const u8]), 127);
+    assert_eq!(run(linux, &[linux.as_ptr(), 0 as *const u8]), 1);
+
+    let ppid = getpid() as usize;
+    let pid = fork().unwrap();
+    if pid == 0 {
+        // nothing may be allocated from now on
+        assert_eq!(personality(PERSONALITY_LINUX), Ok(PERSONALITY_NATIVE));
+        file_test();
+        memory_test();
+        process_test(ppid);
+        linux_syscall(SYS_EXIT_GROUP, [0; 6]);
+        unreachable!();
+    }
+    let mut exit_code: i32 = 0;
+    assert_eq!(waitpid(pid, &mut exit_code), Ok(pid));
+    assert_eq!(exit_code, 0);
+    println!("linux_abi_test passed!");
+    0
+}
@@ -0,0 +1,26 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{spawn, wait_exit_code, read_file, SpawnAction, OpenFlags};
+
+const LINUX: &str = "linux\0";
+/// Built from user/linux/hello_musl.c, a static musl program.
+const HELLO: &str = "hello_musl\0";
+const NAME: &str = "rcore\0";
+const OUTPUT: &str = "linux_test_out\0";
+
+/// Run a program of the C library musl under the linux launcher, which
+/// goes through its startup code, stdio and exit.
+#[no_mangle]
+pub fn main() -> i32 {
+    let args = [LINUX.as_ptr(), HELLO.as_ptr(), NAME.as_ptr(), 0 as *const u8];
+    let actions = [SpawnAction::open(1, OUTPUT, OpenFlags::CREATE | OpenFlags::WRONLY)];
+    let pid = spawn(LINUX, &args, &actions).unwrap();
+    assert_eq!(wait_exit_code(pid), Ok(7));
+    assert_eq!(read_file(OUTPUT).unwrap().as_slice(), b"Hello from musl, rcore!\n");
+    println!("linux_test passed!");
+    0
+}
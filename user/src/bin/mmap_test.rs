@@ -0,0 +1,95 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{mmap, munmap, fork, exit, waitpid, MmapProt, Errno, wifsignaled, wtermsig};
+use user_lib::signal::SIGSEGV;
+
+const PAGE_SIZE: usize = 0x1000;
+
+static MAPPED: u8 = 0;
+
+fn fill(start: usize, len: usize) {
+    for addr in (start..start + len).step_by(8) {
+        unsafe { (addr as *mut usize).write_volatile(addr); }
+    }
+}
+
+fn check(start: usize, len: usize) {
+    for addr in (start..start + len).step_by(8) {
+        assert_eq!(unsafe { (addr as *const usize).read_volatile() }, addr);
+    }
+}
+
+/// Touching `addr` should kill a child with SIGSEGV.
+fn assert_unmapped(addr: usize) {
+    let pid = fork().unwrap();
+    if pid == 0 {
+        unsafe { (addr as *mut u8).write_volatile(0); }
+        exit(0);
+    }
+    let mut exit_code: i32 = 0;
+    assert_eq!(waitpid(pid, &mut exit_code), Ok(pid));
+    assert!(wifsignaled(exit_code) && wtermsig(exit_code) == SIGSEGV);
+}
+
+fn large_buffer_test() {
+    let len = 1 << 20;
+    let start = mmap(0, len, MmapProt::READ | MmapProt::WRITE).unwrap();
+    assert_eq!(start % PAGE_SIZE, 0);
+    fill(start, len);
+    check(start, len);
+    munmap(start, len).unwrap();
+    println!("large buffer test passed!");
+}
+
+fn split_test() {
+    let len = 4 * PAGE_SIZE;
+    let start = mmap(0, len, MmapProt::READ | MmapProt::WRITE).unwrap();
+    fill(start, len);
+    // punch a hole in the middle, the rest stays mapped
+    munmap(start + PAGE_SIZE, 2 * PAGE_SIZE).unwrap();
+    check(start, PAGE_SIZE);
+    check(start + 3 * PAGE_SIZE, PAGE_SIZE);
+    assert_unmapped(start + PAGE_SIZE);
+    assert_unmapped(start + 2 * PAGE_SIZE);
+    // the hole can be mapped again, but not more than that
+    assert_eq!(
+        mmap(start + PAGE_SIZE, 3 * PAGE_SIZE, MmapProt::READ | MmapProt::WRITE),
+        Err(Errno::EEXIST)
+    );
+    assert_eq!(
+        mmap(start + PAGE_SIZE, 2 * PAGE_SIZE, MmapProt::READ | MmapProt::WRITE),
+        Ok(start + PAGE_SIZE)
+    );
+    // new pages are zeroed
+    assert_eq!(unsafe { ((start + PAGE_SIZE) as *const usize).read_volatile() }, 0);
+    munmap(start, len).unwrap();
+    assert_unmapped(start);
+    println!("split test passed!");
+}
+
+fn error_test() {
+    let prot = MmapProt::READ | MmapProt::WRITE;
+    assert_eq!(mmap(0, 0, prot), Err(Errno::EINVAL));
+    assert_eq!(mmap(PAGE_SIZE + 1, PAGE_SIZE, prot), Err(Errno::EINVAL));
+    assert_eq!(mmap(0, PAGE_SIZE, MmapProt::empty()), Err(Errno::EINVAL));
+    // the data of the program is mapped already, above its code
+    let data_page = &MAPPED as *const u8 as usize & !(PAGE_SIZE - 1);
+    assert_eq!(mmap(data_page, PAGE_SIZE, prot), Err(Errno::EEXIST));
+    assert_eq!(mmap(0, 1 << 40, prot), Err(Errno::ENOMEM));
+    assert_eq!(munmap(1, PAGE_SIZE), Err(Errno::EINVAL));
+    assert_eq!(munmap(PAGE_SIZE, 0), Err(Errno::EINVAL));
+    println!("error test passed!");
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    large_buffer_test();
+    split_test();
+    error_test();
+    println!("mmap_test passed!");
+    0
+}
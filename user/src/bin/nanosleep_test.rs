@@ -0,0 +1,95 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    fork,
+    getpid,
+    exit,
+    kill,
+    sleep,
+    get_time,
+    nanosleep,
+    clock_nanosleep,
+    sigaction,
+    wait_exit_code,
+    SignalAction,
+    SignalFlags,
+    TimeSpec,
+    Errno,
+    CLOCK_MONOTONIC,
+    TIMER_ABSTIME,
+};
+use user_lib::signal::SIGUSR1;
+
+extern "C" fn on_user_signal(_signum: usize) {}
+
+/// Fork a child which sends SIGUSR1 to this process after `ms` milliseconds.
+fn interrupt_after(ms: usize) -> usize {
+    let ppid = getpid() as usize;
+    let pid = fork().unwrap();
+    if pid == 0 {
+        sleep(ms);
+        kill(ppid, SIGUSR1).unwrap();
+        exit(0);
+    }
+    pid
+}
+
+fn duration_test() {
+    let start = get_time();
+    assert_eq!(nanosleep(&TimeSpec::from_ms(50), None), Ok(()));
+    assert!(get_time() - start >= 50);
+    let invalid = TimeSpec { sec: 0, nsec: 1_000_000_000 };
+    assert_eq!(nanosleep(&invalid, None), Err(Errno::EINVAL));
+    assert_eq!(clock_nanosleep(CLOCK_MONOTONIC, 0, &invalid, None), Err(Errno::EINVAL));
+    println!("nanosleep duration test passed!");
+}
+
+/// A signal cuts the sleep short, and the time left goes to `rem`.
+fn interrupt_test() {
+    let req = TimeSpec { sec: 2, nsec: 0 };
+    let pid = interrupt_after(50);
+    let mut rem = TimeSpec::default();
+    assert_eq!(nanosleep(&req, Some(&mut rem)), Err(Errno::EINTR));
+    assert!(rem != TimeSpec::default() && rem.sec < req.sec);
+    assert_eq!(wait_exit_code(pid), Ok(0));
+    let pid = interrupt_after(50);
+    let mut rem = TimeSpec::default();
+    assert_eq!(clock_nanosleep(CLOCK_MONOTONIC, 0, &req, Some(&mut rem)), Err(Errno::EINTR));
+    assert!(rem != TimeSpec::default() && rem.sec < req.sec);
+    assert_eq!(wait_exit_code(pid), Ok(0));
+    println!("nanosleep interrupt test passed!");
+}
+
+/// A sleep too long to be counted in cycles lasts until it is interrupted.
+fn overflow_test() {
+    let req = TimeSpec { sec: usize::MAX / 2, nsec: 999_999_999 };
+    let pid = interrupt_after(50);
+    let mut rem = TimeSpec::default();
+    assert_eq!(nanosleep(&req, Some(&mut rem)), Err(Errno::EINTR));
+    assert!(rem.sec > 1_000_000_000);
+    assert_eq!(wait_exit_code(pid), Ok(0));
+    println!("nanosleep overflow test passed!");
+}
+
+fn deadline_test() {
+    let deadline = get_time() as usize + 50;
+    assert_eq!(clock_nanosleep(CLOCK_MONOTONIC, TIMER_ABSTIME, &TimeSpec::from_ms(deadline), None), Ok(()));
+    assert!(get_time() as usize >= deadline);
+    println!("clock_nanosleep deadline test passed!");
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let action = SignalAction::new(on_user_signal, SignalFlags::empty());
+    sigaction(SIGUSR1, Some(&action), None).unwrap();
+    duration_test();
+    interrupt_test();
+    overflow_test();
+    deadline_test();
+    println!("nanosleep_test passed!");
+    0
+}
@@ -0,0 +1,35 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::task_list;
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let infos = match task_list() {
+        Ok(infos) => infos,
+        Err(errno) => {
+            println!("ps: {}", errno);
+            return 1;
+        }
+    };
+    println!("  PID   TID  PPID  PGID S      VSZ      RSS  TIME(ms) CMD");
+    for info in infos.iter() {
+        let ppid = if info.ppid == usize::MAX { -1 } else { info.ppid as isize };
+        println!(
+            "{:>5} {:>5} {:>5} {:>5} {} {:>8} {:>8} {:>9} {}",
+            info.pid,
+            info.tid,
+            ppid,
+            info.pgid,
+            info.state_char(),
+            info.vsz / 1024,
+            info.rss / 1024,
+            (info.utime.as_us() + info.stime.as_us()) / 1000,
+            info.name(),
+        );
+    }
+    0
+}
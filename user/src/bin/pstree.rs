@@ -0,0 +1,52 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+extern crate alloc;
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use user_lib::{task_list, TaskInfo};
+
+/// Print `process` and its children below it, indented by depth.
+fn print_tree(processes: &[&TaskInfo], threads: &[TaskInfo], process: &TaskInfo, prefix: &mut String) {
+    let thread_count = threads.iter().filter(|info| info.pid == process.pid).count();
+    if thread_count > 1 {
+        println!("{}({}) {{{} threads}}", process.name(), process.pid, thread_count);
+    } else {
+        println!("{}({})", process.name(), process.pid);
+    }
+    let children: Vec<&TaskInfo> = processes
+        .iter()
+        .copied()
+        .filter(|info| info.ppid == process.pid)
+        .collect();
+    for (i, child) in children.iter().enumerate() {
+        let last = i + 1 == children.len();
+        print!("{}{}", prefix, if last { "`-- " } else { "|-- " });
+        let len = prefix.len();
+        prefix.push_str(if last { "    " } else { "|   " });
+        print_tree(processes, threads, child, prefix);
+        prefix.truncate(len);
+    }
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let threads = match task_list() {
+        Ok(infos) => infos,
+        Err(errno) => {
+            println!("pstree: {}", errno);
+            return 1;
+        }
+    };
+    // each process is shown once, by its main thread
+    let processes: Vec<&TaskInfo> = threads.iter().filter(|info| info.tid == 0).collect();
+    for root in processes
+        .iter()
+        .filter(|info| !processes.iter().any(|parent| parent.pid == info.ppid)) {
+        print_tree(&processes, &threads, root, &mut String::new());
+    }
+    0
+}
@@ -0,0 +1,44 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{fork, exit, wait, sleep, get_time};
+
+/// CPU-bound workers competing with a thread which sleeps most of the time.
+/// Under the stride and MLFQ schedulers the sleeper runs soon after it wakes up,
+/// whatever the number of workers. Run by `usertests stride` and `usertests mlfq`.
+const WORKERS: usize = 4;
+const WORK_MS: isize = 1500;
+const SLEEP_MS: usize = 10;
+const ROUNDS: usize = 50;
+/// Allowed delay between the end of a sleep and the sleeper running again.
+const MAX_LATENCY_MS: isize = 30;
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let end = get_time() + WORK_MS;
+    for _ in 0..WORKERS {
+        if fork().unwrap() == 0 {
+            while get_time() < end {}
+            exit(0);
+        }
+    }
+    let mut worst = 0;
+    for _ in 0..ROUNDS {
+        let start = get_time();
+        sleep(SLEEP_MS);
+        let latency = get_time() - start - SLEEP_MS as isize;
+        worst = worst.max(latency);
+    }
+    println!("worst wakeup latency: {} ms", worst);
+    let mut exit_code = 0;
+    for _ in 0..WORKERS {
+        wait(&mut exit_code).unwrap();
+        assert_eq!(exit_code, 0);
+    }
+    assert!(worst <= MAX_LATENCY_MS, "the sleeper waited {} ms to run", worst);
+    println!("sched_latency passed!");
+    0
+}
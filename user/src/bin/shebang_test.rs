@@ -0,0 +1,84 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    spawn,
+    fork,
+    exec,
+    exit,
+    waitpid,
+    wexitstatus,
+    wait_exit_code,
+    write_file,
+    Errno,
+};
+
+const SCRIPT: &str = "shebang_script\0";
+const NESTED: &str = "shebang_nested\0";
+const SPACED: &str = "shebang_spaced\0";
+const SHELL_SCRIPT: &str = "shebang_shell\0";
+const LOOP: &str = "shebang_loop\0";
+const MISSING: &str = "shebang_missing\0";
+const EMPTY: &str = "shebang_empty\0";
+const EXTRA: &str = "extra\0";
+
+fn run(path: &str, extra: bool) -> i32 {
+    let args = if extra {
+        [path.as_ptr(), EXTRA.as_ptr(), 0 as *const u8]
+    } else {
+        [path.as_ptr(), 0 as *const u8, 0 as *const u8]
+    };
+    wait_exit_code(spawn(path, &args, &[]).unwrap()).unwrap()
+}
+
+/// Run by the scripts above as their interpreter, tell which argv we got.
+fn interpret(argv: &[&str]) -> i32 {
+    match argv {
+        ["interp", "shebang_script", "extra"] => 1,
+        ["interp", "shebang_script", "shebang_nested", "extra"] => 2,
+        ["interp  twice", "shebang_spaced"] => 3,
+        _ => 100,
+    }
+}
+
+#[no_mangle]
+pub fn main(argc: usize, argv: &[&str]) -> i32 {
+    if argc > 1 {
+        return interpret(&argv[1..]);
+    }
+    write_file(SCRIPT, b"#!shebang_test interp\nnot read by anyone\n").unwrap();
+    write_file(NESTED, b"#!shebang_script\n").unwrap();
+    write_file(SPACED, b"#!  shebang_test   interp  twice  \n").unwrap();
+    write_file(SHELL_SCRIPT, b"#!user_shell\n# a comment\n\nshebang_test interp shebang_script extra\n").unwrap();
+    write_file(LOOP, b"#!shebang_loop\n").unwrap();
+    write_file(MISSING, b"#!no_such_interpreter\n").unwrap();
+    write_file(EMPTY, b"#!\n").unwrap();
+
+    assert_eq!(run(SCRIPT, true), 1);
+    assert_eq!(run(NESTED, true), 2);
+    assert_eq!(run(SPACED, false), 3);
+    // the shell exits with the status of the last command
+    assert_eq!(run(SHELL_SCRIPT, false), 1);
+
+    // exec goes the same way
+    let pid = fork().unwrap();
+    if pid == 0 {
+        exec(SCRIPT, &[SCRIPT.as_ptr(), EXTRA.as_ptr(), 0 as *const u8]).unwrap();
+        exit(100);
+    }
+    let mut exit_code: i32 = 0;
+    assert_eq!(waitpid(pid, &mut exit_code), Ok(pid));
+    assert_eq!(wexitstatus(exit_code), 1);
+
+    let args = [0 as *const u8];
+    assert_eq!(spawn(LOOP, &args, &[]), Err(Errno::ELOOP));
+    assert_eq!(exec(LOOP, &args), Err(Errno::ELOOP));
+    assert_eq!(spawn(MISSING, &args, &[]), Err(Errno::ENOENT));
+    assert_eq!(spawn(EMPTY, &args, &[]), Err(Errno::ENOEXEC));
+    assert_eq!(exec(EMPTY, &args), Err(Errno::ENOEXEC));
+    println!("shebang_test passed!");
+    0
+}
@@ -0,0 +1,101 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    fork,
+    waitpid,
+    getpid,
+    exit,
+    kill,
+    sigaction,
+    sigprocmask,
+    SignalAction,
+    SignalFlags,
+    SigMaskHow,
+    Errno,
+    wexitstatus,
+    wifsignaled,
+    wtermsig,
+};
+use user_lib::signal::{SIGUSR1, SIGUSR2, SIGSEGV, SIGKILL};
+
+static mut CAUGHT: usize = 0;
+
+extern "C" fn on_user_signal(signum: usize) {
+    unsafe { CAUGHT = signum; }
+}
+
+extern "C" fn on_segv(signum: usize) {
+    assert_eq!(signum, SIGSEGV);
+    exit(42);
+}
+
+fn caught() -> usize {
+    unsafe { core::ptr::read_volatile(&CAUGHT) }
+}
+
+fn handler_test() {
+    let action = SignalAction::new(on_user_signal, SignalFlags::empty());
+    sigaction(SIGUSR1, Some(&action), None).unwrap();
+    kill(getpid() as usize, SIGUSR1).unwrap();
+    assert_eq!(caught(), SIGUSR1);
+    println!("signal handler test passed!");
+}
+
+fn mask_test() {
+    unsafe { CAUGHT = 0; }
+    let action = SignalAction::new(on_user_signal, SignalFlags::empty());
+    sigaction(SIGUSR2, Some(&action), None).unwrap();
+    sigprocmask(SigMaskHow::Block, Some(SignalFlags::SIGUSR2), None).unwrap();
+    kill(getpid() as usize, SIGUSR2).unwrap();
+    assert_eq!(caught(), 0);
+    let mut old_set = SignalFlags::empty();
+    sigprocmask(SigMaskHow::Unblock, Some(SignalFlags::SIGUSR2), Some(&mut old_set)).unwrap();
+    assert!(old_set.contains(SignalFlags::SIGUSR2));
+    assert_eq!(caught(), SIGUSR2);
+    println!("signal mask test passed!");
+}
+
+fn segv_test() {
+    let pid = fork().unwrap();
+    if pid == 0 {
+        let action = SignalAction::new(on_segv, SignalFlags::empty());
+        sigaction(SIGSEGV, Some(&action), None).unwrap();
+        unsafe { (0x20_0000_0000 as *mut u8).write_volatile(0); }
+        unreachable!();
+    }
+    let mut exit_code: i32 = 0;
+    assert_eq!(waitpid(pid, &mut exit_code), Ok(pid));
+    assert_eq!(wexitstatus(exit_code), 42);
+    println!("catch SIGSEGV test passed!");
+}
+
+fn kill_test() {
+    let pid = fork().unwrap();
+    if pid == 0 {
+        loop {}
+    }
+    assert_eq!(
+        sigaction(SIGKILL, Some(&SignalAction::ignore()), None),
+        Err(Errno::EINVAL)
+    );
+    kill(pid, SIGKILL).unwrap();
+    let mut exit_code: i32 = 0;
+    assert_eq!(waitpid(pid, &mut exit_code), Ok(pid));
+    assert!(wifsignaled(exit_code) && wtermsig(exit_code) == SIGKILL);
+    assert_eq!(kill(pid, SIGKILL), Err(Errno::ESRCH));
+    println!("kill test passed!");
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    handler_test();
+    mask_test();
+    segv_test();
+    kill_test();
+    println!("sig_tests passed!");
+    0
+}
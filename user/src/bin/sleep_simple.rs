@@ -14,6 +14,7 @@ pub fn main() -> i32 {
     sleep(100);
     let end = get_time();
     println!("time_msec = {} after sleeping 100 ticks, delta = {}ms!", end, end - start);
+    assert!(end - start >= 100);
     println!("r_sleep passed!");
     0
 }
\ No newline at end of file
@@ -0,0 +1,92 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+extern crate alloc;
+
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use user_lib::{
+    thread, fork, exit, waitpid, sleep, mmap, munmap, MmapProt,
+    wexitstatus, wifsignaled, wtermsig,
+};
+use user_lib::signal::SIGSEGV;
+
+/// Run with more than one hart (`make run SMP=4`), the threads below
+/// then really run at the same time.
+const THREAD_NUM: usize = 4;
+const PER_THREAD: usize = 1_000_000;
+const PAGE_SIZE: usize = 0x1000;
+
+static COUNTER: AtomicUsize = AtomicUsize::new(0);
+static STARTED: AtomicUsize = AtomicUsize::new(0);
+
+/// Threads which never yield still all make progress.
+fn parallel_test() {
+    let handles: Vec<_> = (0..THREAD_NUM)
+        .map(|_| thread::spawn(|| {
+            for _ in 0..PER_THREAD {
+                COUNTER.fetch_add(1, Ordering::Relaxed);
+            }
+            0
+        }).unwrap())
+        .collect();
+    for handle in handles {
+        assert_eq!(handle.join(), Ok(0));
+    }
+    assert_eq!(COUNTER.load(Ordering::SeqCst), THREAD_NUM * PER_THREAD);
+    println!("parallel test passed!");
+}
+
+/// A page unmapped by one thread is gone for the threads running on other
+/// harts as well, even if they had it in their TLB.
+fn shootdown_test() {
+    let pid = fork().unwrap();
+    if pid == 0 {
+        let base = mmap(0, PAGE_SIZE, MmapProt::READ | MmapProt::WRITE).unwrap();
+        unsafe { (base as *mut usize).write_volatile(1); }
+        for _ in 0..THREAD_NUM {
+            thread::spawn(move || {
+                STARTED.fetch_add(1, Ordering::SeqCst);
+                loop {
+                    assert_eq!(unsafe { (base as *const usize).read_volatile() }, 1);
+                }
+            }).unwrap();
+        }
+        while STARTED.load(Ordering::SeqCst) < THREAD_NUM {}
+        munmap(base, PAGE_SIZE).unwrap();
+        // the readers fault right away, killing the process
+        sleep(1000);
+        exit(0);
+    }
+    let mut exit_code: i32 = 0;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), Ok(pid));
+    assert!(wifsignaled(exit_code) && wtermsig(exit_code) == SIGSEGV);
+    println!("shootdown test passed!");
+}
+
+/// Exiting stops the other threads, which are busy on other harts.
+fn exit_test() {
+    let pid = fork().unwrap();
+    if pid == 0 {
+        for _ in 0..THREAD_NUM {
+            thread::spawn(|| loop {}).unwrap();
+        }
+        sleep(100);
+        exit(3);
+    }
+    let mut exit_code: i32 = 0;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), Ok(pid));
+    assert_eq!(wexitstatus(exit_code), 3);
+    println!("exit test passed!");
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    parallel_test();
+    shootdown_test();
+    exit_test();
+    println!("smp_test passed!");
+    0
+}
@@ -0,0 +1,76 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    spawn,
+    wait_exit_code,
+    SpawnAction,
+    wait,
+    pipe,
+    read,
+    close,
+    open,
+    OpenFlags,
+    Errno,
+};
+
+const HELLO: &str = "Hello world from user mode program!\n";
+
+fn read_all(fd: usize, buffer: &mut [u8]) -> usize {
+    let mut len = 0;
+    loop {
+        match read(fd, &mut buffer[len..]).unwrap() {
+            0 => break,
+            n => len += n,
+        }
+    }
+    len
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    // arguments, with the fd table inherited as it is
+    let args = ["cmdline_args\0".as_ptr(), "spawned\0".as_ptr(), 0 as *const u8];
+    let pid = spawn("cmdline_args\0", &args, &[]).unwrap();
+    assert_eq!(wait_exit_code(pid), Ok(0));
+
+    // stdout of the child goes into a pipe, the parent keeps its own
+    let mut pipe_fd = [0usize; 2];
+    pipe(&mut pipe_fd).unwrap();
+    let pid = spawn(
+        "hello_world\0",
+        &[0 as *const u8],
+        &[SpawnAction::dup2(pipe_fd[1], 1), SpawnAction::close(pipe_fd[1]), SpawnAction::close(pipe_fd[0])],
+    ).unwrap();
+    close(pipe_fd[1]).unwrap();
+    let mut buffer = [0u8; 64];
+    let len = read_all(pipe_fd[0], &mut buffer);
+    close(pipe_fd[0]).unwrap();
+    assert_eq!(&buffer[..len], HELLO.as_bytes());
+    assert_eq!(wait_exit_code(pid), Ok(0));
+
+    // stdout of the child goes into a file opened for it
+    let pid = spawn(
+        "hello_world\0",
+        &[0 as *const u8],
+        &[SpawnAction::open(1, "spawn_test_output\0", OpenFlags::CREATE | OpenFlags::WRONLY)],
+    ).unwrap();
+    assert_eq!(wait_exit_code(pid), Ok(0));
+    let fd = open("spawn_test_output\0", OpenFlags::RDONLY).unwrap();
+    let len = read_all(fd, &mut buffer);
+    close(fd).unwrap();
+    assert_eq!(&buffer[..len], HELLO.as_bytes());
+
+    // failures leave no child behind
+    assert_eq!(spawn("no_such_file\0", &[0 as *const u8], &[]), Err(Errno::ENOENT));
+    assert_eq!(spawn("hello_world\0", &[0 as *const u8], &[SpawnAction::close(42)]), Err(Errno::EBADF));
+    assert_eq!(spawn("hello_world\0", &[0 as *const u8], &[SpawnAction::dup2(42, 3)]), Err(Errno::EBADF));
+    let mut exit_code: i32 = 0;
+    assert_eq!(wait(&mut exit_code), Err(Errno::ECHILD));
+
+    println!("spawn_test passed!");
+    0
+}
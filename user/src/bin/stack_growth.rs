@@ -0,0 +1,112 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    fork, exit, waitpid, wexitstatus, wifsignaled, wtermsig, thread,
+    pipe, read, write, close, getrlimit, setrlimit, RLimit, RLIMIT_STACK, Errno,
+};
+use user_lib::signal::SIGSEGV;
+use core::mem::MaybeUninit;
+
+const KIB: usize = 1024;
+
+/// Use about `depth` KiB of stack.
+fn recurse(depth: usize) -> usize {
+    let mut buffer = [0u8; KIB];
+    buffer[depth % KIB] = depth as u8;
+    let buffer = unsafe { core::ptr::read_volatile(&buffer) };
+    if depth == 0 {
+        0
+    } else {
+        recurse(depth - 1) + buffer[depth % KIB] as usize
+    }
+}
+
+fn expected(depth: usize) -> usize {
+    (1..=depth).map(|d| d as u8 as usize).sum()
+}
+
+fn set_stack_limit(limit: usize) {
+    let mut rlim = RLimit::default();
+    getrlimit(RLIMIT_STACK, &mut rlim).unwrap();
+    rlim.rlim_cur = limit;
+    setrlimit(RLIMIT_STACK, &rlim).unwrap();
+}
+
+fn run_child(f: fn()) -> i32 {
+    let pid = fork().unwrap();
+    if pid == 0 {
+        f();
+        exit(0);
+    }
+    let mut exit_code: i32 = 0;
+    assert_eq!(waitpid(pid, &mut exit_code), Ok(pid));
+    exit_code
+}
+
+/// Far beyond the initial 8 KiB, within the default limit.
+fn growth_test() {
+    assert_eq!(recurse(40), expected(40));
+    let handle = thread::spawn(|| recurse(40) as i32).unwrap();
+    assert_eq!(handle.join(), Ok(expected(40) as i32));
+    println!("growth test passed!");
+}
+
+fn limit_test() {
+    // a raised limit lets the stack grow further
+    let exit_code = run_child(|| {
+        set_stack_limit(512 * KIB);
+        assert_eq!(recurse(400), expected(400));
+    });
+    assert_eq!(wexitstatus(exit_code), 0);
+    // going beyond the limit ends with SIGSEGV
+    let exit_code = run_child(|| {
+        set_stack_limit(32 * KIB);
+        recurse(64);
+    });
+    assert!(wifsignaled(exit_code) && wtermsig(exit_code) == SIGSEGV);
+    println!("limit test passed!");
+}
+
+/// The kernel writes to a part of the stack which has not been touched yet.
+fn kernel_access_test() {
+    fn read_far_below() {
+        let mut pipe_fd = [0usize; 2];
+        pipe(&mut pipe_fd).unwrap();
+        assert_eq!(write(pipe_fd[1], b"deep"), Ok(4));
+        close(pipe_fd[1]).unwrap();
+        // left uninitialized so that its lowest pages are not touched before read
+        let mut buffer: MaybeUninit<[u8; 32 * KIB]> = MaybeUninit::uninit();
+        let bottom = unsafe { core::slice::from_raw_parts_mut(buffer.as_mut_ptr() as *mut u8, 4) };
+        assert_eq!(read(pipe_fd[0], bottom), Ok(4));
+        assert_eq!(bottom, b"deep");
+        close(pipe_fd[0]).unwrap();
+    }
+    assert_eq!(wexitstatus(run_child(read_far_below)), 0);
+    println!("kernel access test passed!");
+}
+
+fn error_test() {
+    let mut rlim = RLimit::default();
+    assert_eq!(getrlimit(0, &mut rlim), Err(Errno::EINVAL));
+    getrlimit(RLIMIT_STACK, &mut rlim).unwrap();
+    assert!(rlim.rlim_cur <= rlim.rlim_max);
+    let bad = RLimit { rlim_cur: rlim.rlim_max + 1, rlim_max: rlim.rlim_max };
+    assert_eq!(setrlimit(RLIMIT_STACK, &bad), Err(Errno::EINVAL));
+    let bad = RLimit { rlim_cur: rlim.rlim_cur, rlim_max: rlim.rlim_max * 2 };
+    assert_eq!(setrlimit(RLIMIT_STACK, &bad), Err(Errno::EPERM));
+    println!("error test passed!");
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    growth_test();
+    limit_test();
+    kernel_access_test();
+    error_test();
+    println!("stack_growth passed!");
+    0
+}
@@ -0,0 +1,86 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{fork, exit, wait, get_time, set_priority, pipe, read, write, close, Errno};
+
+/// Under the stride scheduler, each worker gets a share of the processor
+/// proportional to its priority during the window. Run by `usertests stride`
+/// on a kernel built with `make run SCHED=stride`.
+const PRIORITIES: [isize; 6] = [5, 6, 7, 8, 9, 10];
+const WINDOW_MS: isize = 2000;
+/// Time for all the workers to be forked before the window starts.
+const START_DELAY_MS: isize = 200;
+/// Allowed deviation of a worker from the mean share per priority, in percent.
+const TOLERANCE: usize = 30;
+
+fn worker(prio: isize, start: isize, write_fd: usize) -> ! {
+    set_priority(prio).unwrap();
+    while get_time() < start {}
+    let mut count = 0usize;
+    while get_time() < start + WINDOW_MS {
+        count += 1;
+    }
+    let report = [prio as usize, count];
+    let bytes = unsafe {
+        core::slice::from_raw_parts(report.as_ptr() as *const u8, core::mem::size_of_val(&report))
+    };
+    assert_eq!(write(write_fd, bytes), Ok(bytes.len()));
+    exit(0);
+}
+
+fn read_report(read_fd: usize) -> [usize; 2] {
+    let mut report = [0usize; 2];
+    let bytes = unsafe {
+        core::slice::from_raw_parts_mut(report.as_mut_ptr() as *mut u8, core::mem::size_of_val(&report))
+    };
+    let mut done = 0;
+    while done < bytes.len() {
+        let len = read(read_fd, &mut bytes[done..]).unwrap();
+        assert!(len > 0, "a worker exited without a report");
+        done += len;
+    }
+    report
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    assert_eq!(set_priority(1), Err(Errno::EINVAL));
+    assert_eq!(set_priority(-1), Err(Errno::EINVAL));
+    assert_eq!(set_priority(isize::MAX), Err(Errno::EINVAL));
+    assert_eq!(set_priority(1 << 16), Ok(1 << 16));
+    assert_eq!(set_priority(16), Ok(16));
+    let mut pipe_fd = [0usize; 2];
+    pipe(&mut pipe_fd).unwrap();
+    let start = get_time() + START_DELAY_MS;
+    for prio in PRIORITIES.iter() {
+        if fork().unwrap() == 0 {
+            close(pipe_fd[0]).unwrap();
+            worker(*prio, start, pipe_fd[1]);
+        }
+    }
+    close(pipe_fd[1]).unwrap();
+    let mut shares = [0usize; PRIORITIES.len()];
+    for share in shares.iter_mut() {
+        let [prio, count] = read_report(pipe_fd[0]);
+        println!("priority {}: {} loops", prio, count);
+        *share = count / prio;
+    }
+    close(pipe_fd[0]).unwrap();
+    let mut exit_code = 0;
+    for _ in PRIORITIES.iter() {
+        wait(&mut exit_code).unwrap();
+        assert_eq!(exit_code, 0);
+    }
+    let mean = shares.iter().sum::<usize>() / shares.len();
+    for share in shares.iter() {
+        assert!(
+            *share * 100 >= mean * (100 - TOLERANCE) && *share * 100 <= mean * (100 + TOLERANCE),
+            "loops per priority {} too far from the mean {}", share, mean,
+        );
+    }
+    println!("stride_test passed!");
+    0
+}
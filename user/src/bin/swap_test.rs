@@ -0,0 +1,103 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{fork, exit, waitpid, wexitstatus, pipe, read, write, close, mmap, munmap, MmapProt};
+
+const PAGE_SIZE: usize = 0x1000;
+/// More than the free memory of the machine, so that pages have to be swapped out.
+const SIZE: usize = 6 << 20;
+const PAGE_COUNT: usize = SIZE / PAGE_SIZE;
+
+fn page(base: usize, i: usize) -> *mut usize {
+    (base + i * PAGE_SIZE) as *mut usize
+}
+
+fn pattern(i: usize, round: usize) -> usize {
+    i.wrapping_mul(0x9e37_79b9) ^ round
+}
+
+/// The first and the last word of the page are both checked.
+fn fill(base: usize, i: usize, round: usize) {
+    let words = PAGE_SIZE / core::mem::size_of::<usize>();
+    unsafe {
+        page(base, i).write_volatile(pattern(i, round));
+        page(base, i).add(words - 1).write_volatile(!pattern(i, round));
+    }
+}
+
+fn check(base: usize, i: usize, round: usize) {
+    let words = PAGE_SIZE / core::mem::size_of::<usize>();
+    unsafe {
+        assert_eq!(page(base, i).read_volatile(), pattern(i, round), "page {}", i);
+        assert_eq!(page(base, i).add(words - 1).read_volatile(), !pattern(i, round), "page {}", i);
+    }
+}
+
+fn swap_test(base: usize) {
+    for i in 0..PAGE_COUNT {
+        fill(base, i, 0);
+    }
+    // twice, so that every page has to come back from swap at least once
+    for _ in 0..2 {
+        for i in 0..PAGE_COUNT {
+            check(base, i, 0);
+        }
+    }
+    println!("swap test passed!");
+}
+
+/// The kernel reads and writes pages which have been swapped out.
+fn kernel_access_test(base: usize) {
+    let mut pipe_fd = [0usize; 2];
+    pipe(&mut pipe_fd).unwrap();
+    let src = unsafe { core::slice::from_raw_parts(page(base, 1) as *const u8, PAGE_SIZE) };
+    assert_eq!(write(pipe_fd[1], src), Ok(PAGE_SIZE));
+    close(pipe_fd[1]).unwrap();
+    let dst = unsafe { core::slice::from_raw_parts_mut(page(base, 0) as *mut u8, PAGE_SIZE) };
+    assert_eq!(read(pipe_fd[0], dst), Ok(PAGE_SIZE));
+    close(pipe_fd[0]).unwrap();
+    assert_eq!(unsafe { page(base, 0).read_volatile() }, pattern(1, 0));
+    fill(base, 0, 0);
+    for i in 0..PAGE_COUNT {
+        check(base, i, 0);
+    }
+    println!("kernel access test passed!");
+}
+
+/// Swapped out pages are inherited by fork, each side reads its own copy back.
+fn fork_test(base: usize) {
+    let pid = fork().unwrap();
+    if pid == 0 {
+        for i in 0..PAGE_COUNT {
+            check(base, i, 0);
+        }
+        for i in (0..PAGE_COUNT).step_by(4) {
+            fill(base, i, 1);
+        }
+        for i in 0..PAGE_COUNT {
+            check(base, i, if i % 4 == 0 { 1 } else { 0 });
+        }
+        exit(0);
+    }
+    let mut exit_code: i32 = 0;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), Ok(pid));
+    assert_eq!(wexitstatus(exit_code), 0);
+    for i in 0..PAGE_COUNT {
+        check(base, i, 0);
+    }
+    println!("fork test passed!");
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let base = mmap(0, SIZE, MmapProt::READ | MmapProt::WRITE).unwrap();
+    swap_test(base);
+    kernel_access_test(base);
+    fork_test(base);
+    munmap(base, SIZE).unwrap();
+    println!("swap_test passed!");
+    0
+}
@@ -0,0 +1,137 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+extern crate alloc;
+
+use alloc::vec::Vec;
+use user_lib::{
+    thread,
+    yield_,
+    mutex_create,
+    mutex_blocking_create,
+    mutex_lock,
+    mutex_unlock,
+    semaphore_create,
+    semaphore_up,
+    semaphore_down,
+    condvar_create,
+    condvar_signal,
+    condvar_wait,
+    Errno,
+};
+
+const THREAD_NUM: usize = 4;
+const PER_THREAD: usize = 50;
+
+static mut COUNTER: usize = 0;
+
+/// A non-atomic read-modify-write which is only correct under the lock.
+fn critical_add() {
+    unsafe {
+        let value = core::ptr::read_volatile(&COUNTER);
+        yield_();
+        core::ptr::write_volatile(&mut COUNTER, value + 1);
+    }
+}
+
+fn mutex_test(mutex_id: usize) {
+    unsafe { COUNTER = 0; }
+    let handles: Vec<_> = (0..THREAD_NUM)
+        .map(|_| thread::spawn(move || {
+            for _ in 0..PER_THREAD {
+                mutex_lock(mutex_id).unwrap();
+                critical_add();
+                mutex_unlock(mutex_id).unwrap();
+            }
+            0
+        }).unwrap())
+        .collect();
+    for handle in handles {
+        assert_eq!(handle.join(), Ok(0));
+    }
+    assert_eq!(unsafe { COUNTER }, THREAD_NUM * PER_THREAD);
+}
+
+const BUFFER_SIZE: usize = 4;
+const ITEMS: usize = 32;
+static mut BUFFER: [usize; BUFFER_SIZE] = [0; BUFFER_SIZE];
+
+fn semaphore_test() {
+    let empty = semaphore_create(BUFFER_SIZE).unwrap();
+    let full = semaphore_create(0).unwrap();
+    let producer = thread::spawn(move || {
+        for i in 0..ITEMS {
+            semaphore_down(empty).unwrap();
+            unsafe { BUFFER[i % BUFFER_SIZE] = i; }
+            semaphore_up(full).unwrap();
+        }
+        0
+    }).unwrap();
+    let consumer = thread::spawn(move || {
+        let mut sum = 0;
+        for i in 0..ITEMS {
+            semaphore_down(full).unwrap();
+            sum += unsafe { BUFFER[i % BUFFER_SIZE] };
+            semaphore_up(empty).unwrap();
+        }
+        sum as i32
+    }).unwrap();
+    assert_eq!(producer.join(), Ok(0));
+    assert_eq!(consumer.join(), Ok((ITEMS * (ITEMS - 1) / 2) as i32));
+    println!("semaphore test passed!");
+}
+
+static mut READY: bool = false;
+
+fn condvar_test() {
+    let mutex_id = mutex_blocking_create().unwrap();
+    let condvar_id = condvar_create().unwrap();
+    let waiter = thread::spawn(move || {
+        mutex_lock(mutex_id).unwrap();
+        while !unsafe { core::ptr::read_volatile(&READY) } {
+            condvar_wait(condvar_id, mutex_id).unwrap();
+        }
+        mutex_unlock(mutex_id).unwrap();
+        0
+    }).unwrap();
+    // let the waiter go to sleep first
+    yield_();
+    mutex_lock(mutex_id).unwrap();
+    unsafe { core::ptr::write_volatile(&mut READY, true); }
+    condvar_signal(condvar_id).unwrap();
+    mutex_unlock(mutex_id).unwrap();
+    assert_eq!(waiter.join(), Ok(0));
+    println!("condvar test passed!");
+}
+
+fn error_test() {
+    assert_eq!(mutex_lock(100), Err(Errno::EINVAL));
+    assert_eq!(semaphore_up(100), Err(Errno::EINVAL));
+    assert_eq!(condvar_signal(100), Err(Errno::EINVAL));
+    let mutex_id = mutex_blocking_create().unwrap();
+    assert_eq!(mutex_unlock(mutex_id), Err(Errno::EPERM));
+    // only the thread holding the lock may release it
+    mutex_lock(mutex_id).unwrap();
+    let other = thread::spawn(move || {
+        assert_eq!(mutex_unlock(mutex_id), Err(Errno::EPERM));
+        0
+    }).unwrap();
+    assert_eq!(other.join(), Ok(0));
+    mutex_unlock(mutex_id).unwrap();
+    println!("error test passed!");
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    mutex_test(mutex_create().unwrap());
+    println!("spin mutex test passed!");
+    mutex_test(mutex_blocking_create().unwrap());
+    println!("blocking mutex test passed!");
+    semaphore_test();
+    condvar_test();
+    error_test();
+    println!("sync_tests passed!");
+    0
+}
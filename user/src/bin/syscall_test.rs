@@ -0,0 +1,93 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    syscall_stats,
+    SyscallStat,
+    getpid,
+    fork,
+    kill,
+    exit,
+    sleep,
+    waitpid,
+    clock_nanosleep,
+    sigaction,
+    SignalAction,
+    SignalFlags,
+    TimeSpec,
+    Errno,
+    CLOCK_MONOTONIC,
+    TIMER_ABSTIME,
+    PERSONALITY_NATIVE,
+    PERSONALITY_LINUX,
+};
+use user_lib::signal::SIGUSR1;
+
+const SYSCALL_GETPID: usize = 172;
+const SYSCALL_OPENAT: usize = 56;
+
+fn find(stats: &[SyscallStat], personality: usize, id: usize) -> SyscallStat {
+    *stats.iter().find(|stat| stat.personality == personality && stat.id == id).unwrap()
+}
+
+fn stats_test() {
+    let before = syscall_stats().unwrap();
+    for _ in 0..10 {
+        getpid();
+    }
+    let after = syscall_stats().unwrap();
+    assert_eq!(before.len(), after.len());
+    let (getpid_before, getpid_after) = (
+        find(&before, PERSONALITY_NATIVE, SYSCALL_GETPID),
+        find(&after, PERSONALITY_NATIVE, SYSCALL_GETPID),
+    );
+    assert_eq!(getpid_after.name(), "getpid");
+    // other processes may make system calls meanwhile
+    assert!(getpid_after.count >= getpid_before.count + 10);
+    // both tables are there, each sorted by id
+    let openat = find(&after, PERSONALITY_LINUX, SYSCALL_OPENAT);
+    assert_eq!(openat.name(), "openat");
+    for table in [PERSONALITY_NATIVE, PERSONALITY_LINUX].iter() {
+        let ids = after.iter().filter(|stat| stat.personality == *table).map(|stat| stat.id);
+        assert!(ids.clone().zip(ids.skip(1)).all(|(id, next)| id < next));
+    }
+    println!("syscall stats test passed!");
+}
+
+extern "C" fn on_user_signal(_signum: usize) {}
+
+/// clock_nanosleep takes four arguments, the last one being where the
+/// time left goes when a signal cuts the sleep short.
+fn six_args_test() {
+    let action = SignalAction::new(on_user_signal, SignalFlags::empty());
+    sigaction(SIGUSR1, Some(&action), None).unwrap();
+    let ppid = getpid() as usize;
+    let pid = fork().unwrap();
+    if pid == 0 {
+        sleep(50);
+        kill(ppid, SIGUSR1).unwrap();
+        exit(0);
+    }
+    let req = TimeSpec { sec: 5, nsec: 0 };
+    let mut rem = TimeSpec::default();
+    assert_eq!(clock_nanosleep(CLOCK_MONOTONIC, 0, &req, Some(&mut rem)), Err(Errno::EINTR));
+    assert!(rem != TimeSpec::default() && rem.sec < req.sec);
+    let mut exit_code: i32 = 0;
+    assert_eq!(waitpid(pid, &mut exit_code), Ok(pid));
+    // a deadline in the past is met at once, and leaves rem alone
+    let mut rem = TimeSpec { sec: 1, nsec: 1 };
+    assert_eq!(clock_nanosleep(CLOCK_MONOTONIC, TIMER_ABSTIME, &TimeSpec::default(), Some(&mut rem)), Ok(()));
+    assert_eq!(rem, TimeSpec { sec: 1, nsec: 1 });
+    println!("six arguments test passed!");
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    stats_test();
+    six_args_test();
+    println!("syscall_test passed!");
+    0
+}
@@ -0,0 +1,31 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{syscall_stats, PERSONALITY_LINUX};
+
+/// The system calls which have been made since boot, and how many times.
+/// `syscount -a` lists those which have not been made as well.
+#[no_mangle]
+pub fn main(argc: usize, argv: &[&str]) -> i32 {
+    let all = argc == 2 && argv[1] == "-a";
+    if argc > 2 || (argc == 2 && !all) {
+        println!("usage: syscount [-a]");
+        return 1;
+    }
+    let stats = match syscall_stats() {
+        Ok(stats) => stats,
+        Err(errno) => {
+            println!("syscount: {}", errno);
+            return 1;
+        }
+    };
+    println!("TABLE     ID NAME                        CALLS");
+    for stat in stats.iter().filter(|stat| all || stat.count > 0) {
+        let table = if stat.personality == PERSONALITY_LINUX { "linux" } else { "native" };
+        println!("{:<6} {:>5} {:<24} {:>8}", table, stat.id, stat.name(), stat.count);
+    }
+    0
+}
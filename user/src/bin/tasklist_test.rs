@@ -0,0 +1,83 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use core::sync::atomic::{AtomicBool, Ordering};
+use user_lib::{
+    fork,
+    getpid,
+    sleep,
+    yield_,
+    kill,
+    waitpid,
+    waitpid_with_options,
+    WaitOptions,
+    thread,
+    task_list,
+    TaskInfo,
+    TASK_RUNNING,
+    TASK_STOPPED,
+    TASK_ZOMBIE,
+};
+use user_lib::signal::{SIGSTOP, SIGKILL};
+
+static DONE: AtomicBool = AtomicBool::new(false);
+
+fn find(pid: usize, tid: usize) -> Option<TaskInfo> {
+    task_list().unwrap().into_iter().find(|info| info.pid == pid && info.tid == tid)
+}
+
+/// Reaped threads are only released once the hart they ran on has switched away.
+fn gone(pid: usize, tid: usize) -> bool {
+    find(pid, tid).map_or(true, |info| info.state == TASK_ZOMBIE)
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let pid = getpid() as usize;
+    let me = find(pid, 0).unwrap();
+    assert_eq!(me.state, TASK_RUNNING);
+    assert_eq!(me.name(), "tasklist_test");
+    assert!(me.rss > 0 && me.vsz >= me.rss);
+    assert!(me.utime.as_us() + me.stime.as_us() > 0);
+    // initproc has no parent
+    assert!(task_list().unwrap().iter().any(|info| info.ppid == usize::MAX && info.name() == "initproc"));
+
+    // a stopped child is listed under us
+    let child = fork().unwrap();
+    if child == 0 {
+        loop {
+            sleep(10);
+        }
+    }
+    let info = find(child, 0).unwrap();
+    assert_eq!(info.ppid, pid);
+    assert_eq!(info.name(), me.name());
+    kill(child, SIGSTOP).unwrap();
+    let mut exit_code: i32 = 0;
+    assert_eq!(waitpid_with_options(child as isize, &mut exit_code, WaitOptions::WUNTRACED), Ok(child));
+    // it may still be on its way to block on another hart
+    while find(child, 0).unwrap().state != TASK_STOPPED {
+        yield_();
+    }
+    kill(child, SIGKILL).unwrap();
+    assert_eq!(waitpid(child, &mut exit_code), Ok(child));
+    assert!(gone(child, 0));
+
+    // threads are listed one by one
+    let handle = thread::spawn(|| {
+        while !DONE.load(Ordering::Acquire) {
+            yield_();
+        }
+        0
+    }).unwrap();
+    let tid = handle.tid();
+    assert_eq!(find(pid, tid).unwrap().pgid, me.pgid);
+    DONE.store(true, Ordering::Release);
+    assert_eq!(handle.join(), Ok(0));
+    assert!(gone(pid, tid));
+    println!("tasklist_test passed!");
+    0
+}
@@ -0,0 +1,70 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+extern crate alloc;
+
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use user_lib::{thread, fork, waitpid, exit, yield_, waittid, Errno, wexitstatus};
+
+const THREAD_NUM: usize = 4;
+const PER_THREAD: usize = 100;
+
+static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+fn join_test() {
+    let handles: Vec<_> = (0..THREAD_NUM)
+        .map(|i| thread::spawn(move || {
+            for _ in 0..PER_THREAD {
+                COUNTER.fetch_add(1, Ordering::SeqCst);
+                yield_();
+            }
+            assert_ne!(thread::gettid(), 0);
+            i as i32 + 1
+        }).unwrap())
+        .collect();
+    for (i, handle) in handles.into_iter().enumerate() {
+        assert_eq!(handle.join(), Ok(i as i32 + 1));
+    }
+    assert_eq!(COUNTER.load(Ordering::SeqCst), THREAD_NUM * PER_THREAD);
+    println!("join test passed!");
+}
+
+fn error_test() {
+    let mut exit_code: i32 = 0;
+    assert_eq!(thread::gettid(), 0);
+    assert_eq!(waittid(0, &mut exit_code), Err(Errno::EDEADLK));
+    assert_eq!(waittid(100, &mut exit_code), Err(Errno::ESRCH));
+    let handle = thread::spawn(|| {
+        loop {
+            yield_();
+        }
+    }).unwrap();
+    // only a single-threaded process may fork
+    assert_eq!(fork(), Err(Errno::EBUSY));
+    // the looping thread is ended by the process exit below
+    let _ = handle.tid();
+    println!("error test passed!");
+}
+
+fn process_exit_test() {
+    let pid = fork().unwrap();
+    if pid == 0 {
+        error_test();
+        exit(7);
+    }
+    let mut exit_code: i32 = 0;
+    assert_eq!(waitpid(pid, &mut exit_code), Ok(pid));
+    assert_eq!(wexitstatus(exit_code), 7);
+    println!("process exit test passed!");
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    join_test();
+    process_exit_test();
+    println!("threads passed!");
+    0
+}
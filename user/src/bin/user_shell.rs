@@ -11,22 +11,355 @@ const CR: u8 = 0x0du8;
 const DL: u8 = 0x7fu8;
 const BS: u8 = 0x08u8;
 
+use alloc::format;
 use alloc::string::String;
 use alloc::vec::Vec;
 use user_lib::{
-    fork,
-    exec,
-    waitpid,
-    open,
+    spawn_with_env,
+    SpawnAction,
+    waitpid_with_options,
+    WaitOptions,
     OpenFlags,
+    open,
+    read,
     close,
-    dup,
+    Errno,
+    wifexited,
+    wexitstatus,
+    wifstopped,
+    wifcontinued,
+    wtermsig,
+    getpgid,
+    setsid,
+    tcsetpgrp,
+    kill,
+    killpg,
+    sigaction,
+    SignalAction,
+    SignalFlags,
 };
+use user_lib::signal::{SIGINT, SIGTSTP, SIGCONT};
 use user_lib::console::getchar;
+use user_lib::env;
+
+const STDIN: usize = 0;
+
+#[derive(Copy, Clone, PartialEq)]
+enum JobState {
+    Running,
+    Stopped,
+}
+
+/// A command started by the shell, which leads a process group of its own
+/// if the shell is interactive.
+struct Job {
+    id: usize,
+    pid: usize,
+    state: JobState,
+    command: String,
+}
+
+struct Shell {
+    /// Reading commands from the console rather than from a script, with
+    /// each command in a process group of its own.
+    interactive: bool,
+    pgid: usize,
+    /// Exit code of the last command run in the foreground.
+    last_status: i32,
+    /// Jobs in the background, either running or stopped.
+    jobs: Vec<Job>,
+}
+
+/// The keys of the console do not stop or kill the shell, but its children
+/// get the default actions back.
+extern "C" fn on_console_signal(_signum: usize) {}
+
+impl Shell {
+    fn new(interactive: bool) -> Self {
+        if interactive {
+            // fails if we lead a process group already, e.g. started by another shell
+            let _ = setsid();
+            tcsetpgrp(STDIN, getpgid(0).unwrap()).unwrap();
+            let action = SignalAction::new(on_console_signal, SignalFlags::empty());
+            sigaction(SIGINT, Some(&action), None).unwrap();
+            sigaction(SIGTSTP, Some(&action), None).unwrap();
+        }
+        Self { interactive, pgid: getpgid(0).unwrap(), last_status: 0, jobs: Vec::new() }
+    }
+
+    fn add_job(&mut self, pid: usize, state: JobState, command: String) -> usize {
+        let id = self.jobs.iter().map(|job| job.id).max().unwrap_or(0) + 1;
+        self.jobs.push(Job { id, pid, state, command });
+        id
+    }
+
+    /// The job named by `arg`, "%1" or "1", or the latest one.
+    fn find_job(&self, arg: Option<&str>) -> Option<usize> {
+        match arg {
+            None => if self.jobs.is_empty() { None } else { Some(self.jobs.len() - 1) },
+            Some(arg) => {
+                let id: usize = arg.trim_start_matches('%').parse().ok()?;
+                self.jobs.iter().position(|job| job.id == id)
+            }
+        }
+    }
+
+    fn continue_job(&self, pid: usize) {
+        if self.interactive {
+            killpg(pid, SIGCONT).unwrap();
+        } else {
+            kill(pid, SIGCONT).unwrap();
+        }
+    }
+
+    /// Give the console to the job and wait until it exits or stops.
+    fn run_foreground(&mut self, pid: usize, command: String) {
+        if self.interactive {
+            tcsetpgrp(STDIN, pid).unwrap();
+        }
+        let mut exit_code: i32 = 0;
+        loop {
+            match waitpid_with_options(pid as isize, &mut exit_code, WaitOptions::WUNTRACED) {
+                Err(Errno::EINTR) => continue,
+                result => {
+                    assert_eq!(result, Ok(pid));
+                    break;
+                }
+            }
+        }
+        if self.interactive {
+            tcsetpgrp(STDIN, self.pgid).unwrap();
+        }
+        if wifstopped(exit_code) {
+            println!("");
+            let id = self.add_job(pid, JobState::Stopped, command.clone());
+            println!("[{}] Stopped    {}", id, command);
+        } else if wifexited(exit_code) {
+            println!("Shell: Process {} exited with code {}", pid, wexitstatus(exit_code));
+            self.last_status = wexitstatus(exit_code);
+        } else {
+            println!("Shell: Process {} killed by signal {}", pid, wtermsig(exit_code));
+            self.last_status = 128 + wtermsig(exit_code) as i32;
+        }
+    }
+
+    /// Notice the background jobs which have exited, stopped or continued.
+    fn update_jobs(&mut self) {
+        let options = WaitOptions::WNOHANG | WaitOptions::WUNTRACED | WaitOptions::WCONTINUED;
+        let mut idx = 0;
+        while idx < self.jobs.len() {
+            let job = &mut self.jobs[idx];
+            let mut exit_code: i32 = 0;
+            match waitpid_with_options(job.pid as isize, &mut exit_code, options) {
+                Ok(pid) if pid == job.pid => {
+                    if wifstopped(exit_code) {
+                        job.state = JobState::Stopped;
+                    } else if wifcontinued(exit_code) {
+                        job.state = JobState::Running;
+                    } else {
+                        if wifexited(exit_code) {
+                            println!("[{}] Done({})    {}", job.id, wexitstatus(exit_code), job.command);
+                        } else {
+                            println!("[{}] Killed({})    {}", job.id, wtermsig(exit_code), job.command);
+                        }
+                        self.jobs.remove(idx);
+                        continue;
+                    }
+                }
+                _ => {}
+            }
+            idx += 1;
+        }
+    }
+
+    /// Return true if `args` is a built-in command, which has been run.
+    fn run_builtin(&mut self, args: &[&str]) -> bool {
+        match args[0] {
+            "jobs" => {
+                self.update_jobs();
+                for job in self.jobs.iter() {
+                    let state = match job.state {
+                        JobState::Running => "Running",
+                        JobState::Stopped => "Stopped",
+                    };
+                    println!("[{}] {}    {}", job.id, state, job.command);
+                }
+            }
+            "fg" => match self.find_job(args.get(1).copied()) {
+                Some(idx) => {
+                    let job = self.jobs.remove(idx);
+                    println!("{}", job.command);
+                    if job.state == JobState::Stopped {
+                        self.continue_job(job.pid);
+                    }
+                    self.run_foreground(job.pid, job.command);
+                }
+                None => println!("fg: no such job"),
+            },
+            "bg" => match self.find_job(args.get(1).copied()) {
+                Some(idx) => {
+                    self.jobs[idx].state = JobState::Running;
+                    self.continue_job(self.jobs[idx].pid);
+                    let job = &self.jobs[idx];
+                    println!("[{}] {} &", job.id, job.command);
+                }
+                None => println!("bg: no such job"),
+            },
+            _ => return false,
+        }
+        true
+    }
+
+    fn run_line(&mut self, line: &str) {
+        let mut words: Vec<&str> = line.split(' ').filter(|word| !word.is_empty()).collect();
+        if words.is_empty() {
+            return;
+        }
+        if self.run_builtin(&words) {
+            return;
+        }
+        // a trailing & runs the command in the background
+        let background = words.last() == Some(&"&");
+        if background {
+            words.pop();
+        }
+        let command = words.join(" ");
+        let mut args_copy: Vec<String> = words
+            .iter()
+            .map(|&arg| {
+                let mut string = String::new();
+                string.push_str(arg);
+                string.push('\0');
+                string
+            })
+            .collect();
+
+        // redirect input
+        let mut input = String::new();
+        if let Some((idx, _)) = args_copy
+        .iter()
+        .enumerate()
+        .find(|(_, arg)| arg.as_str() == "<\0") {
+            input = args_copy[idx + 1].clone();
+            args_copy.drain(idx..=idx + 1);
+        }
+
+        // redirect output
+        let mut output = String::new();
+        if let Some((idx, _)) = args_copy
+        .iter()
+        .enumerate()
+        .find(|(_, arg)| arg.as_str() == ">\0") {
+            output = args_copy[idx + 1].clone();
+            args_copy.drain(idx..=idx + 1);
+        }
+
+        // leading VAR=value words only go into the environment of the child
+        let mut envs: Vec<String> = env::vars()
+            .iter()
+            .map(|(key, value)| format!("{}={}\0", key, value))
+            .collect();
+        while !args_copy.is_empty() && args_copy[0].find('=').map_or(false, |idx| idx > 0) {
+            let assignment = args_copy.remove(0);
+            let key = &assignment[..=assignment.find('=').unwrap()];
+            envs.retain(|env| !env.starts_with(key));
+            envs.push(assignment);
+        }
+        if args_copy.is_empty() {
+            println!("Shell: no command to run");
+            return;
+        }
+        let mut envs_addr: Vec<*const u8> = envs
+            .iter()
+            .map(|env| env.as_ptr())
+            .collect();
+        envs_addr.push(0 as *const u8);
+
+        let mut args_addr: Vec<*const u8> = args_copy
+            .iter()
+            .map(|arg| arg.as_ptr())
+            .collect();
+        args_addr.push(0 as *const u8);
+        // each command leads a process group of its own, so that the keys
+        // of the console only reach it while it is in the foreground
+        let mut actions = Vec::new();
+        if self.interactive {
+            actions.push(SpawnAction::setpgid(0));
+        }
+        // redirections only change the fd table of the child
+        if !input.is_empty() {
+            actions.push(SpawnAction::open(0, input.as_str(), OpenFlags::RDONLY));
+        }
+        if !output.is_empty() {
+            actions.push(SpawnAction::open(
+                1,
+                output.as_str(),
+                OpenFlags::CREATE | OpenFlags::WRONLY,
+            ));
+        }
+        match spawn_with_env(
+            args_copy[0].as_str(),
+            args_addr.as_slice(),
+            envs_addr.as_slice(),
+            actions.as_slice(),
+        ) {
+            Ok(pid) if background => {
+                let id = self.add_job(pid, JobState::Running, command);
+                println!("[{}] {}", id, pid);
+            }
+            Ok(pid) => self.run_foreground(pid, command),
+            Err(errno) => println!("Error when executing: {}", errno),
+        }
+    }
+}
+
+/// Run the commands in the file at `path` one line after another, lines
+/// starting with '#' being comments, e.g. the `#!user_shell` line.
+fn run_script(path: &str) -> i32 {
+    let fd = match open(path, OpenFlags::RDONLY) {
+        Ok(fd) => fd,
+        Err(errno) => {
+            println!("Shell: cannot open {}: {}", path, errno);
+            return 1;
+        }
+    };
+    let mut script = Vec::new();
+    let mut buffer = [0u8; 512];
+    loop {
+        match read(fd, &mut buffer) {
+            Ok(0) => break,
+            Ok(len) => script.extend_from_slice(&buffer[..len]),
+            Err(errno) => {
+                println!("Shell: cannot read {}: {}", path, errno);
+                return 1;
+            }
+        }
+    }
+    close(fd).unwrap();
+    let script = match core::str::from_utf8(&script) {
+        Ok(script) => script,
+        Err(_) => {
+            println!("Shell: {} is not a text file", path);
+            return 1;
+        }
+    };
+    let mut shell = Shell::new(false);
+    for line in script.lines().map(|line| line.trim()) {
+        if !line.is_empty() && !line.starts_with('#') {
+            shell.run_line(line);
+            shell.update_jobs();
+        }
+    }
+    shell.last_status
+}
 
 #[no_mangle]
-pub fn main() -> i32 {
+pub fn main(argc: usize, argv: &[&str]) -> i32 {
+    if argc > 1 {
+        return run_script(argv[1]);
+    }
     println!("Rust user shell");
+    let mut shell = Shell::new(true);
     let mut line: String = String::new();
     print!(">> ");
     loop {
@@ -35,90 +368,10 @@ pub fn main() -> i32 {
             LF | CR => {
                 println!("");
                 if !line.is_empty() {
-                    let args: Vec<_> = line.as_str().split(' ').collect();
-                    let mut args_copy: Vec<String> = args
-                    .iter()
-                    .map(|&arg| {
-                        let mut string = String::new();
-                        string.push_str(arg);
-                        string
-                    })
-                    .collect();
-
-                    args_copy
-                    .iter_mut()
-                    .for_each(|string| {
-                        string.push('\0');
-                    });
-
-                    // redirect input
-                    let mut input = String::new();
-                    if let Some((idx, _)) = args_copy
-                    .iter()
-                    .enumerate()
-                    .find(|(_, arg)| arg.as_str() == "<\0") {
-                        input = args_copy[idx + 1].clone();
-                        args_copy.drain(idx..=idx + 1);
-                    }
-
-                    // redirect output
-                    let mut output = String::new();
-                    if let Some((idx, _)) = args_copy
-                    .iter()
-                    .enumerate()
-                    .find(|(_, arg)| arg.as_str() == ">\0") {
-                        output = args_copy[idx + 1].clone();
-                        args_copy.drain(idx..=idx + 1);
-                    }
-
-                    let mut args_addr: Vec<*const u8> = args_copy
-                        .iter()
-                        .map(|arg| arg.as_ptr())
-                        .collect();
-                    args_addr.push(0 as *const u8);
-                    let pid = fork();
-                    if pid == 0 {
-                        // input redirection
-                        if !input.is_empty() {
-                            let input_fd = open(input.as_str(), OpenFlags::RDONLY);
-                            if input_fd == -1 {
-                                println!("Error when opening file {}", input);
-                                return -4;
-                            }
-                            let input_fd = input_fd as usize;
-                            close(0);
-                            assert_eq!(dup(input_fd), 0);
-                            close(input_fd);
-                        }
-                        // output redirection
-                        if !output.is_empty() {
-                            let output_fd = open(
-                                output.as_str(),
-                                OpenFlags::CREATE | OpenFlags::WRONLY
-                            );
-                            if output_fd == -1 {
-                                println!("Error when opening file {}", output);
-                                return -4;
-                            }
-                            let output_fd = output_fd as usize;
-                            close(1);
-                            assert_eq!(dup(output_fd), 1);
-                            close(output_fd);
-                        }
-                        // child process
-                        if exec(args_copy[0].as_str(), args_addr.as_slice()) == -1 {
-                            println!("Error when executing!");
-                            return -4;
-                        }
-                        unreachable!();
-                    } else {
-                        let mut exit_code: i32 = 0;
-                        let exit_pid = waitpid(pid as usize, &mut exit_code);
-                        assert_eq!(pid, exit_pid);
-                        println!("Shell: Process {} exited with code {}", pid, exit_code);
-                    }
+                    shell.run_line(line.as_str());
                     line.clear();
                 }
+                shell.update_jobs();
                 print!(">> ");
             }
             BS | DL => {
@@ -135,4 +388,4 @@ pub fn main() -> i32 {
             }
         }
     }
-}
\ No newline at end of file
+}
@@ -3,38 +3,99 @@
 
 #[macro_use]
 extern crate user_lib;
+extern crate alloc;
 
 static TESTS: &[&str] = &[
+    "cow_test\0",
+    "cputime_test\0",
+    "dynamic_test\0",
+    "elf_test\0",
+    "env_test\0",
+    "errno_test\0",
     "exit\0",
     "fantastic_text\0",
     "forktest\0",
     "forktest2\0",
     "forktest_simple\0",
+    "heap_test\0",
     "hello_world\0",
+    "jobctl_test\0",
+    "lazy_test\0",
+    "linux_abi_test\0",
+    "linux_test\0",
     "matrix\0",
+    "mmap_test\0",
+    "nanosleep_test\0",
+    "shebang_test\0",
+    "sig_tests\0",
     "sleep\0",
     "sleep_simple\0",
-    "stack_overflow\0",
+    "smp_test\0",
+    "spawn_test\0",
+    "stack_growth\0",
+    "swap_test\0",
+    "sync_tests\0",
+    "syscall_test\0",
+    "tasklist_test\0",
+    "threads\0",
+    "wait_nohang\0",
     "yield\0",
 ];
 
-use user_lib::{exec, fork, waitpid};
+/// Tests which are expected to be killed, e.g. by SIGSEGV.
+static FAIL_TESTS: &[&str] = &[
+    "stack_overflow\0",
+];
+
+/// Tests which only pass under some scheduling policies. They are run by
+/// `usertests <policy>`, e.g. `usertests stride` on a kernel built with
+/// `make run SCHED=stride`.
+static SCHED_TESTS: &[(&str, &[&str])] = &[
+    ("sched_latency\0", &["stride", "mlfq"]),
+    ("stride_test\0", &["stride"]),
+];
+
+use alloc::vec::Vec;
+use user_lib::{spawn, wait_exit_code};
+
+/// Run `test` and tell whether it exited with 0.
+fn run(test: &str) -> bool {
+    println!("Usertests: Running {}", test);
+    let pid = spawn(test, &[0 as *const u8], &[]).unwrap();
+    let exit_code = wait_exit_code(pid).unwrap();
+    println!("\x1b[32mUsertests: Test {} in Process {} exited with code {}\x1b[0m", test, pid, exit_code);
+    exit_code == 0
+}
 
 #[no_mangle]
-pub fn main() -> i32 {
-    for test in TESTS {
-        println!("Usertests: Running {}", test);
-        let pid = fork();
-        if pid == 0 {
-            exec(*test, &[0 as *const u8]);
-            panic!("unreachable!");
-        } else {
-            let mut exit_code: i32 = Default::default();
-            let wait_pid = waitpid(pid as usize, &mut exit_code);
-            assert_eq!(pid, wait_pid);
-            println!("\x1b[32mUsertests: Test {} in Process {} exited with code {}\x1b[0m", test, pid, exit_code);
+pub fn main(argc: usize, argv: &[&str]) -> i32 {
+    if argc > 2 {
+        println!("usage: usertests [fifo|stride|mlfq]");
+        return 1;
+    }
+    let sched_tests: Vec<&str> = SCHED_TESTS
+        .iter()
+        .filter(|(_, policies)| argc == 2 && policies.contains(&argv[1]))
+        .map(|(test, _)| *test)
+        .collect();
+    let mut failed = 0;
+    for test in TESTS.iter().chain(sched_tests.iter()) {
+        if !run(test) {
+            failed += 1;
+            println!("\x1b[31mUsertests: Test {} failed\x1b[0m", test);
         }
     }
+    for test in FAIL_TESTS {
+        if run(test) {
+            failed += 1;
+            println!("\x1b[31mUsertests: Test {} should have failed\x1b[0m", test);
+        }
+    }
+    if failed > 0 {
+        let total = TESTS.len() + sched_tests.len() + FAIL_TESTS.len();
+        println!("Usertests: {} of {} tests failed!", failed, total);
+        return 1;
+    }
     println!("Usertests passed!");
     0
-}
\ No newline at end of file
+}
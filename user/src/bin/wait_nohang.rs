@@ -0,0 +1,60 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    fork,
+    exit,
+    sleep,
+    setpgid,
+    waitpid,
+    waitpid_with_options,
+    wifexited,
+    wexitstatus,
+    WaitOptions,
+    Errno,
+};
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let pid = fork().unwrap();
+    if pid == 0 {
+        sleep(100);
+        exit(7);
+    }
+    let mut exit_code: i32 = 0;
+    // the child is still sleeping
+    assert_eq!(waitpid_with_options(pid as isize, &mut exit_code, WaitOptions::WNOHANG), Ok(0));
+    // block until it exits
+    assert_eq!(waitpid(pid, &mut exit_code), Ok(pid));
+    assert!(wifexited(exit_code));
+    assert_eq!(wexitstatus(exit_code), 7);
+    assert_eq!(
+        waitpid_with_options(-1, &mut exit_code, WaitOptions::WNOHANG),
+        Err(Errno::ECHILD)
+    );
+
+    // 0 waits for the children in our process group, -pgid for those in another
+    let leader = fork().unwrap();
+    if leader == 0 {
+        sleep(100);
+        exit(1);
+    }
+    setpgid(leader, 0).unwrap();
+    let member = fork().unwrap();
+    if member == 0 {
+        exit(2);
+    }
+    assert_eq!(waitpid_with_options(0, &mut exit_code, WaitOptions::empty()), Ok(member));
+    assert_eq!(wexitstatus(exit_code), 2);
+    assert_eq!(
+        waitpid_with_options(0, &mut exit_code, WaitOptions::WNOHANG),
+        Err(Errno::ECHILD)
+    );
+    assert_eq!(waitpid_with_options(-(leader as isize), &mut exit_code, WaitOptions::empty()), Ok(leader));
+    assert_eq!(wexitstatus(exit_code), 1);
+    println!("wait_nohang passed!");
+    0
+}
@@ -9,7 +9,7 @@ struct Stdout;
 
 impl Write for Stdout {
     fn write_str(&mut self, s: &str) -> fmt::Result {
-        write(STDOUT, s.as_bytes());
+        let _ = write(STDOUT, s.as_bytes());
         Ok(())
     }
 }
@@ -34,6 +34,6 @@ macro_rules! println {
 
 pub fn getchar() -> u8 {
     let mut c = [0u8; 1];
-    read(STDIN, &mut c);
+    read(STDIN, &mut c).unwrap();
     c[0]
 }
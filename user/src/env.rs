@@ -0,0 +1,73 @@
+use alloc::vec::Vec;
+
+/// Keys of the auxiliary vector, as in Linux.
+pub const AT_NULL: usize = 0;
+pub const AT_PHDR: usize = 3;
+pub const AT_PHENT: usize = 4;
+pub const AT_PHNUM: usize = 5;
+pub const AT_PAGESZ: usize = 6;
+pub const AT_ENTRY: usize = 9;
+pub const AT_UID: usize = 11;
+pub const AT_EUID: usize = 12;
+pub const AT_GID: usize = 13;
+pub const AT_EGID: usize = 14;
+pub const AT_HWCAP: usize = 16;
+/// Clock ticks per second, as in `times`.
+pub const AT_CLKTCK: usize = 17;
+pub const AT_SECURE: usize = 23;
+pub const AT_RANDOM: usize = 25;
+
+/// envp on the initial stack, right after the null pointer ending argv.
+static mut ENVP: usize = 0;
+
+pub(crate) fn init(envp: usize) {
+    unsafe { ENVP = envp; }
+}
+
+fn c_str(ptr: *const u8) -> &'static str {
+    let len = (0usize..).find(|i| unsafe { ptr.add(*i).read_volatile() == 0 }).unwrap();
+    core::str::from_utf8(unsafe { core::slice::from_raw_parts(ptr, len) }).unwrap()
+}
+
+/// The environment passed to the program, "KEY=value" strings ending with '\0',
+/// including the null pointer at the end, so it can be passed on to execve.
+pub fn environ() -> &'static [*const u8] {
+    let envp = unsafe { ENVP } as *const *const u8;
+    let len = (0usize..).find(|i| unsafe { envp.add(*i).read_volatile().is_null() }).unwrap();
+    unsafe { core::slice::from_raw_parts(envp, len + 1) }
+}
+
+/// All the variables of the environment, in order.
+pub fn vars() -> Vec<(&'static str, &'static str)> {
+    let environ = environ();
+    environ[..environ.len() - 1]
+        .iter()
+        .map(|env| {
+            let env = c_str(*env);
+            match env.find('=') {
+                Some(idx) => (&env[..idx], &env[idx + 1..]),
+                None => (env, ""),
+            }
+        })
+        .collect()
+}
+
+pub fn var(key: &str) -> Option<&'static str> {
+    vars().into_iter().find(|(k, _)| *k == key).map(|(_, value)| value)
+}
+
+/// Value of `key` in the auxiliary vector, which follows envp.
+pub fn auxval(key: usize) -> Option<usize> {
+    let environ = environ();
+    let mut auxv = unsafe { environ.as_ptr().add(environ.len()) } as *const usize;
+    loop {
+        let (k, value) = unsafe { (auxv.read_volatile(), auxv.add(1).read_volatile()) };
+        if k == AT_NULL {
+            return None;
+        }
+        if k == key {
+            return Some(value);
+        }
+        auxv = unsafe { auxv.add(2) };
+    }
+}
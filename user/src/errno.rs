@@ -0,0 +1,115 @@
+use core::fmt::{self, Display, Formatter};
+
+/// Error numbers reported by the kernel, with the same values as `SysError`
+/// in the kernel. Those this library does not know of are kept as they are.
+#[repr(isize)]
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Errno {
+    EPERM = 1,
+    ENOENT = 2,
+    ESRCH = 3,
+    EINTR = 4,
+    EIO = 5,
+    E2BIG = 7,
+    ENOEXEC = 8,
+    EBADF = 9,
+    ECHILD = 10,
+    EAGAIN = 11,
+    ENOMEM = 12,
+    EACCES = 13,
+    EFAULT = 14,
+    EBUSY = 16,
+    EEXIST = 17,
+    ENODEV = 19,
+    ENOTDIR = 20,
+    EISDIR = 21,
+    EINVAL = 22,
+    EMFILE = 24,
+    ENOTTY = 25,
+    ENOSPC = 28,
+    ESPIPE = 29,
+    EPIPE = 32,
+    ERANGE = 34,
+    EDEADLK = 35,
+    ENOSYS = 38,
+    ELOOP = 40,
+    Unknown(isize),
+}
+
+impl Errno {
+    pub fn from_code(code: isize) -> Self {
+        match code {
+            1 => Self::EPERM,
+            2 => Self::ENOENT,
+            3 => Self::ESRCH,
+            4 => Self::EINTR,
+            5 => Self::EIO,
+            7 => Self::E2BIG,
+            8 => Self::ENOEXEC,
+            9 => Self::EBADF,
+            10 => Self::ECHILD,
+            11 => Self::EAGAIN,
+            12 => Self::ENOMEM,
+            13 => Self::EACCES,
+            14 => Self::EFAULT,
+            16 => Self::EBUSY,
+            17 => Self::EEXIST,
+            19 => Self::ENODEV,
+            20 => Self::ENOTDIR,
+            21 => Self::EISDIR,
+            22 => Self::EINVAL,
+            24 => Self::EMFILE,
+            25 => Self::ENOTTY,
+            28 => Self::ENOSPC,
+            29 => Self::ESPIPE,
+            32 => Self::EPIPE,
+            34 => Self::ERANGE,
+            35 => Self::EDEADLK,
+            38 => Self::ENOSYS,
+            40 => Self::ELOOP,
+            code => Self::Unknown(code),
+        }
+    }
+    pub fn description(&self) -> &'static str {
+        match self {
+            Self::EPERM => "Operation not permitted",
+            Self::ENOENT => "No such file or directory",
+            Self::ESRCH => "No such process",
+            Self::EINTR => "Interrupted system call",
+            Self::EIO => "I/O error",
+            Self::E2BIG => "Argument list too long",
+            Self::ENOEXEC => "Exec format error",
+            Self::EBADF => "Bad file descriptor",
+            Self::ECHILD => "No child processes",
+            Self::EAGAIN => "Try again",
+            Self::ENOMEM => "Out of memory",
+            Self::EACCES => "Permission denied",
+            Self::EFAULT => "Bad address",
+            Self::EBUSY => "Device or resource busy",
+            Self::EEXIST => "File exists",
+            Self::ENODEV => "No such device",
+            Self::ENOTDIR => "Not a directory",
+            Self::EISDIR => "Is a directory",
+            Self::EINVAL => "Invalid argument",
+            Self::EMFILE => "Too many open files",
+            Self::ENOTTY => "Not a typewriter",
+            Self::ENOSPC => "No space left on device",
+            Self::ESPIPE => "Illegal seek",
+            Self::EPIPE => "Broken pipe",
+            Self::ERANGE => "Result out of range",
+            Self::EDEADLK => "Resource deadlock would occur",
+            Self::ENOSYS => "Function not implemented",
+            Self::ELOOP => "Too many levels of symbolic links",
+            Self::Unknown(_) => "Unknown error",
+        }
+    }
+}
+
+impl Display for Errno {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Unknown(code) => write!(f, "Unknown error {}", code),
+            _ => f.write_str(self.description()),
+        }
+    }
+}
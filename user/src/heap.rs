@@ -0,0 +1,41 @@
+use core::alloc::{GlobalAlloc, Layout};
+use core::ptr::{self, NonNull};
+use buddy_system_allocator::LockedHeap;
+use crate::sys_sbrk;
+
+const PAGE_SIZE: usize = 0x1000;
+/// The heap grows by at least this much at a time to save syscalls.
+const MIN_GROWTH: usize = 0x4000;
+
+/// A buddy allocator which starts empty and asks the kernel for more
+/// memory with sbrk whenever an allocation does not fit.
+pub struct GrowingHeap(LockedHeap);
+
+impl GrowingHeap {
+    pub const fn empty() -> Self {
+        Self(LockedHeap::empty())
+    }
+}
+
+unsafe impl GlobalAlloc for GrowingHeap {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let mut heap = self.0.lock();
+        if let Ok(ptr) = heap.alloc(layout) {
+            return ptr.as_ptr();
+        }
+        // Regions added to a buddy allocator are never merged with each other,
+        // so the new one alone must hold an aligned block big enough for `layout`.
+        let block = layout.size().max(layout.align()).next_power_of_two();
+        let size = (block * 2).max(MIN_GROWTH);
+        let size = (size + PAGE_SIZE - 1) & !(PAGE_SIZE - 1);
+        let start = match sys_sbrk(size as isize) {
+            Ok(start) => start,
+            Err(_) => return ptr::null_mut(),
+        };
+        heap.add_to_heap(start, start + size);
+        heap.alloc(layout).map_or(ptr::null_mut(), |ptr| ptr.as_ptr())
+    }
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        self.0.lock().dealloc(NonNull::new_unchecked(ptr), layout)
+    }
+}
@@ -1,28 +1,35 @@
 #![no_std]
 #![feature(llvm_asm)]
+#![feature(global_asm)]
 #![feature(linkage)]
 #![feature(panic_info_message)]
 #![feature(alloc_error_handler)]
+#![feature(arbitrary_enum_discriminant)]
 
 #[macro_use]
 pub mod console;
 mod syscall;
 mod lang_items;
+mod errno;
+mod heap;
+pub mod signal;
+pub mod thread;
+pub mod env;
 
 extern crate alloc;
 #[macro_use]
 extern crate bitflags;
 
 use syscall::*;
-use buddy_system_allocator::LockedHeap;
+pub use syscall::SysResult;
+pub use errno::Errno;
+pub use signal::{SignalFlags, SignalAction, SigMaskHow};
+use heap::GrowingHeap;
 use alloc::vec::Vec;
-
-const USER_HEAP_SIZE: usize = 32768;
-
-static mut HEAP_SPACE: [u8; USER_HEAP_SIZE] = [0; USER_HEAP_SIZE];
+use core::marker::PhantomData;
 
 #[global_allocator]
-static HEAP: LockedHeap = LockedHeap::empty();
+static HEAP: GrowingHeap = GrowingHeap::empty();
 
 #[alloc_error_handler]
 pub fn handle_alloc_error(layout: core::alloc::Layout) -> ! {
@@ -32,10 +39,6 @@ pub fn handle_alloc_error(layout: core::alloc::Layout) -> ! {
 #[no_mangle]
 #[link_section = ".text.entry"]
 pub extern "C" fn _start(argc: usize, argv: usize) -> ! {
-    unsafe {
-        HEAP.lock()
-            .init(HEAP_SPACE.as_ptr() as usize, USER_HEAP_SIZE);
-    }
     let mut v: Vec<&'static str> = Vec::new();
     for i in 0..argc {
         let str_start = unsafe {
@@ -50,6 +53,8 @@ pub extern "C" fn _start(argc: usize, argv: usize) -> ! {
             }).unwrap()
         );
     }
+    // envp follows the null pointer ending argv
+    env::init(argv + (argc + 1) * core::mem::size_of::<usize>());
     exit(main(argc, v.as_slice()));
 }
 
@@ -69,40 +74,427 @@ bitflags! {
     }
 }
 
-pub fn dup(fd: usize) -> isize { sys_dup(fd) }
-pub fn open(path: &str, flags: OpenFlags) -> isize { sys_open(path, flags.bits) }
-pub fn close(fd: usize) -> isize { sys_close(fd) }
-pub fn pipe(pipe_fd: &mut [usize]) -> isize { sys_pipe(pipe_fd) }
-pub fn read(fd: usize, buf: &mut [u8]) -> isize { sys_read(fd, buf) }
-pub fn write(fd: usize, buf: &[u8]) -> isize { sys_write(fd, buf) }
+/// Same layout as `struct timespec` in Linux.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct TimeSpec {
+    pub sec: usize,
+    pub nsec: usize,
+}
+
+impl TimeSpec {
+    pub fn from_ms(ms: usize) -> Self {
+        Self { sec: ms / 1000, nsec: ms % 1000 * 1_000_000 }
+    }
+}
+
+/// Same layout as `struct rlimit` in Linux.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct RLimit {
+    pub rlim_cur: usize,
+    pub rlim_max: usize,
+}
+
+/// Limit on the size of the stack of each thread, the only resource so far.
+pub const RLIMIT_STACK: usize = 3;
+
+/// Clock ticks per second, the unit of `times`.
+pub const CLK_TCK: usize = 100;
+
+/// Same layout as `struct tms` in Linux, in clock ticks.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Tms {
+    pub tms_utime: usize,
+    pub tms_stime: usize,
+    pub tms_cutime: usize,
+    pub tms_cstime: usize,
+}
+
+/// Same layout as `struct timeval` in Linux.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default)]
+pub struct TimeVal {
+    pub sec: usize,
+    pub usec: usize,
+}
+
+impl TimeVal {
+    pub fn as_us(&self) -> usize {
+        self.sec * 1_000_000 + self.usec
+    }
+}
+
+/// Same layout as `struct rusage` in Linux, only the times are filled.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default)]
+pub struct RUsage {
+    pub ru_utime: TimeVal,
+    pub ru_stime: TimeVal,
+    pub ru_others: [isize; 14],
+}
+
+pub const RUSAGE_SELF: isize = 0;
+/// Children which have been waited for, and their own children.
+pub const RUSAGE_CHILDREN: isize = -1;
+pub const RUSAGE_THREAD: isize = 1;
+
+pub const CLOCK_REALTIME: usize = 0;
+pub const CLOCK_MONOTONIC: usize = 1;
+/// `clock_nanosleep` flag, the request is a deadline rather than a duration.
+pub const TIMER_ABSTIME: usize = 1;
+
+pub fn dup(fd: usize) -> SysResult { sys_dup(fd) }
+pub fn open(path: &str, flags: OpenFlags) -> SysResult { sys_open(path, flags.bits) }
+pub fn close(fd: usize) -> SysResult<()> { sys_close(fd).map(|_| ()) }
+pub fn pipe(pipe_fd: &mut [usize]) -> SysResult<()> { sys_pipe(pipe_fd).map(|_| ()) }
+pub fn read(fd: usize, buf: &mut [u8]) -> SysResult { sys_read(fd, buf) }
+pub fn write(fd: usize, buf: &[u8]) -> SysResult { sys_write(fd, buf) }
+/// The whole content of the file at `path`.
+pub fn read_file(path: &str) -> SysResult<Vec<u8>> {
+    let fd = open(path, OpenFlags::RDONLY)?;
+    let mut data = Vec::new();
+    let mut buffer = [0u8; 512];
+    let result = loop {
+        match read(fd, &mut buffer) {
+            Ok(0) => break Ok(data),
+            Ok(len) => data.extend_from_slice(&buffer[..len]),
+            Err(errno) => break Err(errno),
+        }
+    };
+    close(fd)?;
+    result
+}
+/// Create the file at `path`, or truncate it, and fill it with `data`.
+pub fn write_file(path: &str, data: &[u8]) -> SysResult<()> {
+    let fd = open(path, OpenFlags::CREATE | OpenFlags::WRONLY)?;
+    let result = write(fd, data);
+    close(fd)?;
+    match result? {
+        len if len == data.len() => Ok(()),
+        _ => Err(Errno::EIO),
+    }
+}
 pub fn exit(exit_code: i32) -> ! { sys_exit(exit_code); }
 pub fn yield_() -> isize { sys_yield() }
 pub fn get_time() -> isize { sys_get_time() }
 pub fn getpid() -> isize { sys_getpid() }
-pub fn fork() -> isize { sys_fork() }
-pub fn exec(path: &str, args: &[*const u8]) -> isize { sys_exec(path, args) }
-pub fn wait(exit_code: &mut i32) -> isize {
+/// Set the priority of the current thread, at least 2. Returns the new priority.
+pub fn set_priority(prio: isize) -> SysResult { sys_set_priority(prio) }
+pub fn getrlimit(resource: usize, rlim: &mut RLimit) -> SysResult<()> { sys_getrlimit(resource, rlim).map(|_| ()) }
+pub fn setrlimit(resource: usize, rlim: &RLimit) -> SysResult<()> { sys_setrlimit(resource, rlim).map(|_| ()) }
+/// Returns the clock ticks since boot.
+pub fn times(tms: &mut Tms) -> SysResult { sys_times(tms as *mut _) }
+pub fn getrusage(who: isize, usage: &mut RUsage) -> SysResult<()> { sys_getrusage(who, usage).map(|_| ()) }
+pub fn fork() -> SysResult { sys_fork() }
+/// Run `path` with the environment of the current process.
+pub fn exec(path: &str, args: &[*const u8]) -> SysResult { sys_execve(path, args, env::environ()) }
+/// `envs` are "KEY=value" strings ending with '\0', followed by a null pointer like `args`.
+pub fn execve(path: &str, args: &[*const u8], envs: &[*const u8]) -> SysResult { sys_execve(path, args, envs) }
+
+/// Change to the fd table a spawned process inherits from its parent, or to its process group.
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+pub struct SpawnAction<'a> {
+    kind: usize,
+    fd: usize,
+    newfd: usize,
+    path: usize,
+    flags: usize,
+    _path: PhantomData<&'a str>,
+}
+
+impl<'a> SpawnAction<'a> {
+    const END: Self = Self::new(0, 0, 0, 0, 0);
+    const fn new(kind: usize, fd: usize, newfd: usize, path: usize, flags: usize) -> Self {
+        Self { kind, fd, newfd, path, flags, _path: PhantomData }
+    }
+    /// Close `fd` in the child.
+    pub fn close(fd: usize) -> Self { Self::new(1, fd, 0, 0, 0) }
+    /// Make `newfd` of the child refer to the file of `fd`.
+    pub fn dup2(fd: usize, newfd: usize) -> Self { Self::new(2, fd, newfd, 0, 0) }
+    /// Open `path`, which ends with '\0', as `newfd` of the child.
+    pub fn open(newfd: usize, path: &'a str, flags: OpenFlags) -> Self {
+        Self::new(3, 0, newfd, path.as_ptr() as usize, flags.bits as usize)
+    }
+    /// Put the child in the process group `pgid`, 0 for a new group led by it.
+    pub fn setpgid(pgid: usize) -> Self { Self::new(4, pgid, 0, 0, 0) }
+}
+
+/// Create a child process running `path` without copying the caller, like
+/// fork and exec would. The actions are applied in order to the fd table it inherits.
+pub fn spawn(path: &str, args: &[*const u8], file_actions: &[SpawnAction]) -> SysResult {
+    spawn_with_env(path, args, env::environ(), file_actions)
+}
+/// Like `spawn`, with the environment given as for `execve`.
+pub fn spawn_with_env(
+    path: &str,
+    args: &[*const u8],
+    envs: &[*const u8],
+    file_actions: &[SpawnAction],
+) -> SysResult {
+    let mut actions = file_actions.to_vec();
+    actions.push(SpawnAction::END);
+    sys_spawn(path, args, actions.as_slice(), envs)
+}
+bitflags! {
+    /// Access rights of a mapping, as in Linux.
+    pub struct MmapProt: usize {
+        const READ = 1 << 0;
+        const WRITE = 1 << 1;
+        const EXEC = 1 << 2;
+    }
+}
+
+/// Map `len` bytes of zeroed memory at `addr`, or where the kernel likes if `addr` is 0.
+/// Returns the start address of the mapping.
+pub fn mmap(addr: usize, len: usize, prot: MmapProt) -> SysResult { sys_mmap(addr, len, prot.bits) }
+pub fn munmap(addr: usize, len: usize) -> SysResult<()> { sys_munmap(addr, len).map(|_| ()) }
+/// Set the end of the heap, 0 only queries it. Returns the new end.
+pub fn brk(addr: usize) -> SysResult { sys_brk(addr) }
+/// Move the end of the heap by `increment` bytes. Returns the old end.
+pub fn sbrk(increment: isize) -> SysResult { sys_sbrk(increment) }
+
+pub const TASK_READY: usize = 0;
+pub const TASK_RUNNING: usize = 1;
+pub const TASK_BLOCKED: usize = 2;
+pub const TASK_STOPPED: usize = 3;
+pub const TASK_ZOMBIE: usize = 4;
+
+/// A live thread as listed by `task_list`.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default)]
+pub struct TaskInfo {
+    pub pid: usize,
+    pub tid: usize,
+    /// `usize::MAX` for initproc, which has no parent.
+    pub ppid: usize,
+    pub pgid: usize,
+    pub state: usize,
+    /// Bytes mapped in the address space of the process, and backed by frames.
+    pub vsz: usize,
+    pub rss: usize,
+    pub utime: TimeVal,
+    pub stime: TimeVal,
+    name: [u8; 32],
+}
+
+impl TaskInfo {
+    /// Path of the program last run by the process.
+    pub fn name(&self) -> &str {
+        let len = self.name.iter().position(|c| *c == 0).unwrap_or(self.name.len());
+        core::str::from_utf8(&self.name[..len]).unwrap_or("?")
+    }
+    /// One letter as in the STAT column of ps.
+    pub fn state_char(&self) -> char {
+        match self.state {
+            TASK_READY | TASK_RUNNING => 'R',
+            TASK_BLOCKED => 'S',
+            TASK_STOPPED => 'T',
+            _ => 'Z',
+        }
+    }
+}
+
+/// All live threads, sorted by pid and tid.
+pub fn task_list() -> SysResult<Vec<TaskInfo>> {
+    let mut infos = Vec::new();
     loop {
-        match sys_waitpid(-1, exit_code as *mut _) {
-            -2 => { yield_(); }
-            // -1 or a real pid
-            exit_pid => return exit_pid,
+        // threads may come and go between the calls
+        let count = sys_task_list(infos.as_mut_slice())?;
+        if count <= infos.len() {
+            infos.truncate(count);
+            return Ok(infos);
         }
+        infos.resize(count + 4, TaskInfo::default());
+    }
+}
+
+/// How many times a system call has been made since boot, by everyone.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default)]
+pub struct SyscallStat {
+    /// The table of the system call, `PERSONALITY_NATIVE` or `PERSONALITY_LINUX`.
+    pub personality: usize,
+    pub id: usize,
+    pub count: usize,
+    name: [u8; 24],
+}
+
+impl SyscallStat {
+    pub fn name(&self) -> &str {
+        let len = self.name.iter().position(|c| *c == 0).unwrap_or(self.name.len());
+        core::str::from_utf8(&self.name[..len]).unwrap_or("?")
     }
 }
 
-pub fn waitpid(pid: usize, exit_code: &mut i32) -> isize {
+/// All system calls, those of this kernel first and then those of Linux,
+/// each sorted by id.
+pub fn syscall_stats() -> SysResult<Vec<SyscallStat>> {
+    let mut stats = Vec::new();
     loop {
-        match sys_waitpid(pid as isize, exit_code as *mut _) {
-            -2 => { yield_(); }
-            // -1 or a real pid
-            exit_pid => return exit_pid,
+        let count = sys_syscall_stats(stats.as_mut_slice())?;
+        if count <= stats.len() {
+            stats.truncate(count);
+            return Ok(stats);
         }
+        stats.resize(count, SyscallStat::default());
     }
 }
+
+/// The system calls of this kernel.
+pub const PERSONALITY_NATIVE: usize = 0;
+/// The system calls of Linux on riscv64, to run statically linked Linux
+/// programs. There is no way back from there, nor any heap for user_lib.
+pub const PERSONALITY_LINUX: usize = 1;
+
+/// Switch the system calls of the process, children and new images keep
+/// them. Returns the previous personality.
+pub fn personality(persona: usize) -> SysResult { sys_personality(persona) }
+
+bitflags! {
+    pub struct WaitOptions: u32 {
+        /// Return 0 at once instead of blocking if no child has exited.
+        const WNOHANG = 1;
+        /// Also report children which have stopped.
+        const WUNTRACED = 2;
+        /// Also report stopped children which have been continued.
+        const WCONTINUED = 8;
+    }
+}
+
+pub fn wait(exit_code: &mut i32) -> SysResult { sys_waitpid(-1, exit_code as *mut _, 0) }
+pub fn waitpid(pid: usize, exit_code: &mut i32) -> SysResult {
+    sys_waitpid(pid as isize, exit_code as *mut _, 0)
+}
+/// `pid` is -1 for any child, 0 for any child in the process group of the caller,
+/// or -pgid for any child in that group. Returns 0 with WNOHANG if no child has exited yet.
+pub fn waitpid_with_options(pid: isize, exit_code: &mut i32, options: WaitOptions) -> SysResult {
+    sys_waitpid(pid, exit_code as *mut _, options.bits)
+}
+
+/// Wait for child `pid` to terminate. Returns its exit code, or 128 plus the
+/// number of the signal which killed it, as shells do.
+pub fn wait_exit_code(pid: usize) -> SysResult<i32> {
+    let mut status: i32 = 0;
+    waitpid(pid, &mut status)?;
+    if wifsignaled(status) {
+        Ok(128 + wtermsig(status) as i32)
+    } else {
+        Ok(wexitstatus(status))
+    }
+}
+
+/// Decoding of the status words filled by wait/waitpid.
+pub fn wifexited(status: i32) -> bool { status & 0x7f == 0 }
+pub fn wexitstatus(status: i32) -> i32 { (status >> 8) & 0xff }
+pub fn wifsignaled(status: i32) -> bool { status & 0x7f != 0 && status & 0x7f != 0x7f }
+pub fn wtermsig(status: i32) -> usize { (status & 0x7f) as usize }
+pub fn wcoredump(status: i32) -> bool { status & 0x80 != 0 }
+pub fn wifstopped(status: i32) -> bool { status & 0xff == 0x7f }
+pub fn wstopsig(status: i32) -> usize { ((status >> 8) & 0xff) as usize }
+pub fn wifcontinued(status: i32) -> bool { status == 0xffff }
+pub fn thread_create(entry: usize, arg: usize) -> SysResult { sys_thread_create(entry, arg) }
+pub fn gettid() -> isize { sys_gettid() }
+pub fn waittid(tid: usize, exit_code: &mut i32) -> SysResult { sys_waittid(tid, exit_code as *mut _) }
+/// The waiters of this mutex yield the processor until it is released.
+pub fn mutex_create() -> SysResult { sys_mutex_create(false) }
+/// The waiters of this mutex sleep until it is released.
+pub fn mutex_blocking_create() -> SysResult { sys_mutex_create(true) }
+/// Locking is retried after a signal handler has run.
+pub fn mutex_lock(mutex_id: usize) -> SysResult<()> {
+    loop {
+        match sys_mutex_lock(mutex_id) {
+            Err(Errno::EINTR) => continue,
+            result => return result.map(|_| ()),
+        }
+    }
+}
+pub fn mutex_unlock(mutex_id: usize) -> SysResult<()> { sys_mutex_unlock(mutex_id).map(|_| ()) }
+pub fn semaphore_create(res_count: usize) -> SysResult { sys_semaphore_create(res_count) }
+pub fn semaphore_up(sem_id: usize) -> SysResult<()> { sys_semaphore_up(sem_id).map(|_| ()) }
+pub fn semaphore_down(sem_id: usize) -> SysResult<()> {
+    loop {
+        match sys_semaphore_down(sem_id) {
+            Err(Errno::EINTR) => continue,
+            result => return result.map(|_| ()),
+        }
+    }
+}
+pub fn condvar_create() -> SysResult { sys_condvar_create() }
+pub fn condvar_signal(condvar_id: usize) -> SysResult<()> { sys_condvar_signal(condvar_id).map(|_| ()) }
+/// May return without being signaled, so check the condition in a loop.
+pub fn condvar_wait(condvar_id: usize, mutex_id: usize) -> SysResult<()> {
+    match sys_condvar_wait(condvar_id, mutex_id) {
+        // the mutex was left unlocked, take it as a spurious wakeup
+        Err(Errno::EINTR) => mutex_lock(mutex_id),
+        result => result.map(|_| ()),
+    }
+}
+pub fn kill(pid: usize, signum: usize) -> SysResult<()> { sys_kill(pid as isize, signum).map(|_| ()) }
+/// Send a signal to every process in the process group `pgid`.
+pub fn killpg(pgid: usize, signum: usize) -> SysResult<()> { sys_kill(-(pgid as isize), signum).map(|_| ()) }
+/// `pid` 0 is the caller, `pgid` 0 a new group led by the process.
+pub fn setpgid(pid: usize, pgid: usize) -> SysResult<()> { sys_setpgid(pid, pgid).map(|_| ()) }
+pub fn getpgid(pid: usize) -> SysResult { sys_getpgid(pid) }
+/// Lead a new session and process group, returns the session id.
+pub fn setsid() -> SysResult { sys_setsid() }
+
+const TIOCGPGRP: usize = 0x540f;
+const TIOCSPGRP: usize = 0x5410;
+
+/// Foreground process group of the console, only processes in it can read from it.
+pub fn tcgetpgrp(fd: usize) -> SysResult {
+    let mut pgid: i32 = 0;
+    sys_ioctl(fd, TIOCGPGRP, &mut pgid as *mut i32 as usize)?;
+    Ok(pgid as usize)
+}
+/// Ctrl-C and Ctrl-Z on the console send SIGINT and SIGTSTP to the foreground group.
+pub fn tcsetpgrp(fd: usize, pgid: usize) -> SysResult<()> {
+    let pgid = pgid as i32;
+    sys_ioctl(fd, TIOCSPGRP, &pgid as *const i32 as usize).map(|_| ())
+}
+pub fn sigaction(
+    signum: usize,
+    action: Option<&SignalAction>,
+    old_action: Option<&mut SignalAction>,
+) -> SysResult<()> {
+    sys_sigaction(
+        signum,
+        action.map_or(core::ptr::null(), |action| action as *const _),
+        old_action.map_or(core::ptr::null_mut(), |action| action as *mut _),
+    ).map(|_| ())
+}
+pub fn sigprocmask(
+    how: SigMaskHow,
+    set: Option<SignalFlags>,
+    old_set: Option<&mut SignalFlags>,
+) -> SysResult<()> {
+    let set = set.map(|set| set.bits());
+    let mut old_bits = 0u32;
+    sys_sigprocmask(
+        how as usize,
+        set.as_ref().map_or(core::ptr::null(), |bits| bits as *const _),
+        if old_set.is_some() { &mut old_bits as *mut _ } else { core::ptr::null_mut() },
+    )?;
+    if let Some(old_set) = old_set {
+        *old_set = SignalFlags::from_bits_truncate(old_bits);
+    }
+    Ok(())
+}
+pub fn nanosleep(req: &TimeSpec, rem: Option<&mut TimeSpec>) -> SysResult<()> {
+    sys_nanosleep(req, rem.map_or(core::ptr::null_mut(), |rem| rem as *mut _)).map(|_| ())
+}
+/// `rem` is left alone if `req` is a deadline.
+pub fn clock_nanosleep(clock_id: usize, flags: usize, req: &TimeSpec, rem: Option<&mut TimeSpec>) -> SysResult<()> {
+    sys_clock_nanosleep(clock_id, flags, req, rem.map_or(core::ptr::null_mut(), |rem| rem as *mut _)).map(|_| ())
+}
 pub fn sleep(period_ms: usize) {
-    let start = sys_get_time();
-    while sys_get_time() < start + period_ms as isize {
-        sys_yield();
+    let mut req = TimeSpec::from_ms(period_ms);
+    let mut rem = TimeSpec::default();
+    // keep sleeping for the rest of the time if a signal handler ran
+    while nanosleep(&req, Some(&mut rem)) == Err(Errno::EINTR) {
+        req = rem;
     }
 }
\ No newline at end of file
@@ -0,0 +1,184 @@
+pub const MAX_SIG: usize = 31;
+
+/// Special values of `SignalAction::handler`.
+pub const SIG_DFL: usize = 0;
+pub const SIG_IGN: usize = 1;
+
+bitflags! {
+    /// Bit `1 << signum` represents signal `signum`, numbered as in Linux.
+    pub struct SignalFlags: u32 {
+        const SIGHUP = 1 << 1;
+        const SIGINT = 1 << 2;
+        const SIGQUIT = 1 << 3;
+        const SIGILL = 1 << 4;
+        const SIGTRAP = 1 << 5;
+        const SIGABRT = 1 << 6;
+        const SIGBUS = 1 << 7;
+        const SIGFPE = 1 << 8;
+        const SIGKILL = 1 << 9;
+        const SIGUSR1 = 1 << 10;
+        const SIGSEGV = 1 << 11;
+        const SIGUSR2 = 1 << 12;
+        const SIGPIPE = 1 << 13;
+        const SIGALRM = 1 << 14;
+        const SIGTERM = 1 << 15;
+        const SIGSTKFLT = 1 << 16;
+        const SIGCHLD = 1 << 17;
+        const SIGCONT = 1 << 18;
+        const SIGSTOP = 1 << 19;
+        const SIGTSTP = 1 << 20;
+        const SIGTTIN = 1 << 21;
+        const SIGTTOU = 1 << 22;
+        const SIGURG = 1 << 23;
+        const SIGXCPU = 1 << 24;
+        const SIGXFSZ = 1 << 25;
+        const SIGVTALRM = 1 << 26;
+        const SIGPROF = 1 << 27;
+        const SIGWINCH = 1 << 28;
+        const SIGIO = 1 << 29;
+        const SIGPWR = 1 << 30;
+        const SIGSYS = 1 << 31;
+    }
+}
+
+pub const SIGHUP: usize = 1;
+pub const SIGINT: usize = 2;
+pub const SIGQUIT: usize = 3;
+pub const SIGILL: usize = 4;
+pub const SIGTRAP: usize = 5;
+pub const SIGABRT: usize = 6;
+pub const SIGBUS: usize = 7;
+pub const SIGFPE: usize = 8;
+pub const SIGKILL: usize = 9;
+pub const SIGUSR1: usize = 10;
+pub const SIGSEGV: usize = 11;
+pub const SIGUSR2: usize = 12;
+pub const SIGPIPE: usize = 13;
+pub const SIGALRM: usize = 14;
+pub const SIGTERM: usize = 15;
+pub const SIGSTKFLT: usize = 16;
+pub const SIGCHLD: usize = 17;
+pub const SIGCONT: usize = 18;
+pub const SIGSTOP: usize = 19;
+pub const SIGTSTP: usize = 20;
+pub const SIGTTIN: usize = 21;
+pub const SIGTTOU: usize = 22;
+pub const SIGURG: usize = 23;
+pub const SIGXCPU: usize = 24;
+pub const SIGXFSZ: usize = 25;
+pub const SIGVTALRM: usize = 26;
+pub const SIGPROF: usize = 27;
+pub const SIGWINCH: usize = 28;
+pub const SIGIO: usize = 29;
+pub const SIGPWR: usize = 30;
+pub const SIGSYS: usize = 31;
+
+impl SignalFlags {
+    pub fn from_signum(signum: usize) -> Option<Self> {
+        if signum == 0 || signum > MAX_SIG {
+            None
+        } else {
+            Self::from_bits(1 << signum)
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum DefaultAction {
+    Terminate,
+    CoreDump,
+    Ignore,
+    Stop,
+    Continue,
+}
+
+/// What the kernel does with a signal whose handler is `SIG_DFL`, indexed by signal number.
+pub static DEFAULT_ACTIONS: [DefaultAction; MAX_SIG + 1] = [
+    // 0 is not a signal
+    DefaultAction::Ignore,
+    DefaultAction::Terminate,   // SIGHUP
+    DefaultAction::Terminate,   // SIGINT
+    DefaultAction::CoreDump,    // SIGQUIT
+    DefaultAction::CoreDump,    // SIGILL
+    DefaultAction::CoreDump,    // SIGTRAP
+    DefaultAction::CoreDump,    // SIGABRT
+    DefaultAction::CoreDump,    // SIGBUS
+    DefaultAction::CoreDump,    // SIGFPE
+    DefaultAction::Terminate,   // SIGKILL
+    DefaultAction::Terminate,   // SIGUSR1
+    DefaultAction::CoreDump,    // SIGSEGV
+    DefaultAction::Terminate,   // SIGUSR2
+    DefaultAction::Terminate,   // SIGPIPE
+    DefaultAction::Terminate,   // SIGALRM
+    DefaultAction::Terminate,   // SIGTERM
+    DefaultAction::Terminate,   // SIGSTKFLT
+    DefaultAction::Ignore,      // SIGCHLD
+    DefaultAction::Continue,    // SIGCONT
+    DefaultAction::Stop,        // SIGSTOP
+    DefaultAction::Stop,        // SIGTSTP
+    DefaultAction::Stop,        // SIGTTIN
+    DefaultAction::Stop,        // SIGTTOU
+    DefaultAction::Ignore,      // SIGURG
+    DefaultAction::CoreDump,    // SIGXCPU
+    DefaultAction::CoreDump,    // SIGXFSZ
+    DefaultAction::Terminate,   // SIGVTALRM
+    DefaultAction::Terminate,   // SIGPROF
+    DefaultAction::Ignore,      // SIGWINCH
+    DefaultAction::Terminate,   // SIGIO
+    DefaultAction::Terminate,   // SIGPWR
+    DefaultAction::CoreDump,    // SIGSYS
+];
+
+pub type SignalHandler = extern "C" fn(signum: usize);
+
+/// Same layout as `SignalAction` in the kernel.
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+pub struct SignalAction {
+    pub handler: usize,
+    pub restorer: usize,
+    pub mask: SignalFlags,
+}
+
+impl SignalAction {
+    /// Catch the signal with `handler`, blocking `mask` (and the signal itself) meanwhile.
+    pub fn new(handler: SignalHandler, mask: SignalFlags) -> Self {
+        Self {
+            handler: handler as usize,
+            restorer: __sigreturn as usize,
+            mask,
+        }
+    }
+    pub fn ignore() -> Self {
+        Self {
+            handler: SIG_IGN,
+            restorer: 0,
+            mask: SignalFlags::empty(),
+        }
+    }
+}
+
+impl Default for SignalAction {
+    fn default() -> Self {
+        Self {
+            handler: SIG_DFL,
+            restorer: 0,
+            mask: SignalFlags::empty(),
+        }
+    }
+}
+
+#[repr(usize)]
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum SigMaskHow {
+    Block = 0,
+    Unblock = 1,
+    SetMask = 2,
+}
+
+// Handlers return here with sp pointing at the signal frame.
+global_asm!(include_str!("sigreturn.S"));
+
+extern "C" {
+    fn __sigreturn();
+}
@@ -1,23 +1,65 @@
 const SYSCALL_DUP: usize = 24;
+const SYSCALL_IOCTL: usize = 29;
 const SYSCALL_OPEN: usize = 56;
 const SYSCALL_CLOSE: usize = 57;
 const SYSCALL_PIPE: usize = 59;
 const SYSCALL_READ: usize = 63;
 const SYSCALL_WRITE: usize = 64;
 const SYSCALL_EXIT: usize = 93;
+const SYSCALL_NANOSLEEP: usize = 101;
+const SYSCALL_CLOCK_NANOSLEEP: usize = 115;
 const SYSCALL_YIELD: usize = 124;
+const SYSCALL_KILL: usize = 129;
+const SYSCALL_SIGACTION: usize = 134;
+const SYSCALL_SIGPROCMASK: usize = 135;
+const SYSCALL_SET_PRIORITY: usize = 140;
+const SYSCALL_TIMES: usize = 153;
+const SYSCALL_SETPGID: usize = 154;
+const SYSCALL_GETPGID: usize = 155;
+const SYSCALL_SETSID: usize = 157;
+const SYSCALL_GETRLIMIT: usize = 163;
+const SYSCALL_SETRLIMIT: usize = 164;
+const SYSCALL_GETRUSAGE: usize = 165;
 const SYSCALL_GET_TIME: usize = 169;
 const SYSCALL_GETPID: usize = 172;
+const SYSCALL_BRK: usize = 214;
+const SYSCALL_MUNMAP: usize = 215;
 const SYSCALL_FORK: usize = 220;
-const SYSCALL_EXEC: usize = 221;
+const SYSCALL_EXECVE: usize = 221;
+const SYSCALL_MMAP: usize = 222;
 const SYSCALL_WAITPID: usize = 260;
+const SYSCALL_SPAWN: usize = 400;
+const SYSCALL_THREAD_CREATE: usize = 1000;
+const SYSCALL_GETTID: usize = 1001;
+const SYSCALL_WAITTID: usize = 1002;
+const SYSCALL_MUTEX_CREATE: usize = 1010;
+const SYSCALL_MUTEX_LOCK: usize = 1011;
+const SYSCALL_MUTEX_UNLOCK: usize = 1012;
+const SYSCALL_SEMAPHORE_CREATE: usize = 1020;
+const SYSCALL_SEMAPHORE_UP: usize = 1021;
+const SYSCALL_SEMAPHORE_DOWN: usize = 1022;
+const SYSCALL_CONDVAR_CREATE: usize = 1030;
+const SYSCALL_CONDVAR_SIGNAL: usize = 1031;
+const SYSCALL_CONDVAR_WAIT: usize = 1032;
+const SYSCALL_SBRK: usize = 1040;
+const SYSCALL_TASK_LIST: usize = 1050;
+const SYSCALL_PERSONALITY: usize = 1060;
+const SYSCALL_SYSCALL_STATS: usize = 1070;
 
-fn syscall(id: usize, args: [usize; 3]) -> isize {
+use crate::errno::Errno;
+use crate::signal::SignalAction;
+use crate::{TimeSpec, RLimit, Tms, RUsage, TaskInfo, SpawnAction, SyscallStat};
+
+pub type SysResult<T = usize> = Result<T, Errno>;
+
+/// System calls take up to six arguments in a0..a5, the id in a7.
+fn syscall6(id: usize, args: [usize; 6]) -> isize {
     let mut ret: isize;
     unsafe {
         llvm_asm!("ecall"
             : "={x10}" (ret)
-            : "{x10}" (args[0]), "{x11}" (args[1]), "{x12}" (args[2]), "{x17}" (id)
+            : "{x10}" (args[0]), "{x11}" (args[1]), "{x12}" (args[2]),
+              "{x13}" (args[3]), "{x14}" (args[4]), "{x15}" (args[5]), "{x17}" (id)
             : "memory"
             : "volatile"
         );
@@ -25,28 +67,45 @@ fn syscall(id: usize, args: [usize; 3]) -> isize {
     ret
 }
 
-pub fn sys_dup(fd: usize) -> isize {
-    syscall(SYSCALL_DUP, [fd, 0, 0])
+fn syscall(id: usize, args: [usize; 3]) -> isize {
+    syscall6(id, [args[0], args[1], args[2], 0, 0, 0])
+}
+
+/// Split a raw return value into a result, negative values in `[-4095, -1]` being errors.
+fn decode(ret: isize) -> SysResult {
+    if (-4095..0).contains(&ret) {
+        Err(Errno::from_code(-ret))
+    } else {
+        Ok(ret as usize)
+    }
+}
+
+pub fn sys_dup(fd: usize) -> SysResult {
+    decode(syscall(SYSCALL_DUP, [fd, 0, 0]))
+}
+
+pub fn sys_ioctl(fd: usize, cmd: usize, arg: usize) -> SysResult {
+    decode(syscall(SYSCALL_IOCTL, [fd, cmd, arg]))
 }
 
-pub fn sys_open(path: &str, flags: u32) -> isize {
-    syscall(SYSCALL_OPEN, [path.as_ptr() as usize, flags as usize, 0])
+pub fn sys_open(path: &str, flags: u32) -> SysResult {
+    decode(syscall(SYSCALL_OPEN, [path.as_ptr() as usize, flags as usize, 0]))
 }
 
-pub fn sys_close(fd: usize) -> isize {
-    syscall(SYSCALL_CLOSE, [fd, 0, 0])
+pub fn sys_close(fd: usize) -> SysResult {
+    decode(syscall(SYSCALL_CLOSE, [fd, 0, 0]))
 }
 
-pub fn sys_pipe(pipe: &mut [usize]) -> isize {
-    syscall(SYSCALL_PIPE, [pipe.as_mut_ptr() as usize, 0, 0])
+pub fn sys_pipe(pipe: &mut [usize]) -> SysResult {
+    decode(syscall(SYSCALL_PIPE, [pipe.as_mut_ptr() as usize, 0, 0]))
 }
 
-pub fn sys_read(fd: usize, buffer: &mut [u8]) -> isize {
-    syscall(SYSCALL_READ, [fd, buffer.as_mut_ptr() as usize, buffer.len()])
+pub fn sys_read(fd: usize, buffer: &mut [u8]) -> SysResult {
+    decode(syscall(SYSCALL_READ, [fd, buffer.as_mut_ptr() as usize, buffer.len()]))
 }
 
-pub fn sys_write(fd: usize, buffer: &[u8]) -> isize {
-    syscall(SYSCALL_WRITE, [fd, buffer.as_ptr() as usize, buffer.len()])
+pub fn sys_write(fd: usize, buffer: &[u8]) -> SysResult {
+    decode(syscall(SYSCALL_WRITE, [fd, buffer.as_ptr() as usize, buffer.len()]))
 }
 
 pub fn sys_exit(exit_code: i32) -> ! {
@@ -54,10 +113,38 @@ pub fn sys_exit(exit_code: i32) -> ! {
     panic!("sys_exit never returns!");
 }
 
+pub fn sys_nanosleep(req: &TimeSpec, rem: *mut TimeSpec) -> SysResult {
+    decode(syscall(SYSCALL_NANOSLEEP, [req as *const _ as usize, rem as usize, 0]))
+}
+
+pub fn sys_clock_nanosleep(clock_id: usize, flags: usize, req: &TimeSpec, rem: *mut TimeSpec) -> SysResult {
+    decode(syscall6(SYSCALL_CLOCK_NANOSLEEP, [clock_id, flags, req as *const _ as usize, rem as usize, 0, 0]))
+}
+
 pub fn sys_yield() -> isize {
     syscall(SYSCALL_YIELD, [0, 0, 0])
 }
 
+pub fn sys_set_priority(prio: isize) -> SysResult {
+    decode(syscall(SYSCALL_SET_PRIORITY, [prio as usize, 0, 0]))
+}
+
+pub fn sys_times(tms: *mut Tms) -> SysResult {
+    decode(syscall(SYSCALL_TIMES, [tms as usize, 0, 0]))
+}
+
+pub fn sys_getrusage(who: isize, usage: &mut RUsage) -> SysResult {
+    decode(syscall(SYSCALL_GETRUSAGE, [who as usize, usage as *mut _ as usize, 0]))
+}
+
+pub fn sys_getrlimit(resource: usize, rlim: &mut RLimit) -> SysResult {
+    decode(syscall(SYSCALL_GETRLIMIT, [resource, rlim as *mut _ as usize, 0]))
+}
+
+pub fn sys_setrlimit(resource: usize, rlim: &RLimit) -> SysResult {
+    decode(syscall(SYSCALL_SETRLIMIT, [resource, rlim as *const _ as usize, 0]))
+}
+
 pub fn sys_get_time() -> isize {
     syscall(SYSCALL_GET_TIME, [0, 0, 0])
 }
@@ -66,14 +153,128 @@ pub fn sys_getpid() -> isize {
     syscall(SYSCALL_GETPID, [0, 0, 0])
 }
 
-pub fn sys_fork() -> isize {
-    syscall(SYSCALL_FORK, [0, 0, 0])
+pub fn sys_fork() -> SysResult {
+    decode(syscall(SYSCALL_FORK, [0, 0, 0]))
+}
+
+pub fn sys_execve(path: &str, args: &[*const u8], envs: &[*const u8]) -> SysResult {
+    decode(syscall(
+        SYSCALL_EXECVE,
+        [path.as_ptr() as usize, args.as_ptr() as usize, envs.as_ptr() as usize],
+    ))
+}
+
+pub fn sys_spawn(path: &str, args: &[*const u8], file_actions: &[SpawnAction], envs: &[*const u8]) -> SysResult {
+    decode(syscall6(
+        SYSCALL_SPAWN,
+        [path.as_ptr() as usize, args.as_ptr() as usize, file_actions.as_ptr() as usize, envs.as_ptr() as usize, 0, 0],
+    ))
+}
+
+pub fn sys_mmap(addr: usize, len: usize, prot: usize) -> SysResult {
+    decode(syscall(SYSCALL_MMAP, [addr, len, prot]))
+}
+
+pub fn sys_munmap(addr: usize, len: usize) -> SysResult {
+    decode(syscall(SYSCALL_MUNMAP, [addr, len, 0]))
+}
+
+pub fn sys_brk(addr: usize) -> SysResult {
+    decode(syscall(SYSCALL_BRK, [addr, 0, 0]))
+}
+
+pub fn sys_sbrk(increment: isize) -> SysResult {
+    decode(syscall(SYSCALL_SBRK, [increment as usize, 0, 0]))
+}
+
+pub fn sys_task_list(infos: &mut [TaskInfo]) -> SysResult {
+    decode(syscall(SYSCALL_TASK_LIST, [infos.as_mut_ptr() as usize, infos.len(), 0]))
 }
 
-pub fn sys_exec(path: &str, args: &[*const u8]) -> isize {
-    syscall(SYSCALL_EXEC, [path.as_ptr() as usize, args.as_ptr() as usize, 0])
+pub fn sys_syscall_stats(stats: &mut [SyscallStat]) -> SysResult {
+    decode(syscall(SYSCALL_SYSCALL_STATS, [stats.as_mut_ptr() as usize, stats.len(), 0]))
 }
 
-pub fn sys_waitpid(pid: isize, exit_code: *mut i32) -> isize {
-    syscall(SYSCALL_WAITPID, [pid as usize, exit_code as usize, 0])
-}
\ No newline at end of file
+pub fn sys_personality(persona: usize) -> SysResult {
+    decode(syscall(SYSCALL_PERSONALITY, [persona, 0, 0]))
+}
+
+pub fn sys_waitpid(pid: isize, exit_code: *mut i32, options: u32) -> SysResult {
+    decode(syscall(SYSCALL_WAITPID, [pid as usize, exit_code as usize, options as usize]))
+}
+
+pub fn sys_thread_create(entry: usize, arg: usize) -> SysResult {
+    decode(syscall(SYSCALL_THREAD_CREATE, [entry, arg, 0]))
+}
+
+pub fn sys_gettid() -> isize {
+    syscall(SYSCALL_GETTID, [0, 0, 0])
+}
+
+pub fn sys_waittid(tid: usize, exit_code: *mut i32) -> SysResult {
+    decode(syscall(SYSCALL_WAITTID, [tid, exit_code as usize, 0]))
+}
+
+pub fn sys_mutex_create(blocking: bool) -> SysResult {
+    decode(syscall(SYSCALL_MUTEX_CREATE, [blocking as usize, 0, 0]))
+}
+
+pub fn sys_mutex_lock(mutex_id: usize) -> SysResult {
+    decode(syscall(SYSCALL_MUTEX_LOCK, [mutex_id, 0, 0]))
+}
+
+pub fn sys_mutex_unlock(mutex_id: usize) -> SysResult {
+    decode(syscall(SYSCALL_MUTEX_UNLOCK, [mutex_id, 0, 0]))
+}
+
+pub fn sys_semaphore_create(res_count: usize) -> SysResult {
+    decode(syscall(SYSCALL_SEMAPHORE_CREATE, [res_count, 0, 0]))
+}
+
+pub fn sys_semaphore_up(sem_id: usize) -> SysResult {
+    decode(syscall(SYSCALL_SEMAPHORE_UP, [sem_id, 0, 0]))
+}
+
+pub fn sys_semaphore_down(sem_id: usize) -> SysResult {
+    decode(syscall(SYSCALL_SEMAPHORE_DOWN, [sem_id, 0, 0]))
+}
+
+pub fn sys_condvar_create() -> SysResult {
+    decode(syscall(SYSCALL_CONDVAR_CREATE, [0, 0, 0]))
+}
+
+pub fn sys_condvar_signal(condvar_id: usize) -> SysResult {
+    decode(syscall(SYSCALL_CONDVAR_SIGNAL, [condvar_id, 0, 0]))
+}
+
+pub fn sys_condvar_wait(condvar_id: usize, mutex_id: usize) -> SysResult {
+    decode(syscall(SYSCALL_CONDVAR_WAIT, [condvar_id, mutex_id, 0]))
+}
+
+pub fn sys_kill(pid: isize, signum: usize) -> SysResult {
+    decode(syscall(SYSCALL_KILL, [pid as usize, signum, 0]))
+}
+
+pub fn sys_setpgid(pid: usize, pgid: usize) -> SysResult {
+    decode(syscall(SYSCALL_SETPGID, [pid, pgid, 0]))
+}
+
+pub fn sys_getpgid(pid: usize) -> SysResult {
+    decode(syscall(SYSCALL_GETPGID, [pid, 0, 0]))
+}
+
+pub fn sys_setsid() -> SysResult {
+    decode(syscall(SYSCALL_SETSID, [0, 0, 0]))
+}
+
+pub fn sys_sigaction(
+    signum: usize,
+    action: *const SignalAction,
+    old_action: *mut SignalAction,
+) -> SysResult {
+    decode(syscall(SYSCALL_SIGACTION, [signum, action as usize, old_action as usize]))
+}
+
+pub fn sys_sigprocmask(how: usize, set: *const u32, old_set: *mut u32) -> SysResult {
+    decode(syscall(SYSCALL_SIGPROCMASK, [how, set as usize, old_set as usize]))
+}
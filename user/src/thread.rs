@@ -0,0 +1,48 @@
+use alloc::boxed::Box;
+use crate::{exit, thread_create, waittid, SysResult};
+
+type ThreadMain = Box<dyn FnOnce() -> i32 + Send + 'static>;
+
+/// A thread of the current process, joining it gives its exit code.
+pub struct JoinHandle {
+    tid: usize,
+}
+
+impl JoinHandle {
+    pub fn tid(&self) -> usize {
+        self.tid
+    }
+    /// Block until the thread exits and return its exit code.
+    pub fn join(self) -> SysResult<i32> {
+        let mut exit_code: i32 = 0;
+        waittid(self.tid, &mut exit_code)?;
+        Ok(exit_code)
+    }
+}
+
+/// Run `f` in a new thread sharing the address space of the current process.
+/// The thread exits with the value returned by `f`.
+pub fn spawn<F>(f: F) -> SysResult<JoinHandle>
+where
+    F: FnOnce() -> i32 + Send + 'static,
+{
+    let main: Box<ThreadMain> = Box::new(Box::new(f));
+    let arg = Box::into_raw(main) as usize;
+    match thread_create(thread_start as usize, arg) {
+        Ok(tid) => Ok(JoinHandle { tid }),
+        Err(err) => {
+            drop(unsafe { Box::from_raw(arg as *mut ThreadMain) });
+            Err(err)
+        }
+    }
+}
+
+pub fn gettid() -> usize {
+    crate::gettid() as usize
+}
+
+/// A new thread starts here with the boxed closure in a0, there is nowhere to return to.
+extern "C" fn thread_start(arg: usize) -> ! {
+    let main = unsafe { Box::from_raw(arg as *mut ThreadMain) };
+    exit(main());
+}